@@ -241,6 +241,8 @@
               partitionType = "count";
               cargoNextestCommand = "RUST_LOG=debug cargo nextest";
               cargoNextestExtraArgs = "--workspace";
+              # For the `git:` source tests.
+              nativeBuildInputs = commonArgs.nativeBuildInputs ++ [ pkgs.git ];
               env = {
                 RUST_LOG = "debug";
               };
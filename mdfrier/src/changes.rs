@@ -0,0 +1,125 @@
+//! Incremental reparsing, see [`MdFrier::reparse`](crate::MdFrier::reparse).
+
+use std::ops::Range;
+
+use tree_sitter::{InputEdit, Point, Tree};
+
+/// What changed between two parses, see [`MdFrier::reparse`](crate::MdFrier::reparse).
+///
+/// The default is no changes.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Changes {
+    /// The edited byte range of the previous text.
+    pub old: Range<usize>,
+    /// The edited byte range of the new text.
+    pub new: Range<usize>,
+    /// Byte ranges of the new text whose syntax tree changed, which may be outside of `new`, e.g.
+    /// when a paragraph becomes a list item.
+    pub changed: Vec<Range<usize>>,
+}
+
+impl Changes {
+    pub(crate) fn new(edit: &InputEdit, old_tree: &Tree, new_tree: &Tree) -> Self {
+        Changes {
+            old: edit.start_byte..edit.old_end_byte,
+            new: edit.start_byte..edit.new_end_byte,
+            changed: old_tree
+                .changed_ranges(new_tree)
+                .map(|range| range.start_byte..range.end_byte)
+                .collect(),
+        }
+    }
+
+    /// Map a byte range of the previous text to the new text.
+    ///
+    /// Returns `None` if the range was edited, or its syntax changed.
+    pub fn map_range(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let mapped = if range.end <= self.old.start {
+            range
+        } else if range.start >= self.old.end {
+            (range.start - self.old.end + self.new.end)..(range.end - self.old.end + self.new.end)
+        } else {
+            return None;
+        };
+        let overlaps =
+            |changed: &Range<usize>| changed.start < mapped.end && mapped.start < changed.end;
+        if self.changed.iter().any(overlaps) {
+            None
+        } else {
+            Some(mapped)
+        }
+    }
+}
+
+/// The single edit from `old` to `new`, between their common prefix and suffix.
+pub(crate) fn text_edit(old: &str, new: &str) -> InputEdit {
+    let prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(old.len().min(new.len()) - prefix)
+        .take_while(|(old, new)| old == new)
+        .count();
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old.len() - suffix,
+        new_end_byte: new.len() - suffix,
+        start_position: point(old, prefix),
+        old_end_position: point(old, old.len() - suffix),
+        new_end_position: point(new, new.len() - suffix),
+    }
+}
+
+fn point(text: &str, byte: usize) -> Point {
+    let before = text.as_bytes().get(..byte).unwrap_or_default();
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or_default();
+    Point {
+        row: before.iter().filter(|b| **b == b'\n').count(),
+        column: byte - line_start,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_between_prefix_and_suffix() {
+        let edit = text_edit("# One\n\nold\n", "# One\n\nnew text\n");
+        assert_eq!(edit.start_byte, 7);
+        assert_eq!(edit.old_end_byte, 10);
+        assert_eq!(edit.new_end_byte, 15);
+        assert_eq!(edit.start_position, Point { row: 2, column: 0 });
+        assert_eq!(edit.new_end_position, Point { row: 2, column: 8 });
+    }
+
+    #[test]
+    fn map_range() {
+        let changes = Changes {
+            old: 10..12,
+            new: 10..15,
+            changed: vec![20..22, 40..41],
+        };
+        assert_eq!(changes.map_range(0..10), Some(0..10));
+        assert_eq!(changes.map_range(5..11), None);
+        assert_eq!(changes.map_range(12..14), Some(15..17));
+        assert_eq!(changes.map_range(16..20), None);
+        assert_eq!(changes.map_range(24..30), Some(27..33));
+    }
+
+    #[test]
+    fn default_maps_everything() {
+        let changes = Changes::default();
+        assert_eq!(changes.map_range(0..0), Some(0..0));
+        assert_eq!(changes.map_range(3..8), Some(3..8));
+    }
+}
@@ -0,0 +1,288 @@
+//! Block-level diffs between two versions of a document, see [`MdFrier::diff`](crate::MdFrier::diff).
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    Line, Mapper, MdDocument,
+    markdown::{MdSection, Modifier},
+};
+
+/// Above this many compared pairs of blocks, the differing middle of the documents is not aligned,
+/// but removed and added as a whole.
+const MAX_COMPARISONS: usize = 1 << 22;
+
+/// How a block differs from the old version of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockChange {
+    /// The block is only in the new version.
+    Added,
+    /// The block is only in the old version.
+    Removed,
+}
+
+/// The new version of a document, with the blocks that were removed from the old version
+/// inserted where they were.
+///
+/// Removed blocks have their byte ranges after the end of the new text, so that each [`Line`]
+/// can be looked up with [`MdDiff::change`].
+#[derive(Debug)]
+pub struct MdDiff {
+    document: MdDocument,
+    changes: HashMap<Range<usize>, BlockChange>,
+}
+
+impl MdDiff {
+    pub(crate) fn new(old: MdDocument, new: &MdDocument) -> Self {
+        let offset = new.text_len;
+        let old = old.sections;
+        let new = &new.sections;
+
+        let prefix = old
+            .iter()
+            .zip(new)
+            .take_while(|(old, new)| same_block(old, new))
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(old, new)| same_block(old, new))
+            .count();
+        let old_middle = &old[prefix..old.len() - suffix];
+        let new_middle = &new[prefix..new.len() - suffix];
+
+        let mut merged = Merged {
+            sections: new[..prefix].to_vec(),
+            changes: HashMap::new(),
+            offset,
+        };
+        let (n, m) = (old_middle.len(), new_middle.len());
+        if n.saturating_mul(m) > MAX_COMPARISONS {
+            old_middle.iter().for_each(|section| merged.remove(section));
+            new_middle.iter().for_each(|section| merged.add(section));
+        } else {
+            // The length of the longest common subsequence of `old_middle[i..]` and
+            // `new_middle[j..]`, at `i * (m + 1) + j`.
+            let mut lcs = vec![0_u32; (n + 1) * (m + 1)];
+            for i in (0..n).rev() {
+                for j in (0..m).rev() {
+                    lcs[i * (m + 1) + j] = if same_block(&old_middle[i], &new_middle[j]) {
+                        lcs[(i + 1) * (m + 1) + j + 1] + 1
+                    } else {
+                        lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+                    };
+                }
+            }
+            // Removed blocks come before the blocks that were added in their place.
+            let (mut i, mut j) = (0, 0);
+            while i < n || j < m {
+                if i < n && j < m && same_block(&old_middle[i], &new_middle[j]) {
+                    merged.sections.push(new_middle[j].clone());
+                    i += 1;
+                    j += 1;
+                } else if j == m
+                    || (i < n && lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1])
+                {
+                    merged.remove(&old_middle[i]);
+                    i += 1;
+                } else {
+                    merged.add(&new_middle[j]);
+                    j += 1;
+                }
+            }
+        }
+        let Merged {
+            mut sections,
+            changes,
+            ..
+        } = merged;
+        sections.extend_from_slice(&new[new.len() - suffix..]);
+
+        MdDiff {
+            document: MdDocument {
+                sections,
+                text_len: offset,
+            },
+            changes,
+        }
+    }
+
+    /// Wrap the merged document to `width`, like [`MdDocument::layout`], with the lines of
+    /// removed blocks struck through.
+    pub fn layout<'a, M: Mapper>(
+        &'a self,
+        width: u16,
+        mapper: &'a M,
+    ) -> impl Iterator<Item = Line> + 'a {
+        self.document.layout(width, mapper).map(|mut line| {
+            if self.change(&line.source) == Some(BlockChange::Removed) {
+                for span in &mut line.spans {
+                    span.modifiers |= Modifier::Strikethrough;
+                }
+            }
+            line
+        })
+    }
+
+    /// The change of the block at, or around, the [`Line::source`] range, if any.
+    ///
+    /// Blank lines between blocks are never changed.
+    pub fn change(&self, source: &Range<usize>) -> Option<BlockChange> {
+        self.changes
+            .get(source)
+            .or_else(|| {
+                // A row of a table, or a line of code.
+                if source.is_empty() {
+                    return None;
+                }
+                self.changes
+                    .iter()
+                    .find(|(block, _)| block.start <= source.start && source.end <= block.end)
+                    .map(|(_, change)| change)
+            })
+            .copied()
+    }
+
+    /// The changed blocks by their [`Line::source`] range.
+    pub fn changes(&self) -> &HashMap<Range<usize>, BlockChange> {
+        &self.changes
+    }
+}
+
+// The sections of the merged document, in order.
+struct Merged {
+    sections: Vec<MdSection>,
+    changes: HashMap<Range<usize>, BlockChange>,
+    // The length of the new text, which removed sections are moved after.
+    offset: usize,
+}
+
+impl Merged {
+    fn add(&mut self, section: &MdSection) {
+        self.push(section.clone(), BlockChange::Added);
+    }
+
+    fn remove(&mut self, section: &MdSection) {
+        let mut section = section.clone();
+        section.source = section.source.start + self.offset..section.source.end + self.offset;
+        for (part, _) in &mut section.parts {
+            *part = part.start + self.offset..part.end + self.offset;
+        }
+        self.push(section, BlockChange::Removed);
+    }
+
+    fn push(&mut self, section: MdSection, change: BlockChange) {
+        if !section.content.is_blank() {
+            self.changes.insert(section.source.clone(), change);
+        }
+        self.sections.push(section);
+    }
+}
+
+fn same_block(old: &MdSection, new: &MdSection) -> bool {
+    old.content == new.content
+        && old.nesting == new.nesting
+        && old.is_list_continuation == new.is_list_continuation
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{BlockChange, DefaultMapper, MdFrier, Modifier};
+
+    // The text of each line, prefixed with `+` if added, `-` if removed, or a space.
+    fn diff(old: &str, new: &str) -> Vec<String> {
+        let mut frier = MdFrier::new().unwrap();
+        let document = frier.parse_document(new).unwrap();
+        let diff = frier.diff(old, &document).unwrap();
+        diff.layout(80, &DefaultMapper)
+            .map(|line| {
+                let marker = match diff.change(&line.source) {
+                    Some(BlockChange::Added) => '+',
+                    Some(BlockChange::Removed) => '-',
+                    None => ' ',
+                };
+                let text: String = line
+                    .spans
+                    .iter()
+                    .map(|span| span.content.as_str())
+                    .collect();
+                format!("{marker}{text}").trim_end().to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unchanged() {
+        let text = "# Title\n\nSome text.\n\n- a\n- b\n";
+        assert!(
+            diff(text, text)
+                .iter()
+                .all(|line| !line.starts_with(['+', '-']))
+        );
+    }
+
+    #[test]
+    fn changed_blocks() {
+        assert_eq!(
+            diff(
+                "# Title\n\nFirst.\n\nSecond.\n\nThird.\n",
+                "# Title\n\nFirst.\n\nSecond, edited.\n\nThird.\n\nFourth.\n"
+            ),
+            vec![
+                " Title",
+                " First.",
+                "",
+                "-Second.",
+                "",
+                "+Second, edited.",
+                "",
+                " Third.",
+                "",
+                "+Fourth.",
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_code_block() {
+        assert_eq!(
+            diff("```\na\nb\n```\n", "```\na\nc\n```\n"),
+            vec!["-a", "-b", "", "+a", "+c"]
+        );
+    }
+
+    #[test]
+    fn moved_blocks() {
+        assert_eq!(
+            diff("A.\n\nB.\n\nC.\n", "B.\n\nC.\n\nA.\n"),
+            vec!["-A.", "", " B.", "", " C.", "", "+A."]
+        );
+    }
+
+    #[test]
+    fn removed_lines_are_struck_through() {
+        let mut frier = MdFrier::new().unwrap();
+        let document = frier.parse_document("Kept.\n").unwrap();
+        let diff = frier.diff("Gone.\n\nKept.\n", &document).unwrap();
+        let lines: Vec<_> = diff.layout(80, &DefaultMapper).collect();
+        assert!(
+            lines[0]
+                .spans
+                .iter()
+                .all(|span| span.modifiers.contains(Modifier::Strikethrough))
+        );
+        // The removed block's range is after the new text.
+        assert!(lines[0].source.start >= "Kept.\n".len());
+        assert!(
+            lines
+                .last()
+                .unwrap()
+                .spans
+                .iter()
+                .all(|span| !span.modifiers.contains(Modifier::Strikethrough))
+        );
+    }
+}
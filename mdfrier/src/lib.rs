@@ -92,26 +92,32 @@
 //!
 //! ```
 
+mod changes;
+mod diff;
 mod lines;
 pub mod link_tracker;
 pub mod mapper;
 mod markdown;
+mod slides;
 mod wrap;
 
 #[cfg(feature = "ratatui")]
 pub mod ratatui;
 
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
-use tree_sitter::Parser;
+use tree_sitter::{Parser, Tree};
 
+pub use changes::Changes;
+pub use diff::{BlockChange, MdDiff};
 pub use lines::LineIterator;
 pub use mapper::{DefaultMapper, Mapper, StyledMapper};
 pub use markdown::BulletStyle;
 pub use markdown::{Modifier, SourceContent, Span};
+pub use slides::Slide;
 
 use crate::link_tracker::TrackedUrl;
-use crate::markdown::MdIterator;
+use crate::markdown::{MdIterator, MdSection};
 
 // ============================================================================
 // Public output types
@@ -130,6 +136,17 @@ pub struct Line {
     pub kind: LineKind,
     /// Any links and images found *ending* on this line.
     pub urls: Vec<TrackedUrl>,
+    /// The byte range of the markdown block this line was rendered from.
+    ///
+    /// All lines of a block, e.g. a wrapped paragraph, have the same range, except for the rows
+    /// of a table and the lines of a code block, which have the range of their source line.
+    /// Blank lines between blocks have an empty range at the start of the following block.
+    pub source: Range<usize>,
+    /// The 0-based line range of the markdown block this line was rendered from, end exclusive.
+    ///
+    /// Like [`Line::source`], all lines of a block have the same range, except for table rows
+    /// and lines of code.
+    pub source_lines: Range<usize>,
 }
 
 /// The kind of content a line represents.
@@ -214,6 +231,8 @@ impl std::error::Error for MarkdownParseError {}
 pub struct MdFrier {
     parser: Parser,
     inline_parser: Parser,
+    /// The tree and text of the previous [`MdFrier::reparse`].
+    previous: Option<(Tree, String)>,
 }
 
 impl MdFrier {
@@ -234,6 +253,7 @@ impl MdFrier {
         Ok(Self {
             parser,
             inline_parser,
+            previous: None,
         })
     }
 
@@ -258,6 +278,89 @@ impl MdFrier {
         let iter = MdIterator::new(tree, &mut self.inline_parser, text);
         Ok(LineIterator::new(iter, width, mapper))
     }
+
+    /// Parse markdown text without laying it out yet.
+    ///
+    /// [`MdDocument::layout`] produces the same lines as [`MdFrier::parse`], at any width, without
+    /// parsing again.
+    pub fn parse_document(&mut self, text: &str) -> Result<MdDocument, MarkdownParseError> {
+        let tree = self.parser.parse(text, None).ok_or(MarkdownParseError)?;
+        Ok(MdDocument::new(tree, &mut self.inline_parser, text))
+    }
+
+    /// Parse the `old` version of a document, and diff its blocks with the `new` one.
+    ///
+    /// Like [`MdFrier::parse_document`], this does not change what [`MdFrier::reparse`] is
+    /// relative to.
+    pub fn diff(&mut self, old: &str, new: &MdDocument) -> Result<MdDiff, MarkdownParseError> {
+        let tree = self.parser.parse(old, None).ok_or(MarkdownParseError)?;
+        let old = MdDocument::new(tree, &mut self.inline_parser, old);
+        Ok(MdDiff::new(old, new))
+    }
+
+    /// Parse markdown text as an edit of the previously reparsed text.
+    ///
+    /// Like [`MdFrier::parse_document`], but reuses the previous syntax tree, and also returns
+    /// what [`Changes`] from the previous text. Lines whose [`Line::source`] can be mapped with
+    /// [`Changes::map_range`] are unchanged. Without a previous reparse, there are no `Changes`.
+    pub fn reparse(
+        &mut self,
+        text: &str,
+    ) -> Result<(MdDocument, Option<Changes>), MarkdownParseError> {
+        let (tree, changes) = match self.previous.take() {
+            Some((mut old_tree, old_text)) => {
+                let edit = changes::text_edit(&old_text, text);
+                old_tree.edit(&edit);
+                let tree = self
+                    .parser
+                    .parse(text, Some(&old_tree))
+                    .ok_or(MarkdownParseError)?;
+                let changes = Changes::new(&edit, &old_tree, &tree);
+                (tree, Some(changes))
+            }
+            None => (
+                self.parser.parse(text, None).ok_or(MarkdownParseError)?,
+                None,
+            ),
+        };
+        self.previous = Some((tree.clone(), text.to_owned()));
+        let document = MdDocument::new(tree, &mut self.inline_parser, text);
+        Ok((document, changes))
+    }
+
+    /// Split markdown text into slides.
+    ///
+    /// Slides are separated by thematic breaks (`---`), or if there are none, each H1 or H2 header
+    /// starts a new slide. HTML comments are removed from the slides, and returned as speaker
+    /// notes.
+    pub fn slides(&mut self, text: &str) -> Result<Vec<Slide>, MarkdownParseError> {
+        let tree = self.parser.parse(text, None).ok_or(MarkdownParseError)?;
+        Ok(slides::split(&tree, text))
+    }
+}
+
+/// Parsed markdown, that can be laid out at any width.
+///
+/// See [`MdFrier::parse_document`]. Laying out again, e.g. when the terminal is resized, skips
+/// parsing the text.
+#[derive(Debug, Clone)]
+pub struct MdDocument {
+    sections: Vec<MdSection>,
+    text_len: usize,
+}
+
+impl MdDocument {
+    fn new(tree: Tree, inline_parser: &mut Parser, text: &str) -> Self {
+        MdDocument {
+            sections: MdIterator::new(tree, inline_parser, text).collect(),
+            text_len: text.len(),
+        }
+    }
+
+    /// Wrap the parsed markdown to `width`, see [`MdFrier::parse`].
+    pub fn layout<'a, M: Mapper>(&'a self, width: u16, mapper: &'a M) -> LineIterator<'a, M> {
+        LineIterator::new(self.sections.iter().cloned(), width, mapper)
+    }
 }
 
 #[cfg(test)]
@@ -681,6 +784,190 @@ Should be split up nicely.
         insta::assert_snapshot!(output);
     }
 
+    #[test]
+    fn slides_at_thematic_breaks() {
+        let input = "# Title\n\nIntro\n\n---\n\n## First\n\n<!-- Say hi -->\n\n- a\n- b\n\n***\n\n```\n---\n```\n";
+
+        let mut frier = MdFrier::new().unwrap();
+        let slides = frier.slides(input).unwrap();
+        assert_eq!(
+            slides,
+            vec![
+                Slide {
+                    text: "# Title\n\nIntro".to_owned(),
+                    notes: vec![],
+                    line: 0,
+                },
+                Slide {
+                    text: "## First\n\n\n- a\n- b".to_owned(),
+                    notes: vec!["Say hi".to_owned()],
+                    line: 6,
+                },
+                Slide {
+                    text: "```\n---\n```".to_owned(),
+                    notes: vec![],
+                    line: 15,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reparse_maps_unchanged_lines() {
+        let mut frier = MdFrier::new().unwrap();
+        let (document, _) = frier.reparse("# Title\n\nFirst.\n\nSecond.\n").unwrap();
+        let old: Vec<Line> = document.layout(80, &DefaultMapper).collect();
+        let (document, changes) = frier
+            .reparse("# Title\n\nFirst.\n\nSecond, edited.\n")
+            .unwrap();
+        let new: Vec<Line> = document.layout(80, &DefaultMapper).collect();
+        let changes = changes.unwrap();
+        assert_eq!(changes.old, 23..23);
+        assert_eq!(changes.new, 23..31);
+
+        let mapped: Vec<_> = old
+            .iter()
+            .map(|line| changes.map_range(line.source.clone()))
+            .collect();
+        let sources: Vec<_> = new.iter().map(|line| Some(line.source.clone())).collect();
+        assert_eq!(Line::to_strings(&new).last().unwrap(), "Second, edited.");
+        assert_eq!(mapped.len(), sources.len());
+        // Everything but the last paragraph is unchanged.
+        assert_eq!(mapped[..mapped.len() - 1], sources[..sources.len() - 1]);
+        assert_eq!(mapped.last().unwrap(), &None);
+    }
+
+    fn source_lines(width: u16, input: &str) -> Vec<(String, Range<usize>)> {
+        let mut frier = MdFrier::new().unwrap();
+        frier
+            .parse(width, input, &DefaultMapper)
+            .unwrap()
+            .map(|line| (line.to_string(), line.source_lines))
+            .collect()
+    }
+
+    #[test]
+    fn source_lines_list() {
+        let input = "Intro\n\n- one\n- two\n  continued\n\n  second paragraph\n";
+        assert_eq!(
+            source_lines(80, input),
+            vec![
+                ("Intro".to_owned(), 0..1),
+                (String::new(), 2..2),
+                ("- one".to_owned(), 2..3),
+                ("- two continued".to_owned(), 3..5),
+                (String::new(), 6..6),
+                ("  second paragraph".to_owned(), 6..7),
+            ]
+        );
+    }
+
+    #[test]
+    fn source_lines_table() {
+        let input = "Intro\n\n| a | b |\n|---|---|\n| 1 | 2 |\n| 3 | 4 |\n\nOutro\n";
+        let lines = source_lines(80, input);
+        assert_eq!(lines.len(), 10);
+        // Rows map to their own line, borders to the whole table.
+        let expected = [2..6, 2..3, 2..6, 4..5, 5..6, 2..6];
+        for ((text, source_lines), expected) in lines[2..8].iter().zip(expected) {
+            assert_eq!(source_lines, &expected, "{text}");
+        }
+        assert_eq!(lines[9], ("Outro".to_owned(), 7..8));
+    }
+
+    #[test]
+    fn source_lines_code_block() {
+        let input = "Intro
+
+```rust
+let a = 1;
+
+let long_enough_to_wrap = 2;
+```
+";
+        let mut frier = MdFrier::new().unwrap();
+        let lines: Vec<Line> = frier.parse(20, input, &DefaultMapper).unwrap().collect();
+        let code: Vec<_> = lines[2..]
+            .iter()
+            .map(|line| {
+                (
+                    input.get(line.source.clone()).unwrap(),
+                    line.source_lines.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            code,
+            vec![
+                ("let a = 1;\n", 3..4),
+                ("\n", 4..5),
+                ("let long_enough_to_wrap = 2;\n", 5..6),
+                ("let long_enough_to_wrap = 2;\n", 5..6),
+                ("let long_enough_to_wrap = 2;\n", 5..6),
+            ]
+        );
+    }
+
+    #[test]
+    fn source_lines_wrapped_paragraph() {
+        let input = "# Title\n\nA paragraph that is long enough\nto wrap at this width.\n";
+        let mut frier = MdFrier::new().unwrap();
+        let lines: Vec<Line> = frier.parse(12, input, &DefaultMapper).unwrap().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0].source_lines, 0..1);
+        for line in &lines[1..] {
+            assert_eq!(line.source_lines, 2..4);
+            assert_eq!(
+                input.get(line.source.clone()).unwrap(),
+                "A paragraph that is long enough\nto wrap at this width.\n"
+            );
+        }
+    }
+
+    #[test]
+    fn reparse_without_previous() {
+        let mut frier = MdFrier::new().unwrap();
+        let (document, changes) = frier.reparse("Text.\n").unwrap();
+        assert_eq!(
+            Line::to_strings(document.layout(80, &DefaultMapper)),
+            vec!["Text."]
+        );
+        assert_eq!(changes, None);
+    }
+
+    #[test]
+    fn layout_matches_parse() {
+        let input = "# Title\n\nA paragraph that is long enough to wrap.\n\n- item one\n- item two\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let mut frier = MdFrier::new().unwrap();
+        let document = frier.parse_document(input).unwrap();
+        for width in [10, 20, 80] {
+            let parsed: Vec<Line> = frier.parse(width, input, &DefaultMapper).unwrap().collect();
+            let laid_out: Vec<Line> = document.layout(width, &DefaultMapper).collect();
+            assert_eq!(parsed, laid_out, "width {width}");
+        }
+    }
+
+    #[test]
+    fn slides_at_headers() {
+        let input = "Preamble\n\n# One\n\ntext\n\n### Sub\n\nSetext\n------\n\nmore\n";
+
+        let mut frier = MdFrier::new().unwrap();
+        let slides: Vec<_> = frier
+            .slides(input)
+            .unwrap()
+            .into_iter()
+            .map(|slide| slide.text)
+            .collect();
+        assert_eq!(
+            slides,
+            vec![
+                "Preamble",
+                "# One\n\ntext\n\n### Sub",
+                "Setext\n------\n\nmore"
+            ]
+        );
+    }
+
     #[test]
     fn html() {
         let input = r#"<div style="color: green">
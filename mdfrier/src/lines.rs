@@ -7,9 +7,7 @@ use unicode_width::UnicodeWidthStr as _;
 use crate::{
     Line, LineKind, Mapper, MarkdownLink,
     link_tracker::TrackedUrl,
-    markdown::{
-        ListMarker, MdContainer, MdContent, MdIterator, MdSection, Modifier, Span, TableAlignment,
-    },
+    markdown::{ListMarker, MdContainer, MdContent, MdSection, Modifier, Span, TableAlignment},
     wrap::{wrap_md_spans, wrap_md_spans_lines},
 };
 
@@ -54,7 +52,7 @@ enum BorderPosition {
 /// This handles the blank line logic between sections, producing lines
 /// one at a time with proper spacing.
 pub struct LineIterator<'a, M: Mapper> {
-    inner: Peekable<MdIterator<'a>>,
+    inner: Peekable<Box<dyn Iterator<Item = MdSection> + 'a>>,
     width: u16,
     mapper: &'a M,
     /// Buffer of pending lines to emit
@@ -70,7 +68,12 @@ pub struct LineIterator<'a, M: Mapper> {
 }
 
 impl<'a, M: Mapper> LineIterator<'a, M> {
-    pub(crate) fn new(inner: MdIterator<'a>, width: u16, mapper: &'a M) -> Self {
+    pub(crate) fn new(
+        inner: impl Iterator<Item = MdSection> + 'a,
+        width: u16,
+        mapper: &'a M,
+    ) -> Self {
+        let inner: Box<dyn Iterator<Item = MdSection> + 'a> = Box::new(inner);
         LineIterator {
             inner: inner.peekable(),
             width,
@@ -85,7 +88,7 @@ impl<'a, M: Mapper> LineIterator<'a, M> {
 
     /// Process the next MdSection and queue its lines
     fn process_next_section(&mut self) -> bool {
-        let section = match self.inner.next() {
+        let mut section = match self.inner.next() {
             Some(s) => s,
             None => return false,
         };
@@ -177,6 +180,8 @@ impl<'a, M: Mapper> LineIterator<'a, M> {
                 spans: Vec::new(),
                 kind: LineKind::Paragraph,
                 urls: Vec::new(),
+                source: section.source.start..section.source.start,
+                source_lines: section.source_lines.start..section.source_lines.start,
             });
         }
 
@@ -186,8 +191,25 @@ impl<'a, M: Mapper> LineIterator<'a, M> {
         self.prev_was_blank = is_blank_line;
         self.prev_in_list = in_list;
 
+        let source = section.source.clone();
+        let source_lines = section.source_lines.clone();
+        let parts = std::mem::take(&mut section.parts);
         let lines = section_to_lines(self.width, section, self.mapper);
-        self.pending_lines.extend(lines);
+        self.pending_lines
+            .extend(lines.into_iter().map(
+                |(line, part)| match part.and_then(|part| parts.get(part)) {
+                    Some((source, row)) => Line {
+                        source: source.clone(),
+                        source_lines: *row..row + 1,
+                        ..line
+                    },
+                    None => Line {
+                        source: source.clone(),
+                        source_lines: source_lines.clone(),
+                        ..line
+                    },
+                },
+            ));
 
         true
     }
@@ -213,17 +235,24 @@ impl<M: Mapper> Iterator for LineIterator<'_, M> {
     }
 }
 
-/// Convert a markdown section to output lines.
+/// Convert a markdown section to output lines, with the index of the section's part that each
+/// line was rendered from, if any.
 /// Applies mapper decorators before wrapping so widths are correct.
-fn section_to_lines<M: Mapper>(width: u16, section: MdSection, mapper: &M) -> Vec<Line> {
+fn section_to_lines<M: Mapper>(
+    width: u16,
+    section: MdSection,
+    mapper: &M,
+) -> Vec<(Line, Option<usize>)> {
     let nesting = convert_nesting(&section.nesting, section.is_list_continuation);
 
-    match section.content {
+    let lines = match section.content {
         MdContent::Paragraph(p) if p.is_empty() => {
             vec![Line {
                 spans: nesting_to_prefix_spans(&nesting, mapper),
                 kind: LineKind::Paragraph,
                 urls: Vec::new(),
+                source: 0..0,
+                source_lines: 0..0,
             }]
         }
         MdContent::Paragraph(p) => {
@@ -260,6 +289,8 @@ fn section_to_lines<M: Mapper>(width: u16, section: MdSection, mapper: &M) -> Ve
                         spans,
                         kind: LineKind::Header(tier),
                         urls,
+                        source: 0..0,
+                        source_lines: 0..0,
                     })
                     .collect()
             } else {
@@ -267,6 +298,8 @@ fn section_to_lines<M: Mapper>(width: u16, section: MdSection, mapper: &M) -> Ve
                     spans: vec![Span::from(text.clone())],
                     kind: LineKind::Header(tier),
                     urls: Vec::new(),
+                    source: 0..0,
+                    source_lines: 0..0,
                 }]
             };
 
@@ -285,7 +318,7 @@ fn section_to_lines<M: Mapper>(width: u16, section: MdSection, mapper: &M) -> Ve
             lines
         }
         MdContent::CodeBlock { language, code } => {
-            code_block_to_lines(width, language, code, nesting, mapper)
+            return code_block_to_lines(width, language, code, nesting, mapper);
         }
         MdContent::HorizontalRule => {
             let prefix_spans = nesting_to_prefix_spans(&nesting, mapper);
@@ -301,19 +334,23 @@ fn section_to_lines<M: Mapper>(width: u16, section: MdSection, mapper: &M) -> Ve
                 spans,
                 kind: LineKind::HorizontalRule,
                 urls: Vec::new(),
+                source: 0..0,
+                source_lines: 0..0,
             }]
         }
         MdContent::Table {
             header,
             rows,
             alignments,
-        } => table_to_lines(width, &header, &rows, &alignments, nesting, mapper),
+        } => return table_to_lines(width, &header, &rows, &alignments, nesting, mapper),
         MdContent::Html { html } => html
             .split("\n")
             .map(|linestr| Line {
                 spans: vec![Span::from(linestr.to_owned())],
                 kind: LineKind::Paragraph,
                 urls: Vec::new(),
+                source: 0..0,
+                source_lines: 0..0,
             })
             .collect(),
         MdContent::LinkReferenceDefinition { reference, url } => {
@@ -374,11 +411,14 @@ fn section_to_lines<M: Mapper>(width: u16, section: MdSection, mapper: &M) -> Ve
                         } else {
                             vec![]
                         },
+                        source: 0..0,
+                        source_lines: 0..0,
                     }
                 })
                 .collect()
         }
-    }
+    };
+    lines.into_iter().map(|line| (line, None)).collect()
 }
 
 /// Apply mapper decorators to spans (emphasis, code, links, etc).
@@ -625,14 +665,14 @@ fn convert_nesting(md_nesting: &[MdContainer], is_list_continuation: bool) -> Ve
         .collect()
 }
 
-/// Convert a code block to output lines.
+/// Convert a code block to output lines, with the index of their line of code.
 fn code_block_to_lines<M: Mapper>(
     width: u16,
     language: String,
     code: String,
     nesting: Vec<MdLineContainer>,
     mapper: &M,
-) -> Vec<Line> {
+) -> Vec<(Line, Option<usize>)> {
     let code_lines: Vec<&str> = code.lines().collect();
     let num_lines = code_lines.len();
     if num_lines == 0 {
@@ -646,7 +686,7 @@ fn code_block_to_lines<M: Mapper>(
 
     let mut result = Vec::new();
 
-    for line in code_lines {
+    for (i, line) in code_lines.into_iter().enumerate() {
         let line_width = line.width();
 
         if line_width > available_width {
@@ -665,13 +705,18 @@ fn code_block_to_lines<M: Mapper>(
                 if padding > 0 {
                     spans.push(Span::new(" ".repeat(padding), Modifier::Code));
                 }
-                result.push(Line {
-                    spans,
-                    kind: LineKind::CodeBlock {
-                        language: language.clone(),
+                result.push((
+                    Line {
+                        spans,
+                        kind: LineKind::CodeBlock {
+                            language: language.clone(),
+                        },
+                        urls: Vec::new(),
+                        source: 0..0,
+                        source_lines: 0..0,
                     },
-                    urls: Vec::new(),
-                });
+                    Some(i),
+                ));
             }
         } else {
             // Line fits, pad to fill width
@@ -682,13 +727,18 @@ fn code_block_to_lines<M: Mapper>(
             if padding > 0 {
                 spans.push(Span::new(" ".repeat(padding), Modifier::Code));
             }
-            result.push(Line {
-                spans,
-                kind: LineKind::CodeBlock {
-                    language: language.clone(),
+            result.push((
+                Line {
+                    spans,
+                    kind: LineKind::CodeBlock {
+                        language: language.clone(),
+                    },
+                    urls: Vec::new(),
+                    source: 0..0,
+                    source_lines: 0..0,
                 },
-                urls: Vec::new(),
-            });
+                Some(i),
+            ));
         }
     }
 
@@ -773,6 +823,8 @@ fn wrapped_to_lines<M: Mapper>(
                         desc: desc.clone(),
                         url: url.clone(),
                     }],
+                    source: 0..0,
+                    source_lines: 0..0,
                 });
             }
         }
@@ -785,6 +837,8 @@ fn wrapped_to_lines<M: Mapper>(
                 spans: nesting_spans,
                 kind: LineKind::Paragraph,
                 urls,
+                source: 0..0,
+                source_lines: 0..0,
             });
         }
 
@@ -794,7 +848,7 @@ fn wrapped_to_lines<M: Mapper>(
     lines
 }
 
-/// Convert a table to output lines.
+/// Convert a table to output lines, with the index of their row, the header being 0.
 fn table_to_lines<M: Mapper>(
     width: u16,
     header: &[Vec<Span>],
@@ -802,7 +856,7 @@ fn table_to_lines<M: Mapper>(
     alignments: &[TableAlignment],
     nesting: Vec<MdLineContainer>,
     mapper: &M,
-) -> Vec<Line> {
+) -> Vec<(Line, Option<usize>)> {
     let mut lines = Vec::new();
 
     let prefix_spans = nesting_to_prefix_spans(&nesting, mapper);
@@ -845,7 +899,7 @@ fn table_to_lines<M: Mapper>(
     };
 
     // Helper to build border line
-    let build_border = |position: BorderPosition| -> Line {
+    let build_border = |position: BorderPosition| -> (Line, Option<usize>) {
         let (left, mid, right) = match position {
             BorderPosition::Top => (
                 mapper.table_top_left(),
@@ -875,15 +929,19 @@ fn table_to_lines<M: Mapper>(
         }
         spans.push(Span::new(right.to_owned(), Modifier::TableBorder));
 
-        Line {
+        let line = Line {
             spans,
             kind: LineKind::TableBorder,
             urls: Vec::new(),
-        }
+            source: 0..0,
+            source_lines: 0..0,
+        };
+        (line, None)
     };
 
     // Helper to build row lines
-    let build_row_lines = |row: &[Vec<Span>], is_header: bool| -> Vec<Line> {
+    let build_row_lines = |row: &[Vec<Span>], index: usize| -> Vec<(Line, Option<usize>)> {
+        let is_header = index == 0;
         let vertical = mapper.table_vertical();
 
         // Wrap each cell's content (apply decorators first, like paragraphs)
@@ -953,11 +1011,14 @@ fn table_to_lines<M: Mapper>(
                 spans.push(Span::new(vertical.to_owned(), Modifier::TableBorder));
             }
 
-            result.push(Line {
+            let line = Line {
                 spans,
                 kind: LineKind::TableRow { is_header },
                 urls: Vec::new(),
-            });
+                source: 0..0,
+                source_lines: 0..0,
+            };
+            result.push((line, Some(index)));
         }
 
         result
@@ -967,14 +1028,14 @@ fn table_to_lines<M: Mapper>(
     lines.push(build_border(BorderPosition::Top));
 
     // Header row
-    lines.extend(build_row_lines(header, true));
+    lines.extend(build_row_lines(header, 0));
 
     // Header separator
     lines.push(build_border(BorderPosition::HeaderSeparator));
 
     // Data rows
-    for row in rows {
-        lines.extend(build_row_lines(row, false));
+    for (i, row) in rows.iter().enumerate() {
+        lines.extend(build_row_lines(row, i + 1));
     }
 
     // Bottom border
@@ -986,7 +1047,7 @@ fn table_to_lines<M: Mapper>(
 #[cfg(test)]
 #[expect(clippy::unwrap_used)]
 mod tests {
-    use crate::DefaultMapper;
+    use crate::{DefaultMapper, markdown::MdIterator};
 
     use super::*;
     use pretty_assertions::assert_eq;
@@ -1042,7 +1103,9 @@ mod tests {
                     Span::with("", Modifier::Link | Modifier::LinkURLWrapper,),
                 ],
                 kind: LineKind::Paragraph,
-                urls: vec![TrackedUrl::link("http://example.com", 0, 4, 0)]
+                urls: vec![TrackedUrl::link("http://example.com", 0, 4, 0)],
+                source: 0..27,
+                source_lines: 0..1,
             }
         );
     }
@@ -1108,6 +1171,8 @@ mod tests {
                     TrackedUrl::image("image", "http://example.com/img.png"),
                     TrackedUrl::link("http://example.com", 0, 36, 0),
                 ],
+                source: 0..59,
+                source_lines: 0..1,
             }
         );
     }
@@ -1140,6 +1205,8 @@ I have searched far **and** wide but I have *yet* to come across a show that wou
                 ],
                 kind: LineKind::Paragraph,
                 urls: Vec::new(),
+                source: 1..276,
+                source_lines: 1..2,
             }
         );
         assert_eq!(
@@ -1151,6 +1218,8 @@ I have searched far **and** wide but I have *yet* to come across a show that wou
                 ),],
                 kind: LineKind::Paragraph,
                 urls: Vec::new(),
+                source: 1..276,
+                source_lines: 1..2,
             }
         );
         assert_eq!(
@@ -1162,6 +1231,8 @@ I have searched far **and** wide but I have *yet* to come across a show that wou
                 ),],
                 kind: LineKind::Paragraph,
                 urls: Vec::new(),
+                source: 1..276,
+                source_lines: 1..2,
             }
         );
         assert_eq!(
@@ -1173,6 +1244,8 @@ I have searched far **and** wide but I have *yet* to come across a show that wou
                 ),],
                 kind: LineKind::Paragraph,
                 urls: Vec::new(),
+                source: 1..276,
+                source_lines: 1..2,
             }
         );
     }
@@ -1,5 +1,8 @@
 use std::sync::Arc;
-use std::{borrow::Cow, ops::Deref};
+use std::{
+    borrow::Cow,
+    ops::{Deref, Range},
+};
 
 use bitflags::bitflags;
 use regex::Regex;
@@ -115,6 +118,9 @@ impl Iterator for MdIterator<'_> {
                     content,
                     nesting,
                     is_list_continuation,
+                    source: node.byte_range(),
+                    source_lines: node_lines(node),
+                    parts: self.parts(node),
                 });
             }
         }
@@ -248,6 +254,40 @@ impl<'a> MdIterator<'a> {
         }
     }
 
+    /// The source lines of a code block, or the rows of a table, header first.
+    fn parts(&self, node: Node<'a>) -> Vec<(Range<usize>, usize)> {
+        match node.kind() {
+            "fenced_code_block" => node
+                .children(&mut node.walk())
+                .find(|child| child.kind() == "code_fence_content")
+                .map(|content| self.line_parts(content))
+                .unwrap_or_default(),
+            "indented_code_block" => self.line_parts(node),
+            "pipe_table" => node
+                .children(&mut node.walk())
+                .filter(|child| matches!(child.kind(), "pipe_table_header" | "pipe_table_row"))
+                .map(|row| (row.byte_range(), row.start_position().row))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The byte range, with the newline, and the 0-based line of each line of a node.
+    fn line_parts(&self, node: Node<'a>) -> Vec<(Range<usize>, usize)> {
+        let mut start = node.start_byte();
+        self.source
+            .get(node.byte_range())
+            .unwrap_or_default()
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let part = (start..start + line.len(), node.start_position().row + i);
+                start += line.len();
+                part
+            })
+            .collect()
+    }
+
     fn parse_table(&mut self, node: Node<'a>) -> MdContent {
         let mut header: Vec<Vec<Span>> = Vec::new();
         let mut rows: Vec<Vec<Vec<Span>>> = Vec::new();
@@ -753,12 +793,30 @@ impl From<&str> for MdParagraph {
 pub(crate) struct BlockquoteMarker;
 
 /// A markdown section with its content and nesting path.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct MdSection {
     pub content: MdContent,
     pub nesting: Vec<MdContainer>,
     /// True if this is a continuation paragraph within a list item (not the first content).
     pub is_list_continuation: bool,
+    /// The byte range of the block node in the source.
+    pub source: Range<usize>,
+    /// The 0-based line range of the block node in the source.
+    pub source_lines: Range<usize>,
+    /// The byte range and 0-based line of each part of the block that is one source line: the
+    /// lines of a code block, or the rows of a table, header first. Empty for other blocks.
+    pub parts: Vec<(Range<usize>, usize)>,
+}
+
+/// The 0-based line range of a node, without the line after its trailing newline.
+fn node_lines(node: Node<'_>) -> Range<usize> {
+    let start = node.start_position().row;
+    let end = node.end_position();
+    if end.column == 0 && end.row > start {
+        start..end.row
+    } else {
+        start..end.row + 1
+    }
 }
 
 fn strip_blockquote_prefix(s: &str, depth: usize) -> Cow<'_, str> {
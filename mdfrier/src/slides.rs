@@ -0,0 +1,135 @@
+//! Splitting a markdown document into slides.
+
+use std::ops::Range;
+
+use tree_sitter::{Node, Tree};
+
+/// A slide of a markdown document, see [`MdFrier::slides`](crate::MdFrier::slides).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slide {
+    /// The markdown source of the slide, without speaker notes.
+    pub text: String,
+    /// Speaker notes, from the HTML comments of the slide.
+    pub notes: Vec<String>,
+    /// The 0-based line of the document where the slide's text starts.
+    pub line: usize,
+}
+
+/// Top-level blocks that are relevant for slides.
+enum Block {
+    ThematicBreak(Range<usize>),
+    Header(Range<usize>),
+    Comment(Range<usize>),
+}
+
+pub(crate) fn split(tree: &Tree, source: &str) -> Vec<Slide> {
+    let mut blocks = Vec::new();
+    collect_blocks(tree.root_node(), source, &mut blocks);
+
+    let has_breaks = blocks
+        .iter()
+        .any(|block| matches!(block, Block::ThematicBreak(_)));
+
+    let mut slides = Vec::new();
+    let mut slide = SlideBuilder::new(0);
+    for block in blocks {
+        match block {
+            Block::ThematicBreak(range) if has_breaks => {
+                slides.extend(slide.build(source, range.start));
+                slide = SlideBuilder::new(range.end);
+            }
+            Block::Header(range) if !has_breaks => {
+                slides.extend(slide.build(source, range.start));
+                slide = SlideBuilder::new(range.start);
+            }
+            Block::Comment(range) => slide.comments.push(range),
+            _ => {}
+        }
+    }
+    slides.extend(slide.build(source, source.len()));
+    slides
+}
+
+/// Walk `document` and `section` nodes, but not into any other container like lists or quotes.
+fn collect_blocks(node: Node<'_>, source: &str, blocks: &mut Vec<Block>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "section" => collect_blocks(child, source, blocks),
+            "thematic_break" => blocks.push(Block::ThematicBreak(child.byte_range())),
+            "atx_heading" | "setext_heading" if is_slide_header(child) => {
+                blocks.push(Block::Header(child.byte_range()));
+            }
+            "html_block" => {
+                let html = source.get(child.byte_range()).unwrap_or_default().trim();
+                if html.starts_with("<!--") && html.ends_with("-->") {
+                    blocks.push(Block::Comment(child.byte_range()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Only H1 and H2 start a new slide.
+fn is_slide_header(node: Node<'_>) -> bool {
+    let mut cursor = node.walk();
+    node.children(&mut cursor).any(|child| {
+        matches!(
+            child.kind(),
+            "atx_h1_marker" | "atx_h2_marker" | "setext_h1_underline" | "setext_h2_underline"
+        )
+    })
+}
+
+struct SlideBuilder {
+    start: usize,
+    comments: Vec<Range<usize>>,
+}
+
+impl SlideBuilder {
+    fn new(start: usize) -> Self {
+        SlideBuilder {
+            start,
+            comments: Vec::new(),
+        }
+    }
+
+    /// Build the slide up to `end`, or `None` if it would be empty.
+    fn build(self, source: &str, end: usize) -> Option<Slide> {
+        let mut text = String::new();
+        let mut notes = Vec::new();
+        let mut pos = self.start;
+        for comment in self.comments {
+            text.push_str(source.get(pos..comment.start).unwrap_or_default());
+            let html = source.get(comment.clone()).unwrap_or_default().trim();
+            let note = html
+                .strip_prefix("<!--")
+                .and_then(|html| html.strip_suffix("-->"))
+                .unwrap_or_default()
+                .trim();
+            if !note.is_empty() {
+                notes.push(note.to_owned());
+            }
+            pos = comment.end;
+        }
+        text.push_str(source.get(pos..end).unwrap_or_default());
+
+        let trimmed = text.trim_matches(['\n', '\r']);
+        if trimmed.trim().is_empty() && notes.is_empty() {
+            return None;
+        }
+        let leading = text.len() - text.trim_start_matches(['\n', '\r']).len();
+        let line = newlines(source.get(..self.start).unwrap_or_default())
+            + newlines(text.get(..leading).unwrap_or_default());
+        Some(Slide {
+            text: trimmed.to_owned(),
+            notes,
+            line,
+        })
+    }
+}
+
+fn newlines(text: &str) -> usize {
+    text.bytes().filter(|b| *b == b'\n').count()
+}
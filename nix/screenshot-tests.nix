@@ -117,6 +117,15 @@ let
       terminalCommand = "alacritty -e ${mdfriedCmd}";
       terminalPackages = [ pkgs.alacritty ];
     };
+
+    # No terminal, the document as mdfried lays it out with `--export png`.
+    # The terminal screenshots above stay: they test each terminal's graphics protocol, which an
+    # export does not go through.
+    screenshot-test-export = pkgs.runCommand "mdfried-screenshot-export" { } ''
+      mkdir -p $out
+      cd ${src}/assets
+      HOME=$TMPDIR ${mdfriedStatic}/bin/mdfried --export png --width 100 screenshot-test.md > $out/screenshot-export.png
+    '';
   };
 
   terminals = map (name: lib.removePrefix "screenshot-test-" name) (builtins.attrNames screenshotTests);
@@ -8,6 +8,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
 
+// Right-aligned on the status line, see `view::status_line`.
+pub const DEFAULT_STATUS_LINE: &str = "{page}";
+
 // The configuration struct used throughout the program.
 //
 // Has implicit `Default` in `From<UserConfig>`.
@@ -15,13 +18,31 @@ use crate::error::Error;
 pub struct Config {
     pub padding: Padding,
     pub max_image_height: u16,
+    // The frame rate cap of animated images, 0 shows only their first frame.
+    pub max_animation_fps: u16,
     pub watch_debounce_milliseconds: u64,
+    pub watch_highlight_milliseconds: u64,
+    pub watch_poll_seconds: u64,
     pub enable_mouse_capture: bool,
     pub osc8_links: bool,
+    pub scrollbar: bool,
+    pub status_line: String,
+    pub sticky_header: StickyHeader,
     pub debug_override_protocol_type: Option<ProtocolType>,
     pub url_transform_command: Option<String>,
+    // The size cap of the on-disk HTTP cache, 0 disables it.
+    pub http_cache_megabytes: u64,
+    pub remote_images: RemoteImages,
+    pub http: HttpConfig,
+    // Besides the built-in `gitlab:`, `codeberg:`, `gitea:` and `sourcehut:` sources.
+    pub forges: Vec<Forge>,
     pub theme: Theme,
+    pub code_theme: Option<CodeTheme>,
     pub mermaid: MermaidConfig,
+    // The font of image exports, also without a font setup.
+    pub font_family: Option<String>,
+    // Not configurable, `--print` only loads images when asked to.
+    pub load_images: bool,
 }
 
 impl From<UserConfig> for Config {
@@ -29,16 +50,31 @@ impl From<UserConfig> for Config {
         Config {
             padding: uc.padding.unwrap_or_default(),
             max_image_height: uc.max_image_height.unwrap_or(30),
+            max_animation_fps: uc.max_animation_fps.unwrap_or(25),
             watch_debounce_milliseconds: uc.watch_debounce_milliseconds.unwrap_or(100),
+            watch_highlight_milliseconds: uc.watch_highlight_milliseconds.unwrap_or(0),
+            watch_poll_seconds: uc.watch_poll_seconds.unwrap_or(30),
             enable_mouse_capture: uc.enable_mouse_capture.unwrap_or(false),
             osc8_links: uc.osc8_links.unwrap_or(true),
+            scrollbar: uc.scrollbar.unwrap_or(false),
+            status_line: uc
+                .status_line
+                .unwrap_or_else(|| DEFAULT_STATUS_LINE.to_owned()),
+            sticky_header: uc.sticky_header.unwrap_or_default(),
             debug_override_protocol_type: uc.debug_override_protocol_type,
             url_transform_command: uc.url_transform_command,
+            http_cache_megabytes: uc.http_cache_megabytes.unwrap_or(100),
+            remote_images: uc.remote_images.unwrap_or_default(),
+            http: uc.http.unwrap_or_default(),
+            forges: uc.forges.unwrap_or_default(),
             mermaid: uc.mermaid.unwrap_or_default(),
+            font_family: uc.font_family,
+            code_theme: uc.code_theme,
             theme: uc.theme.unwrap_or_else(|| Theme {
                 hide_urls: Some(true),
                 ..Default::default()
             }),
+            load_images: true,
         }
     }
 }
@@ -50,12 +86,23 @@ pub struct UserConfig {
     pub stdio_query_timeout_ms: Option<u64>,
     pub padding: Option<Padding>,
     pub max_image_height: Option<u16>,
+    pub max_animation_fps: Option<u16>,
     pub watch_debounce_milliseconds: Option<u64>,
+    pub watch_highlight_milliseconds: Option<u64>,
+    pub watch_poll_seconds: Option<u64>,
     pub enable_mouse_capture: Option<bool>,
     pub osc8_links: Option<bool>,
+    pub scrollbar: Option<bool>,
+    pub status_line: Option<String>,
+    pub sticky_header: Option<StickyHeader>,
     pub debug_override_protocol_type: Option<ProtocolType>,
     pub url_transform_command: Option<String>,
+    pub http_cache_megabytes: Option<u64>,
+    pub remote_images: Option<RemoteImages>,
+    pub http: Option<HttpConfig>,
+    pub forges: Option<Vec<Forge>>,
     pub theme: Option<Theme>,
+    pub code_theme: Option<CodeTheme>,
     pub mermaid: Option<MermaidConfig>,
 }
 
@@ -82,6 +129,90 @@ impl Default for MermaidConfig {
     }
 }
 
+// Pin the header of the current section at the top of the screen, once scrolled out of view.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StickyHeader {
+    #[default]
+    Off,
+    // Only the innermost header.
+    Innermost,
+    // The innermost header and its parents, like the `{breadcrumb}` in the status line.
+    Parents,
+}
+
+// Whether images are downloaded, which tells their servers that the document is being read.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteImages {
+    #[default]
+    Always,
+    // Only once allowed for the document, with the `I` key.
+    Ask,
+    Never,
+}
+
+pub const DEFAULT_CONNECT_TIMEOUT_SECONDS: u64 = 10;
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 60;
+pub const DEFAULT_MAX_BODY_MEGABYTES: u64 = 50;
+
+// The `[http]` section, shared by the clients of documents and of images.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    pub connect_timeout_seconds: Option<u64>,
+    // The whole request, including reading the body.
+    pub timeout_seconds: Option<u64>,
+    pub max_body_megabytes: Option<u64>,
+    // Otherwise the proxy environment variables apply.
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub headers: Vec<HttpHeader>,
+}
+
+// A header sent to a host and its subdomains, e.g. an auth token.
+//
+// The value is `value`, or the output of `env` or `command`, which replaces any `{}` in `value`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HttpHeader {
+    pub host: String,
+    pub name: String,
+    pub value: Option<String>,
+    pub env: Option<String>,
+    pub command: Option<String>,
+}
+
+// A source prefix for repositories of a forge, e.g. `work:group/project` for a GitLab instance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Forge {
+    pub prefix: String,
+    pub kind: ForgeKind,
+    // The base URL, or `None` if sources start with the host, like `gitea:codeberg.org/owner/repo`.
+    pub url: Option<String>,
+    // Overrides the raw content URL template of `kind`, with `{url}`, `{repo}`, `{ref}` and `{path}`.
+    pub raw_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForgeKind {
+    Gitlab,
+    // Also Forgejo, e.g. Codeberg.
+    Gitea,
+    Sourcehut,
+}
+
+// Syntax highlighting theme for code blocks.
+//
+// A name can be any of arborium's builtin themes (e.g. "tokyo-night", "Gruvbox Light"), a theme
+// family (e.g. "gruvbox", "catppuccin") whose dark or light variant is picked to match the document
+// theme, or a `.toml` theme file relative to the config directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum CodeTheme {
+    Name(String),
+    Variants { dark: String, light: String },
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Theme {
     // Symbols
@@ -279,6 +410,35 @@ impl mdfrier::ratatui::Theme for Theme {
 }
 
 impl Theme {
+    // Whether the document theme is dark, judged by the code block background.
+    pub fn is_dark(&self) -> bool {
+        use mdfrier::ratatui::Theme as _;
+        let (r, g, b) = match self.code_bg() {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Indexed(i @ 16..=231) => {
+                const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+                let i = usize::from(i - 16);
+                (LEVELS[i / 36], LEVELS[(i % 36) / 6], LEVELS[i % 6])
+            }
+            Color::Indexed(i @ 232..=255) => {
+                let gray = 8 + (i - 232) * 10;
+                (gray, gray, gray)
+            }
+            Color::Indexed(7 | 9..=15)
+            | Color::Gray
+            | Color::White
+            | Color::LightRed
+            | Color::LightGreen
+            | Color::LightYellow
+            | Color::LightBlue
+            | Color::LightMagenta
+            | Color::LightCyan => return false,
+            _ => return true,
+        };
+        let luma = 299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b);
+        luma < 128 * 1000
+    }
+
     fn defaults_for_print() -> Theme {
         use mdfrier::ratatui::Theme as _;
         let theme = Theme::default();
@@ -405,12 +565,39 @@ pub fn print_default() -> Result<(), Error> {
         stdio_query_timeout_ms: Some(2000),
         padding: Some(config.padding),
         max_image_height: Some(config.max_image_height),
+        max_animation_fps: Some(config.max_animation_fps),
         watch_debounce_milliseconds: Some(config.watch_debounce_milliseconds),
+        watch_highlight_milliseconds: Some(config.watch_highlight_milliseconds),
+        watch_poll_seconds: Some(config.watch_poll_seconds),
         enable_mouse_capture: Some(config.enable_mouse_capture),
         osc8_links: Some(config.osc8_links),
+        scrollbar: Some(config.scrollbar),
+        status_line: Some(config.status_line),
+        sticky_header: Some(config.sticky_header),
         debug_override_protocol_type: None,
         url_transform_command: Some("readable | html2text".to_owned()),
+        http_cache_megabytes: Some(config.http_cache_megabytes),
+        remote_images: Some(config.remote_images),
+        http: Some(HttpConfig {
+            connect_timeout_seconds: Some(DEFAULT_CONNECT_TIMEOUT_SECONDS),
+            timeout_seconds: Some(DEFAULT_TIMEOUT_SECONDS),
+            max_body_megabytes: Some(DEFAULT_MAX_BODY_MEGABYTES),
+            // See help_configuration.md for `proxy` and `headers`, which are not examples to
+            // save as is.
+            proxy: None,
+            headers: Vec::new(),
+        }),
+        forges: Some(vec![Forge {
+            prefix: "work".to_owned(),
+            kind: ForgeKind::Gitlab,
+            url: Some("https://gitlab.example.com".to_owned()),
+            raw_url: None,
+        }]),
         theme: Some(Theme::defaults_for_print()),
+        code_theme: Some(CodeTheme::Variants {
+            dark: "tokyo-night".to_owned(),
+            light: "github-light".to_owned(),
+        }),
         mermaid: Some(MermaidConfig::Command("mmdc -i - -o - -e png".to_owned())),
     };
 
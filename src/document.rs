@@ -1,26 +1,32 @@
 use std::{
     any::Any as _,
+    collections::{HashMap, HashSet},
     fmt::{Debug, Display},
+    hash::{DefaultHasher, Hash as _, Hasher as _},
     num::NonZero,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, Range},
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use itertools::Either;
 
 use cosmic_text::{Attrs, Buffer, Family, Metrics, Shaping, fontdb::Database};
 use image::{
-    DynamicImage, GenericImage as _, GenericImageView as _, ImageFormat, ImageReader, Pixel as _,
-    Rgba, RgbaImage, imageops::FilterType,
+    AnimationDecoder as _, DynamicImage, Frames, GenericImage as _, GenericImageView as _,
+    ImageFormat, ImageReader, Pixel as _, Rgba, RgbaImage,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    imageops::FilterType,
 };
-use mdfrier::{MarkdownLink, SourceContent};
+use mdfrier::{BlockChange, MarkdownLink, SourceContent};
 use ratatui::{layout::Size, text::Line};
 
 use ratatui_image::{FontSize, Resize, picker::Picker, protocol::Protocol, sliced::SlicedProtocol};
 use regex::{Match, Regex};
 use reqwest::{
     Client,
-    header::{ACCEPT, CONTENT_TYPE, HeaderMap, HeaderValue},
+    header::{ACCEPT, HeaderMap, HeaderValue},
 };
 
 #[cfg(feature = "svg")]
@@ -31,16 +37,66 @@ use unicode_width::UnicodeWidthStr as _;
 
 use crate::{
     Error,
+    config::RemoteImages,
     cursor::CursorPointer,
+    http_cache,
     setup::FontRenderer,
-    sources::{DocumentSource, SharedDocumentSource, extend_url, github_usercontent_url},
-    worker::ImageCache,
+    sources::{DocumentSource, SharedDocumentSource, extend_url, git, github},
+    watch::SectionSnapshot,
+    worker::{ImageCache, animation::Animation},
 };
 
 #[derive(Default)]
 pub struct Document {
     sections: Vec<Section>,
     total_lines: u16,
+    sources: HashMap<SectionID, SectionSource>,
+    // The hash of the text this document was parsed from, see `text_hash`.
+    text_hash: Option<u64>,
+    // The text this document was parsed from, for exports.
+    text: String,
+    // The changed blocks from the diff base, by their source range, see `Model::diff_base`.
+    changes: HashMap<Range<usize>, BlockChange>,
+}
+
+// The markdown source byte ranges of a section, and of each of its lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SectionSource {
+    pub range: Range<usize>,
+    pub lines: Vec<Range<usize>>,
+}
+
+// A section of an incremental reparse, in document order.
+pub enum SectionPatch {
+    // The previous section with this id is unchanged, at a new source.
+    Keep(SectionID, SectionSource),
+    New(Section, SectionSource),
+}
+
+// What an incremental reparse needs to know about the displayed document.
+#[derive(Debug)]
+pub struct PreviousParse {
+    pub text_hash: u64,
+    pub sections: Vec<SectionSnapshot>,
+    // The sections with a loaded image, which `Document::patch` moves to new sections of the
+    // same image.
+    pub images: HashSet<SectionID>,
+}
+
+// The source of the `i`th line of a section. Images and header images are taller than their
+// source line.
+fn source_of_line(source: &SectionSource, i: u16) -> &Range<usize> {
+    source
+        .lines
+        .get(usize::from(i))
+        .or(source.lines.last())
+        .unwrap_or(&source.range)
+}
+
+pub fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
 }
 
 impl Document {
@@ -53,6 +109,143 @@ impl Document {
         self.sections.push(section);
     }
 
+    pub fn set_source(&mut self, section_id: SectionID, source: SectionSource) {
+        self.sources.insert(section_id, source);
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text_hash = Some(text_hash(text));
+        text.clone_into(&mut self.text);
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_changes(&mut self, changes: HashMap<Range<usize>, BlockChange>) {
+        self.changes = changes;
+    }
+
+    // How the line at `y` differs from the diff base, if it does.
+    pub fn change(&self, y: u16) -> Option<BlockChange> {
+        if self.changes.is_empty() {
+            return None;
+        }
+        self.block_change(self.line_source(y)?)
+    }
+
+    // The change of the block at, or around for a table row or a line of code, `source`.
+    fn block_change(&self, source: &Range<usize>) -> Option<BlockChange> {
+        self.changes
+            .get(source)
+            .or_else(|| {
+                if source.is_empty() {
+                    return None;
+                }
+                self.changes
+                    .iter()
+                    .find(|(block, _)| block.start <= source.start && source.end <= block.end)
+                    .map(|(_, change)| change)
+            })
+            .copied()
+    }
+
+    // The first line of each run of changed lines. Blank lines between changed blocks don't end
+    // a run, so that a replaced block is one change.
+    pub fn change_starts(&self) -> Vec<u16> {
+        let mut starts = Vec::new();
+        let mut in_change = false;
+        for (y, line) in self.line_sources() {
+            if line.is_empty() {
+                continue;
+            }
+            let changed = self.block_change(line).is_some();
+            if changed && !in_change {
+                starts.push(y);
+            }
+            in_change = changed;
+        }
+        starts
+    }
+
+    // The markdown source byte range of each line with a known source.
+    fn line_sources(&self) -> impl Iterator<Item = (u16, &Range<usize>)> {
+        let mut top = 0;
+        self.sections.iter().flat_map(move |section| {
+            let section_top = top;
+            top += section.height;
+            let source = self.sources.get(&section.id);
+            (0..section.height)
+                .filter_map(move |i| Some((section_top + i, source_of_line(source?, i))))
+        })
+    }
+
+    pub fn previous(&self) -> Option<PreviousParse> {
+        Some(PreviousParse {
+            text_hash: self.text_hash?,
+            sections: self.snapshot(),
+            images: self
+                .sections
+                .iter()
+                .filter(|section| matches!(section.content, SectionContent::Image(..)))
+                .map(|section| section.id)
+                .collect(),
+        })
+    }
+
+    // Rebuild the document from an incremental reparse, moving over the kept sections.
+    pub fn patch(&mut self, patches: Vec<SectionPatch>) {
+        let mut old: HashMap<SectionID, Vec<Section>> = HashMap::new();
+        for section in std::mem::take(&mut self.sections) {
+            old.entry(section.id).or_default().push(section);
+        }
+        self.sources.clear();
+        self.total_lines = 0;
+        let mut placeholders = Vec::new();
+        for patch in patches {
+            let (id, sections, source) = match patch {
+                SectionPatch::Keep(id, source) => {
+                    let Some(sections) = old.remove(&id) else {
+                        log::error!("patch: kept section #{id} not found");
+                        continue;
+                    };
+                    (id, sections, source)
+                }
+                SectionPatch::New(section, source) => {
+                    if let SectionContent::ImagePlaceholder(link, lines) = &section.content {
+                        let trailing_blank = lines
+                            .last()
+                            .map(|(line, _)| line.spans.is_empty())
+                            .unwrap_or_default();
+                        placeholders.push((section.id, link.clone(), trailing_blank));
+                    }
+                    (section.id, vec![section], source)
+                }
+            };
+            self.sources.insert(id, source);
+            for section in sections {
+                self.total_lines += section.height;
+                self.sections.push(section);
+            }
+        }
+
+        // The same image at the same width, the worker doesn't load these again.
+        let mut moved: HashMap<String, Vec<(SlicedProtocol, Size, Size)>> = HashMap::new();
+        for section in old.into_values().flatten() {
+            if let SectionContent::Image(link, proto, size, max_size) = section.content {
+                moved
+                    .entry(link.url)
+                    .or_default()
+                    .push((proto, size, max_size));
+            }
+        }
+        for (id, link, trailing_blank) in placeholders {
+            if let Some(image) = moved.get_mut(&link.url).and_then(Vec::pop) {
+                self.update_image(id, link, image, trailing_blank);
+            }
+        }
+    }
+
     // Update widgets with a list by id
     pub fn update(&mut self, updates: Vec<Section>) {
         let Some(first_id) = updates.first().map(|s| s.id) else {
@@ -123,6 +316,16 @@ impl Document {
         self.total_lines = self.total_lines.saturating_sub(old_height) + new_height;
     }
 
+    /// Show the next frame of an animated image, if the section still shows the image.
+    pub fn update_frame(&mut self, section_id: SectionID, url: &str, frame: SlicedProtocol) {
+        if let Some(section) = self.sections.iter_mut().find(|s| s.id == section_id)
+            && let SectionContent::Image(link, proto, ..) = &mut section.content
+            && link.url == url
+        {
+            *proto = frame;
+        }
+    }
+
     pub fn update_header(&mut self, section_id: SectionID, rows: Vec<(String, u8, Protocol)>) {
         if rows.is_empty() {
             log::error!("update_header: empty rows for section #{section_id}");
@@ -192,6 +395,21 @@ impl Document {
         cache
     }
 
+    /// Revert images to placeholders, so that they are not cached on reparse.
+    pub fn forget_images(&mut self, urls: &[String]) {
+        for section in &mut self.sections {
+            if let SectionContent::Image(link, ..) = &section.content
+                && urls.contains(&link.url)
+            {
+                section.content = SectionContent::ImagePlaceholder(link.clone(), vec![]);
+                // Not kept by an incremental reparse.
+                self.sources.remove(&section.id);
+                self.total_lines = self.total_lines.saturating_sub(section.height) + 1;
+                section.height = 1;
+            }
+        }
+    }
+
     pub fn trim(&mut self, last_section_id: Option<usize>) {
         let Some(last_section_id) = last_section_id else {
             log::warn!("Document::trim without last_section_id, nothing parsed");
@@ -214,6 +432,24 @@ impl Document {
         }
     }
 
+    // The markdown source byte offset of the line at `y`, if known.
+    pub fn source_offset(&self, y: u16) -> Option<usize> {
+        self.line_source(y).map(|line| line.start)
+    }
+
+    // The markdown source byte range of the line at `y`, if known.
+    fn line_source(&self, y: u16) -> Option<&Range<usize>> {
+        let mut top = 0;
+        for section in &self.sections {
+            if y < top + section.height {
+                let source = self.sources.get(&section.id)?;
+                return Some(source_of_line(source, y - top));
+            }
+            top += section.height;
+        }
+        None
+    }
+
     pub fn get_y(&self, CursorPointer { id, index }: &CursorPointer) -> Option<i16> {
         let mut y = 0;
         for section in &self.sections {
@@ -261,6 +497,9 @@ impl Document {
         scroll: u16,
     ) -> Option<CursorPointer> {
         let locate = move |section: &Section| -> Option<(u16, CursorPointer)> {
+            if let Some(pointer) = Document::section_cursor(section, &target) {
+                return Some((0, pointer));
+            }
             if let SectionContent::Lines(lines) = &section.content {
                 let mut flat_index = 0;
                 for (line_y, (_, extras)) in lines.iter().enumerate() {
@@ -373,7 +612,7 @@ impl Document {
                         .collect();
                     Either::Left(Either::Left(flattened.into_iter()))
                 } else {
-                    Either::Right(std::iter::empty())
+                    Either::Right(Document::section_cursor(section, target).into_iter())
                 }
             }
             FindMode::Prev => {
@@ -396,12 +635,124 @@ impl Document {
                     flattened.reverse();
                     Either::Left(Either::Right(flattened.into_iter()))
                 } else {
-                    Either::Right(std::iter::empty())
+                    Either::Right(Document::section_cursor(section, target).into_iter())
+                }
+            }
+        }
+    }
+
+    // Images are selected as a whole, by their section.
+    fn section_cursor(section: &Section, target: &FindTarget) -> Option<CursorPointer> {
+        (matches!(target, FindTarget::Link) && matches!(section.content, SectionContent::Image(..)))
+            .then_some(CursorPointer {
+                id: section.id,
+                index: 0,
+            })
+    }
+
+    /// Find the link under a document position, e.g. for a mouse click.
+    ///
+    /// Mirrors the overlay areas of `link_overlays` in the view: a link that wraps over multiple
+    /// lines has its `LineExtra` on the last line, and covers the preceding lines up to the width.
+    pub fn link_at(&self, x: u16, y: u16, width: u16) -> Option<CursorPointer> {
+        let mut section_y = 0;
+        for section in &self.sections {
+            if y >= section_y + section.height {
+                section_y += section.height;
+                continue;
+            }
+            let SectionContent::Lines(lines) = &section.content else {
+                return None;
+            };
+            let line_idx = usize::from(y - section_y);
+            let mut flat_index = 0;
+            for (idx, (_, extras)) in lines.iter().enumerate() {
+                if idx >= line_idx {
+                    let lines_down = idx - line_idx;
+                    for (i, extra) in extras.iter().enumerate() {
+                        let LineExtra::Link {
+                            start, end, lines, ..
+                        } = extra
+                        else {
+                            continue;
+                        };
+                        let lines_up = lines.unwrap_or_default();
+                        let (from, to) = match lines_down {
+                            0 if lines_up > 0 => (0, *end),
+                            0 => (*start, *end),
+                            n if n == lines_up => (*start, width),
+                            n if n < lines_up => (0, width),
+                            _ => continue,
+                        };
+                        if (from..to).contains(&x) {
+                            return Some(CursorPointer {
+                                id: section.id,
+                                index: flat_index + i,
+                            });
+                        }
+                    }
                 }
+                flat_index += extras.len();
+            }
+            return None;
+        }
+        None
+    }
+
+    /// Find the section at a document line, and the line where it starts.
+    pub fn section_at(&self, y: u16) -> Option<(u16, &Section)> {
+        let mut section_y = 0;
+        for section in &self.sections {
+            if y < section_y + section.height {
+                return Some((section_y, section));
+            }
+            section_y += section.height;
+        }
+        None
+    }
+
+    /// Find the image of a section, e.g. selected with the link cursor.
+    pub fn image_by_id(&self, id: SectionID) -> Option<&MarkdownLink> {
+        self.sections
+            .iter()
+            .find_map(|section| match &section.content {
+                SectionContent::Image(link, ..) if section.id == id => Some(link),
+                _ => None,
+            })
+    }
+
+    /// Find the image under a document position.
+    pub fn image_at(&self, y: u16) -> Option<&MarkdownLink> {
+        match &self.section_at(y)?.1.content {
+            SectionContent::Image(link, ..) | SectionContent::ImagePlaceholder(link, _) => {
+                Some(link)
             }
+            _ => None,
         }
     }
 
+    /// Find the task checkbox at a document position, for the link cursor.
+    pub fn task_at(&self, x: u16, y: u16) -> Option<CursorPointer> {
+        let (top, section) = self.section_at(y)?;
+        let SectionContent::Lines(lines) = &section.content else {
+            return None;
+        };
+        let line_idx = usize::from(y - top);
+        let flat_index: usize = lines
+            .iter()
+            .take(line_idx)
+            .map(|(_, extras)| extras.len())
+            .sum();
+        let (_, extras) = lines.get(line_idx)?;
+        extras
+            .iter()
+            .position(|extra| matches!(extra, LineExtra::Task { start, end } if (*start..*end).contains(&x)))
+            .map(|i| CursorPointer {
+                id: section.id,
+                index: flat_index + i,
+            })
+    }
+
     #[cfg(test)]
     pub fn find_extra_by_cursor(&self, pointer: &CursorPointer) -> Option<&LineExtra> {
         for section in self.iter() {
@@ -431,32 +782,44 @@ impl Document {
 
     // Update all link URLs that point to the link reference definition.
     pub fn update_link_references(&mut self, definition_id: String, url: &str) {
-        for section in &mut self.sections {
-            let SectionContent::Lines(lines) = &mut section.content else {
-                continue;
-            };
-            for (_, extras) in lines {
-                for extra in extras {
-                    let LineExtra::Link {
-                        source, reference, ..
-                    } = extra
-                    else {
-                        continue;
-                    };
-                    let LinkReference::Reference { id } = reference else {
-                        continue;
-                    };
-                    if *id == definition_id {
-                        *source = SourceContent::from(url);
-                    }
-                }
-            }
-        }
+        resolve_link_references(&mut self.sections, &definition_id, url);
     }
 
     pub fn total_lines(&self) -> u16 {
         self.total_lines
     }
+
+    // The position and plain text of every section, to find what changed after a reload.
+    pub fn snapshot(&self) -> Vec<SectionSnapshot> {
+        let mut snapshot: Vec<SectionSnapshot> = Vec::new();
+        let mut y = 0;
+        let mut last_id = None;
+        for section in &self.sections {
+            // Header images are split into multiple sections with the same id.
+            if last_id == Some(section.id)
+                && let Some(last) = snapshot.last_mut()
+            {
+                last.height += section.height;
+                // Without repeating the tier.
+                last.text
+                    .extend(section.content.plain_text().chars().skip(1));
+            } else {
+                snapshot.push(SectionSnapshot {
+                    id: section.id,
+                    source: self
+                        .sources
+                        .get(&section.id)
+                        .map(|source| source.range.clone()),
+                    y,
+                    height: section.height,
+                    text: section.content.plain_text(),
+                });
+            }
+            y += section.height;
+            last_id = Some(section.id);
+        }
+        snapshot
+    }
 }
 
 impl Deref for Document {
@@ -486,7 +849,7 @@ pub enum FindTarget {
 impl FindTarget {
     fn matches(&self, extra: &LineExtra) -> bool {
         match self {
-            FindTarget::Link => matches!(extra, LineExtra::Link { .. }),
+            FindTarget::Link => matches!(extra, LineExtra::Link { .. } | LineExtra::Task { .. }),
             FindTarget::Search => matches!(extra, LineExtra::SearchMatch(..)),
         }
     }
@@ -527,6 +890,24 @@ impl SectionContent {
         // TODO: search in headers
     }
 
+    // The text content, regardless of whether images or header images have been loaded.
+    pub fn plain_text(&self) -> String {
+        match self {
+            Self::Image(link, ..) | Self::ImagePlaceholder(link, _) => link.url.clone(),
+            // Header image rows are split at arbitrary whitespace.
+            Self::Header(text, tier, _) | Self::HeaderPlaceholder(text, tier, _) => {
+                let mut plain = tier.to_string();
+                plain.extend(text.chars().filter(|c| !c.is_whitespace()));
+                plain
+            }
+            Self::Lines(lines) | Self::Code(_, lines) => lines
+                .iter()
+                .map(|(line, _)| line.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
     #[expect(clippy::string_slice)] // Regex byte ranges are guaranteed to fall between characters.
     fn regex_to_searchmatch(line_string: &str) -> impl Fn(Match<'_>) -> LineExtra {
         |m: Match| {
@@ -666,6 +1047,8 @@ pub enum LineExtra {
     },
     /// start, end, text
     SearchMatch(usize, usize, String),
+    /// The checkbox of a task list item, selectable with the link cursor.
+    Task { start: u16, end: u16 },
 }
 
 impl Debug for LineExtra {
@@ -691,6 +1074,7 @@ impl Debug for LineExtra {
             LineExtra::SearchMatch(start, end, text) => {
                 write!(f, "SearchMatch({}, {}, {:?})", start, end, text)
             }
+            LineExtra::Task { start, end } => write!(f, "Task({}, {})", start, end),
         }
     }
 }
@@ -718,6 +1102,9 @@ impl PartialEq for LineExtra {
             (LineExtra::SearchMatch(l0, l1, l2), LineExtra::SearchMatch(r0, r1, r2)) => {
                 l0 == r0 && l1 == r1 && l2 == r2
             }
+            (LineExtra::Task { start: l0, end: l1 }, LineExtra::Task { start: r0, end: r1 }) => {
+                l0 == r0 && l1 == r1
+            }
             _ => false,
         }
     }
@@ -730,6 +1117,31 @@ pub enum LinkReference {
     ReferenceDefinition { id: String, url: String },
 }
 
+/// Point the links to `definition_id`, e.g. `[1]`, at `url`.
+pub fn resolve_link_references(sections: &mut [Section], definition_id: &str, url: &str) {
+    for section in sections {
+        let SectionContent::Lines(lines) = &mut section.content else {
+            continue;
+        };
+        for (_, extras) in lines {
+            for extra in extras {
+                let LineExtra::Link {
+                    source, reference, ..
+                } = extra
+                else {
+                    continue;
+                };
+                let LinkReference::Reference { id } = reference else {
+                    continue;
+                };
+                if *id == definition_id {
+                    *source = SourceContent::from(url);
+                }
+            }
+        }
+    }
+}
+
 /// Layout/shape and render `text` into a list of [`DynamicImage`] with a given terminal width.
 pub fn header_images(
     font_renderer: &mut FontRenderer,
@@ -842,7 +1254,7 @@ pub fn header_images(
     Ok(dyn_imgs)
 }
 
-const HEADER_ROW_COUNT: u16 = 2;
+pub const HEADER_ROW_COUNT: u16 = 2;
 
 /// Render a list of images to [`Section`]s.
 pub fn header_sections(
@@ -881,61 +1293,42 @@ pub async fn image_section(
     width: u16,
     document_source: SharedDocumentSource,
     client: Arc<RwLock<Client>>,
+    remote_images: RemoteImages,
     id: SectionID,
     link: MarkdownLink,
     deep_fry_meme: bool,
     fontdb: Option<Arc<Database>>,
-) -> Result<Section, Error> {
-    let link_url = &link.url;
-    let image_source = if link_url.starts_with("https://") || link_url.starts_with("http://") {
-        download_image(client, fontdb, link_url).await?
-    } else {
-        let image_source: Option<ImageSource> = match document_source.read() {
-            Ok(DocumentSource::File {
-                basepath: Some(basepath),
-                ..
-            }) => {
-                let path = basepath.join(link_url).to_str().map(String::from);
-                path.map(ImageSource::Path)
-            }
-            Ok(DocumentSource::Github { repo, branch }) => {
-                if let Ok(repo_url) = github_usercontent_url(&repo, &branch, link_url) {
-                    Some(download_image(client, fontdb, repo_url.as_str()).await?)
-                } else {
-                    None
-                }
-            }
-            Ok(DocumentSource::HyperText { url }) => {
-                if let Ok(extended_url) = extend_url(url.clone(), link_url) {
-                    Some(download_image(client, fontdb, extended_url.as_str()).await?)
-                } else {
-                    None
-                }
-            }
-            _ => None,
-        };
-        image_source.unwrap_or_else(|| ImageSource::Path(link_url.to_owned()))
-    };
+    animate: bool,
+) -> Result<(Section, Option<Animation>), Error> {
+    let image_source =
+        fetch_image(document_source, client, remote_images, &link.url, fontdb).await?;
 
     // Now do all the blocking stuff
     let picker = picker.clone();
     let section = tokio::task::spawn_blocking(move || {
-        let mut dyn_img = match image_source {
-            ImageSource::Bytes(bytes, format) => {
-                ImageReader::with_format(std::io::Cursor::new(bytes), format).decode()?
-            }
-            ImageSource::Path(path) => ImageReader::open(path)?.decode()?,
-            ImageSource::DynamicImage(dyn_img) => dyn_img,
+        let frames = if animate {
+            animation_frames(&image_source).unwrap_or_else(|err| {
+                log::warn!("animation of {}: {err}", link.url);
+                None
+            })
+        } else {
+            None
         };
-
-        if deep_fry_meme {
-            dyn_img = deep_fry(dyn_img);
-        }
-
-        let max_width: u16 = (max_height * 3 / 2).min(width);
-        let max_resize_size = Size::new(max_width, max_height);
-        let size = Resize::Fit(None).size_for(&dyn_img, picker.font_size(), max_resize_size);
-
+        let dyn_img = decode_image(image_source, deep_fry_meme)?;
+        let size = image_size(&picker, &dyn_img, width, max_height);
+        let animation = frames.and_then(|frames| {
+            let frames = frames.map_while(Result::ok).map(|frame| {
+                let delay = Duration::from(frame.delay());
+                let dyn_img = DynamicImage::ImageRgba8(frame.into_buffer());
+                let dyn_img = if deep_fry_meme {
+                    deep_fry(dyn_img)
+                } else {
+                    dyn_img
+                };
+                (dyn_img, delay)
+            });
+            Animation::new(frames, picker.font_size(), size)
+        });
         let max_size = Size::new(width, max_height);
         let sliced = SlicedProtocol::new_with_resize(
             &picker,
@@ -943,38 +1336,199 @@ pub async fn image_section(
             size,
             Resize::Fit(Some(FilterType::Lanczos3)),
         )?;
-        Ok::<Section, Error>(Section {
+        let section = Section {
             id,
             height: size.height,
             content: SectionContent::Image(link, sliced, size, max_size),
-        })
+        };
+        Ok::<_, Error>((section, animation))
     })
     .await??;
     Ok(section)
 }
 
+/// Fetch or read, and decode the image of a markdown link.
+///
+/// Images are only fetched as `remote_images` allows.
+pub async fn load_image(
+    document_source: SharedDocumentSource,
+    client: Arc<RwLock<Client>>,
+    remote_images: RemoteImages,
+    link_url: &str,
+    deep_fry_meme: bool,
+    fontdb: Option<Arc<Database>>,
+) -> Result<DynamicImage, Error> {
+    let image_source =
+        fetch_image(document_source, client, remote_images, link_url, fontdb).await?;
+    let dyn_img =
+        tokio::task::spawn_blocking(move || decode_image(image_source, deep_fry_meme)).await??;
+    Ok(dyn_img)
+}
+
+async fn fetch_image(
+    document_source: SharedDocumentSource,
+    client: Arc<RwLock<Client>>,
+    remote_images: RemoteImages,
+    link_url: &str,
+    fontdb: Option<Arc<Database>>,
+) -> Result<ImageSource, Error> {
+    let document_source = document_source.read();
+    if let Some(url) = document_source
+        .as_ref()
+        .ok()
+        .and_then(|source| remote_image_url(source, link_url))
+    {
+        return download_image(client, remote_images, fontdb, &url).await;
+    }
+    let image_source: Option<ImageSource> = match document_source {
+        Ok(DocumentSource::File {
+            basepath: Some(basepath),
+            ..
+        }) => {
+            let path = basepath.join(link_url).to_str().map(String::from);
+            path.map(ImageSource::Path)
+        }
+        Ok(DocumentSource::Git { repo, rev, path }) => {
+            if let Some(path) = git::link_path(&path, link_url) {
+                Some(git_image(repo, rev, path, fontdb).await?)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    Ok(image_source.unwrap_or_else(|| ImageSource::Path(link_url.to_owned())))
+}
+
+// The URL that the image of a markdown link is downloaded from, if it is remote.
+fn remote_image_url(document_source: &DocumentSource, link_url: &str) -> Option<String> {
+    if link_url.starts_with("https://") || link_url.starts_with("http://") {
+        return Some(link_url.to_owned());
+    }
+    match document_source {
+        DocumentSource::Github { repo, branch, path } => {
+            github::link_url(repo, branch, path, link_url)
+                .ok()
+                .map(String::from)
+        }
+        DocumentSource::Forge {
+            forge,
+            repo,
+            branch,
+            path,
+        } => forge
+            .link_url(repo, branch, path, link_url)
+            .ok()
+            .map(String::from),
+        DocumentSource::HyperText { url } => extend_url(url.clone(), link_url).ok(),
+        _ => None,
+    }
+}
+
+/// Whether the image of a markdown link is remote and fresh in the HTTP cache, so that it loads
+/// without waiting for the network.
+pub fn is_image_cached(
+    document_source: &SharedDocumentSource,
+    remote_images: RemoteImages,
+    link_url: &str,
+) -> bool {
+    remote_images == RemoteImages::Always
+        && document_source
+            .read()
+            .ok()
+            .and_then(|source| remote_image_url(&source, link_url))
+            .is_some_and(|url| http_cache::is_fresh(&url))
+}
+
+// Decode the image, the first frame if animated.
+fn decode_image(image_source: ImageSource, deep_fry_meme: bool) -> Result<DynamicImage, Error> {
+    let mut dyn_img = match image_source {
+        ImageSource::Bytes(bytes, format) => {
+            ImageReader::with_format(std::io::Cursor::new(bytes), format).decode()?
+        }
+        ImageSource::Path(path) => ImageReader::open(path)?.decode()?,
+        ImageSource::DynamicImage(dyn_img) => dyn_img,
+    };
+    if deep_fry_meme {
+        dyn_img = deep_fry(dyn_img);
+    }
+    Ok(dyn_img)
+}
+
+// The frames of an animated GIF, APNG or WebP, or `None` if the image is not animated.
+fn animation_frames(image_source: &ImageSource) -> Result<Option<Frames<'static>>, Error> {
+    let bytes = match image_source {
+        ImageSource::Bytes(bytes, _) => bytes.clone(),
+        ImageSource::Path(path) => std::fs::read(path)?,
+        ImageSource::DynamicImage(_) => return Ok(None),
+    };
+    let format = image::guess_format(&bytes)?;
+    let reader = std::io::Cursor::new(bytes);
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(reader)?.into_frames(),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader)?;
+            if !decoder.is_apng()? {
+                return Ok(None);
+            }
+            decoder.apng()?.into_frames()
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(reader)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames()
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(frames))
+}
+
+/// The size in cells of an image of a markdown link, within `width` and `max_height`.
+pub fn image_size(picker: &Picker, dyn_img: &DynamicImage, width: u16, max_height: u16) -> Size {
+    let max_width: u16 = (max_height * 3 / 2).min(width);
+    let max_resize_size = Size::new(max_width, max_height);
+    Resize::Fit(None).size_for(dyn_img, picker.font_size(), max_resize_size)
+}
+
 async fn download_image(
     client: Arc<RwLock<Client>>,
+    remote_images: RemoteImages,
     #[cfg_attr(not(feature = "svg"), allow(unused_variables))] fontdb: Option<Arc<Database>>,
     url: &str,
 ) -> Result<ImageSource, Error> {
+    match remote_images {
+        RemoteImages::Always => {}
+        RemoteImages::Ask => {
+            return Err(Error::ImageLoad(
+                url.to_owned(),
+                "remote image, press I to load".to_owned(),
+            ));
+        }
+        RemoteImages::Never => {
+            return Err(Error::ImageLoad(
+                url.to_owned(),
+                "remote images are disabled".to_owned(),
+            ));
+        }
+    }
     let mut headers = HeaderMap::new();
-    headers.insert(ACCEPT, HeaderValue::from_static("image/png,image/jpg")); // or "image/jpeg"
+    headers.insert(
+        ACCEPT,
+        HeaderValue::from_static("image/png,image/jpeg,image/gif,image/webp"),
+    );
     let client = client.read().await;
-    let response = client.get(url).headers(headers).send().await?;
+    let response = http_cache::get(&client, url, headers).await?;
     drop(client);
-    if !response.status().is_success() {
+    if !response.status.is_success() {
         return Err(Error::ImageLoad(
             url.to_owned(),
-            format!("status {}", response.status()),
+            format!("status {}", response.status),
         ));
     }
-    let ct = response
-        .headers()
-        .get(CONTENT_TYPE)
-        .and_then(|h| h.to_str().ok());
 
-    let Some(ct) = ct else {
+    let Some(ct) = response.content_type.as_deref() else {
         return Err(Error::ImageLoad(
             url.to_owned(),
             "no content-type".to_owned(),
@@ -991,8 +1545,7 @@ async fn download_image(
                     "svg feature enabled but no fontdb at runtime".to_owned(),
                 ));
             };
-            let bytes = response.bytes().await?.to_vec();
-            let dyn_img = svg_to_rgba(&bytes, fontdb)?;
+            let dyn_img = svg_to_rgba(&response.body, fontdb)?;
             Ok(ImageSource::DynamicImage(dyn_img))
         }
         #[cfg(not(feature = "svg"))]
@@ -1012,9 +1565,37 @@ async fn download_image(
             )),
         }?;
 
-        let bytes = response.bytes().await?.to_vec();
-        Ok(ImageSource::Bytes(bytes, format))
+        Ok(ImageSource::Bytes(response.body, format))
+    }
+}
+
+// Read an image at a revision of a git repository, which has no content-type to go by.
+async fn git_image(
+    repo: PathBuf,
+    rev: String,
+    path: String,
+    #[cfg_attr(not(feature = "svg"), allow(unused_variables))] fontdb: Option<Arc<Database>>,
+) -> Result<ImageSource, Error> {
+    let name = format!("git:{rev}:{path}");
+    let bytes = tokio::task::spawn_blocking(move || git::read(&repo, &rev, &path)).await??;
+    if let Ok(format) = image::guess_format(&bytes) {
+        return Ok(ImageSource::Bytes(bytes, format));
+    }
+    if !name.ends_with(".svg") {
+        return Err(Error::ImageLoad(name, "unknown image format".to_owned()));
+    }
+    #[cfg(feature = "svg")]
+    {
+        let Some(fontdb) = fontdb else {
+            return Err(Error::ImageLoad(
+                name,
+                "svg feature enabled but no fontdb at runtime".to_owned(),
+            ));
+        };
+        Ok(ImageSource::DynamicImage(svg_to_rgba(&bytes, fontdb)?))
     }
+    #[cfg(not(feature = "svg"))]
+    Err(Error::ImageLoad(name, "svg feature not enabled".to_owned()))
 }
 
 #[cfg(feature = "svg")]
@@ -1092,6 +1673,7 @@ fn deep_fry(mut dyn_img: DynamicImage) -> DynamicImage {
 #[cfg(test)]
 mod tests {
 
+    use image::{Frame, ImageFormat, Rgba, RgbaImage, codecs::gif::GifEncoder};
     use ratatui::{
         style::Stylize as _,
         text::{Line, Span},
@@ -1100,7 +1682,7 @@ mod tests {
 
     use crate::{
         cursor::CursorPointer,
-        document::{Document, LineExtra, SectionContent},
+        document::{Document, ImageSource, LineExtra, SectionContent, SectionSource},
         *,
     };
 
@@ -1109,6 +1691,88 @@ mod tests {
         debug::init_test_logger();
     }
 
+    #[test]
+    fn source_offset() {
+        let mut doc = Document::default();
+        for (id, height, source) in [(0, 1, 0..6), (1, 3, 8..30), (2, 4, 32..48)] {
+            doc.push(Section {
+                id,
+                height,
+                content: SectionContent::Lines(Vec::new()),
+            });
+            doc.set_source(
+                id,
+                SectionSource {
+                    lines: vec![source.start..source.start + 2, source.start + 3..source.end],
+                    range: source,
+                },
+            );
+        }
+        assert_eq!(doc.source_offset(0), Some(0));
+        assert_eq!(doc.source_offset(1), Some(8));
+        assert_eq!(doc.source_offset(2), Some(11));
+        // Past the section's source lines, e.g. an image.
+        assert_eq!(doc.source_offset(3), Some(11));
+        assert_eq!(doc.source_offset(4), Some(32));
+        assert_eq!(doc.source_offset(8), None);
+    }
+
+    #[test]
+    #[expect(clippy::unwrap_used)]
+    fn animation_frames() {
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+                let image = RgbaImage::from_pixel(4, 4, Rgba(color));
+                encoder.encode_frame(Frame::new(image)).unwrap();
+            }
+        }
+        let frames = super::animation_frames(&ImageSource::Bytes(gif, ImageFormat::Gif))
+            .unwrap()
+            .unwrap();
+        assert_eq!(frames.count(), 2);
+
+        let still = include_bytes!("../assets/NixOS.png").to_vec();
+        assert!(
+            super::animation_frames(&ImageSource::Bytes(still, ImageFormat::Png))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn changes() {
+        let mut doc = Document::default();
+        // A removed and an added paragraph with a blank line between, and an added paragraph.
+        let lines = [0..10, 20..20, 20..30, 30..40, 40..40, 50..60];
+        for (id, line) in lines.iter().enumerate() {
+            doc.push(Section {
+                id,
+                height: 1,
+                content: SectionContent::Lines(Vec::new()),
+            });
+            doc.set_source(
+                id,
+                SectionSource {
+                    range: line.clone(),
+                    lines: vec![line.clone()],
+                },
+            );
+        }
+        doc.set_changes(HashMap::from([
+            (100..110, BlockChange::Removed),
+            (0..10, BlockChange::Removed),
+            (20..30, BlockChange::Added),
+            (50..60, BlockChange::Added),
+        ]));
+        assert_eq!(doc.change(0), Some(BlockChange::Removed));
+        assert_eq!(doc.change(1), None);
+        assert_eq!(doc.change(2), Some(BlockChange::Added));
+        assert_eq!(doc.change(3), None);
+        assert_eq!(doc.change_starts(), vec![0, 5]);
+    }
+
     #[test]
     fn widgestsources_update() {
         let mut ws = Document::default();
@@ -2,7 +2,10 @@ use core::fmt;
 use std::{
     error::Error as _,
     io,
-    sync::{PoisonError, mpsc::SendError},
+    sync::{
+        PoisonError,
+        mpsc::{RecvError, SendError},
+    },
 };
 
 use color_eyre::eyre::InstallError;
@@ -24,6 +27,12 @@ pub enum Error {
     Io(io::Error),
     UrlParse(Option<url::ParseError>),
     Watch(String),
+    // A failed git command, with its stderr.
+    Git(String),
+    // A request that `--offline` prevented, for the URL.
+    Offline(String),
+    // A response over `max_body_megabytes`, for the URL.
+    ResponseTooLarge(String),
     ThreadClosed,
     Thread(String),
 
@@ -71,6 +80,11 @@ impl fmt::Display for Error {
             }
             Error::Io(err) => write!(f, "I/O error: {err}"),
             Error::Watch(msg) => write!(f, "Watch error: {msg}"),
+            Error::Git(msg) => write!(f, "Git error: {msg}"),
+            Error::Offline(url) => write!(f, "Offline, not fetching {url}"),
+            Error::ResponseTooLarge(url) => {
+                write!(f, "Response of {url} is over max_body_megabytes")
+            }
             Error::Image(err) => write!(f, "Image manipulation error: {err}"),
             Error::Protocol(err) => write!(f, "Terminal graphics error: {err}"),
             Error::Download(err) => write!(f, "HTTP request error: {err}"),
@@ -98,12 +112,24 @@ impl fmt::Display for Error {
 #[derive(Debug)]
 pub enum CommandError {
     UnknownCommand(String),
+    NotEditable,
+    NoRevisions,
+    Editor(String),
+    UnknownExportFormat(String),
 }
 
 impl fmt::Display for CommandError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             CommandError::UnknownCommand(cmd) => write!(f, "Unknown command: {cmd}"),
+            CommandError::NotEditable => write!(f, "Only files can be edited"),
+            CommandError::NoRevisions => {
+                write!(f, "Only files in a git repository have revisions")
+            }
+            CommandError::Editor(status) => write!(f, "Editor failed: {status}"),
+            CommandError::UnknownExportFormat(path) => {
+                write!(f, "Unknown export format: {path}, try .html, .png or .svg")
+            }
         }
     }
 }
@@ -183,6 +209,12 @@ impl From<SendError<Event>> for Error {
     }
 }
 
+impl From<RecvError> for Error {
+    fn from(_: RecvError) -> Self {
+        Self::ThreadClosed
+    }
+}
+
 impl From<SendError<Cmd>> for Error {
     fn from(err: SendError<Cmd>) -> Self {
         Self::Thread(format!("SendError<Cmd>: {err}"))
@@ -240,6 +272,12 @@ impl From<arborium::Error> for Error {
     }
 }
 
+impl From<arborium_theme::ThemeError> for Error {
+    fn from(value: arborium_theme::ThemeError) -> Self {
+        Self::CodeHighlight(format!("{value}"))
+    }
+}
+
 impl From<ansi_to_tui::Error> for Error {
     fn from(value: ansi_to_tui::Error) -> Self {
         Self::CodeHighlight(format!("{value}"))
@@ -0,0 +1,319 @@
+mod html;
+mod png;
+mod svg;
+
+use std::{borrow::Cow, collections::HashMap, path::Path};
+
+use image::DynamicImage;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Rect, Size},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Paragraph, Widget as _},
+};
+use unicode_width::UnicodeWidthStr as _;
+
+use crate::{
+    config::Theme,
+    document::{Section, SectionContent, SectionID},
+    error::Error,
+    model::Model,
+};
+
+pub use html::html;
+pub use png::png;
+pub use svg::svg;
+
+// The formats of `:write` and `--export`. The image formats draw their text with a font renderer,
+// which the worker adds to the format that was asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat<R = ()> {
+    Html,
+    Png(R),
+    Svg(R),
+}
+
+impl ExportFormat {
+    // The format of a `--export` value, or of a file extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "html" | "htm" => Some(ExportFormat::Html),
+            "png" => Some(ExportFormat::Png(())),
+            "svg" => Some(ExportFormat::Svg(())),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str().and_then(Self::from_extension)
+    }
+
+    // The same format, with a font renderer from `renderer` if it is an image format.
+    pub fn with_renderer<R, F: FnOnce() -> R>(self, renderer: F) -> ExportFormat<R> {
+        match self {
+            ExportFormat::Html => ExportFormat::Html,
+            ExportFormat::Png(()) => ExportFormat::Png(renderer()),
+            ExportFormat::Svg(()) => ExportFormat::Svg(renderer()),
+        }
+    }
+}
+
+// A rendered image that replaces the lines of a section: an image, a diagram, or a header row.
+pub struct Figure {
+    pub image: DynamicImage,
+    // The size in cells, as the view would draw it.
+    pub size: Size,
+}
+
+type Rgb = (u8, u8, u8);
+
+// The page colors, where the document has none.
+const BACKGROUND: Rgb = (0x1e, 0x1e, 0x1e);
+const FOREGROUND: Rgb = (0xd4, 0xd4, 0xd4);
+
+// Export the whole document to stdout once it is parsed, for `--export`.
+pub fn to_stdout(mut model: Model, format: ExportFormat) -> Result<(), Error> {
+    model.wait_loaded()?;
+    // E.g. an unknown code theme, that the worker fell back from.
+    if let Some(err) = model.last_error.take() {
+        eprintln!("{err}");
+    }
+    model.export(format, None)?;
+    model.wait_exported()
+}
+
+fn header_style(theme: &Theme) -> Style {
+    theme
+        .header_color
+        .map_or_else(Style::default, |color| Style::default().fg(color))
+}
+
+// Whether the view leaves a blank line after the figures of a section.
+fn trailing_blank(content: &SectionContent) -> bool {
+    match content {
+        SectionContent::ImagePlaceholder(_, lines) => {
+            lines.last().is_some_and(|(line, _)| line.spans.is_empty())
+        }
+        // Diagrams come from code blocks.
+        SectionContent::Code(..) => true,
+        _ => false,
+    }
+}
+
+// Lay out the sections into a buffer `width` cells wide, as the view draws them, and the rows of
+// the figures to draw over it.
+fn layout<'a>(
+    width: u16,
+    sections: &[Section],
+    figures: &'a HashMap<SectionID, Vec<Figure>>,
+    theme: &Theme,
+) -> (Buffer, Vec<(u16, &'a Figure)>) {
+    enum Row<'a> {
+        Line(Line<'static>),
+        Figure(&'a Figure),
+    }
+
+    let header_style = header_style(theme);
+    let mut rows = Vec::new();
+    for section in sections {
+        if let Some(section_figures) = figures.get(&section.id) {
+            rows.extend(section_figures.iter().map(Row::Figure));
+            if trailing_blank(&section.content) {
+                rows.push(Row::Line(Line::default()));
+            }
+            continue;
+        }
+        match &section.content {
+            SectionContent::Lines(lines)
+            | SectionContent::Code(_, lines)
+            | SectionContent::ImagePlaceholder(_, lines) => {
+                rows.extend(lines.iter().map(|(line, _)| Row::Line(line.clone())));
+            }
+            SectionContent::HeaderPlaceholder(_, _, lines) => {
+                rows.extend(
+                    lines
+                        .iter()
+                        .map(|(line, _)| Row::Line(line.clone().patch_style(header_style))),
+                );
+                rows.push(Row::Line(Line::default()));
+            }
+            SectionContent::Header(text, _, _) => {
+                rows.push(Row::Line(Line::styled(text.clone(), header_style.bold())));
+                rows.push(Row::Line(Line::default()));
+            }
+            // Exports load images as figures.
+            SectionContent::Image(..) => {}
+        }
+    }
+
+    let height = rows
+        .iter()
+        .map(|row| match row {
+            Row::Line(_) => 1,
+            Row::Figure(figure) => figure.size.height,
+        })
+        .fold(0_u16, u16::saturating_add);
+    let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+    let mut placed = Vec::new();
+    let mut y: u16 = 0;
+    for row in rows {
+        match row {
+            Row::Line(line) => {
+                Paragraph::new(line).render(Rect::new(0, y, width, 1), &mut buf);
+                y = y.saturating_add(1);
+            }
+            Row::Figure(figure) => {
+                placed.push((y, figure));
+                y = y.saturating_add(figure.size.height);
+            }
+        }
+    }
+    (buf, placed)
+}
+
+// Consecutive cells of a buffer row with the same style.
+struct Run {
+    x: u16,
+    width: u16,
+    text: String,
+    fg: Rgb,
+    bg: Option<Rgb>,
+    modifier: Modifier,
+}
+
+fn runs(buf: &Buffer, y: u16) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    let mut to_skip = 0;
+    for x in buf.area.left()..buf.area.right() {
+        if to_skip > 0 {
+            to_skip -= 1;
+            continue;
+        }
+        let cell = &buf[(x, y)];
+        let width = cell.symbol().width().max(1) as u16;
+        to_skip = width - 1;
+        let (fg, bg) = colors(cell.fg, cell.bg, cell.modifier);
+        let fg = fg.unwrap_or(FOREGROUND);
+        match runs.last_mut() {
+            Some(run) if (run.fg, run.bg, run.modifier) == (fg, bg, cell.modifier) => {
+                run.text.push_str(cell.symbol());
+                run.width += width;
+            }
+            _ => runs.push(Run {
+                x,
+                width,
+                text: cell.symbol().to_owned(),
+                fg,
+                bg,
+                modifier: cell.modifier,
+            }),
+        }
+    }
+    runs
+}
+
+// The xterm colors of the 16 named colors.
+const PALETTE: [Rgb; 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x00, 0x00),
+    (0x00, 0xcd, 0x00),
+    (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee),
+    (0xcd, 0x00, 0xcd),
+    (0x00, 0xcd, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+
+fn rgb(color: Color) -> Option<Rgb> {
+    Some(match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index) => indexed_rgb(index),
+        Color::Black => PALETTE[0],
+        Color::Red => PALETTE[1],
+        Color::Green => PALETTE[2],
+        Color::Yellow => PALETTE[3],
+        Color::Blue => PALETTE[4],
+        Color::Magenta => PALETTE[5],
+        Color::Cyan => PALETTE[6],
+        Color::Gray => PALETTE[7],
+        Color::DarkGray => PALETTE[8],
+        Color::LightRed => PALETTE[9],
+        Color::LightGreen => PALETTE[10],
+        Color::LightYellow => PALETTE[11],
+        Color::LightBlue => PALETTE[12],
+        Color::LightMagenta => PALETTE[13],
+        Color::LightCyan => PALETTE[14],
+        Color::White => PALETTE[15],
+    })
+}
+
+fn hex((r, g, b): Rgb) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// The foreground and background of a cell, reversed or not.
+fn colors(fg: Color, bg: Color, modifier: Modifier) -> (Option<Rgb>, Option<Rgb>) {
+    let (fg, bg) = (rgb(fg), rgb(bg));
+    if modifier.contains(Modifier::REVERSED) {
+        (
+            Some(bg.unwrap_or(BACKGROUND)),
+            Some(fg.unwrap_or(FOREGROUND)),
+        )
+    } else {
+        (fg, bg)
+    }
+}
+
+// The xterm 256 color palette.
+fn indexed_rgb(index: u8) -> Rgb {
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..16 => PALETTE[usize::from(index)],
+        16..232 => {
+            let index = usize::from(index - 16);
+            (CUBE[index / 36], CUBE[index / 6 % 6], CUBE[index % 6])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;"),
+    )
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use ratatui::style::Color;
+
+    use super::{hex, rgb};
+
+    #[test]
+    fn xterm_colors() {
+        assert_eq!(hex(rgb(Color::Rgb(1, 2, 255)).unwrap()), "#0102ff");
+        assert_eq!(hex(rgb(Color::Indexed(196)).unwrap()), "#ff0000");
+        assert_eq!(hex(rgb(Color::Indexed(244)).unwrap()), "#808080");
+        assert_eq!(rgb(Color::Reset), None);
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    collections::HashMap,
+    io::{Cursor, Write},
+};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use image::ImageFormat;
+use ratatui::{
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::Line,
+};
+use unicode_width::UnicodeWidthChar as _;
+use url::Url;
+
+use crate::{
+    config::Theme,
+    document::{LineExtra, Section, SectionContent, SectionID},
+    error::Error,
+    export::{BACKGROUND, FOREGROUND, Figure, colors, escape, header_style, hex, trailing_blank},
+};
+
+// Write the sections as a standalone HTML page, `width` cells wide.
+pub fn html<W: Write>(
+    out: &mut W,
+    title: &str,
+    width: u16,
+    sections: &[Section],
+    figures: &HashMap<SectionID, Vec<Figure>>,
+    theme: &Theme,
+) -> Result<(), Error> {
+    write!(
+        out,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+:root {{ --line: 1.25em; }}
+body {{ background: {background}; color: {foreground}; font-family: monospace; width: {width}ch; margin: 2em auto; }}
+.l {{ white-space: pre; height: var(--line); line-height: var(--line); overflow: hidden; }}
+.h {{ white-space: pre; height: calc(2 * var(--line)); line-height: calc(2 * var(--line)); }}
+.f img {{ display: block; max-width: 100%; object-fit: contain; object-position: left; }}
+a {{ color: inherit; }}
+</style>
+</head>
+<body>
+"#,
+        title = escape(title),
+        background = hex(BACKGROUND),
+        foreground = hex(FOREGROUND),
+    )?;
+
+    let header_style = header_style(theme);
+    for section in sections {
+        if let Some(section_figures) = figures.get(&section.id) {
+            let alt = match &section.content {
+                SectionContent::ImagePlaceholder(link, _) => link.description.as_str(),
+                SectionContent::HeaderPlaceholder(text, _, _) => text,
+                SectionContent::Code(language, _) => language,
+                _ => "",
+            };
+            for figure in section_figures {
+                write_figure(out, figure, alt)?;
+            }
+            if trailing_blank(&section.content) {
+                writeln!(out, r#"<div class="l"></div>"#)?;
+            }
+            continue;
+        }
+        match &section.content {
+            SectionContent::Lines(lines)
+            | SectionContent::Code(_, lines)
+            | SectionContent::ImagePlaceholder(_, lines) => {
+                write_lines(out, lines, Style::default())?;
+            }
+            SectionContent::HeaderPlaceholder(_, _, lines) => {
+                write_lines(out, lines, header_style)?;
+                writeln!(out, r#"<div class="l"></div>"#)?;
+            }
+            SectionContent::Header(text, tier, _) => {
+                // Like the header images, scaled down by tier.
+                let scale = f32::from(12 - tier) / 12.0_f32 * 2.0;
+                writeln!(
+                    out,
+                    r#"<div class="h" style="{}; font-size: {scale:.2}em">{}</div>"#,
+                    css(header_style),
+                    escape(text),
+                )?;
+            }
+            // Exports load images as figures.
+            SectionContent::Image(..) => {}
+        }
+    }
+
+    writeln!(out, "</body>\n</html>")?;
+    out.flush()?;
+    Ok(())
+}
+
+fn write_figure<W: Write>(out: &mut W, figure: &Figure, alt: &str) -> Result<(), Error> {
+    let mut png = Vec::new();
+    figure
+        .image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    writeln!(
+        out,
+        r#"<div class="f"><img alt="{}" style="width: {}ch; height: calc({} * var(--line))" src="data:image/png;base64,{}"></div>"#,
+        escape(alt),
+        figure.size.width,
+        figure.size.height,
+        STANDARD.encode(&png),
+    )?;
+    Ok(())
+}
+
+fn write_lines<W: Write>(
+    out: &mut W,
+    lines: &[(Line<'static>, Vec<LineExtra>)],
+    style: Style,
+) -> Result<(), Error> {
+    let links = line_links(lines);
+    for ((line, _), links) in lines.iter().zip(links) {
+        write_line(out, line, &links, style)?;
+    }
+    Ok(())
+}
+
+// The column ranges and URLs of the links on each line, see `view::link_overlays`.
+fn line_links<'a>(lines: &'a [(Line<'static>, Vec<LineExtra>)]) -> Vec<Vec<(u16, u16, &'a str)>> {
+    let mut links = vec![Vec::new(); lines.len()];
+    for (idx, (_, extras)) in lines.iter().enumerate() {
+        for extra in extras {
+            let LineExtra::Link {
+                source,
+                start,
+                end,
+                lines: count,
+                ..
+            } = extra
+            else {
+                continue;
+            };
+            let url: &str = source;
+            if !is_safe_url(url) {
+                continue;
+            }
+            // A wrapped link starts `count` lines up.
+            let count = count.unwrap_or_default();
+            for previous in 1..=count {
+                let Some(previous_links) = idx.checked_sub(previous).and_then(|i| links.get_mut(i))
+                else {
+                    break;
+                };
+                let start = if previous == count { *start } else { 0 };
+                previous_links.push((start, u16::MAX, url));
+            }
+            let start = if count > 0 { 0 } else { *start };
+            if !(start == 0 && *end == 0) {
+                links[idx].push((start, *end, url));
+            }
+        }
+    }
+    links
+}
+
+// Whether a link stays a link in the exported page: web and mail links, and relative links.
+// Others, like `javascript:` or `data:`, are plain text, as the page may be shared.
+fn is_safe_url(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => matches!(url.scheme(), "http" | "https" | "mailto"),
+        Err(err) => err == url::ParseError::RelativeUrlWithoutBase,
+    }
+}
+
+fn write_line<W: Write>(
+    out: &mut W,
+    line: &Line<'static>,
+    links: &[(u16, u16, &str)],
+    style: Style,
+) -> Result<(), Error> {
+    let align = match line.alignment {
+        Some(Alignment::Center) => r#" style="text-align: center""#,
+        Some(Alignment::Right) => r#" style="text-align: right""#,
+        _ => "",
+    };
+    write!(out, r#"<div class="l"{align}>"#)?;
+
+    let mut column: u16 = 0;
+    let mut link = None;
+    for span in &line.spans {
+        let span_css = css(style.patch(line.style).patch(span.style));
+        let mut run = String::new();
+        for c in span.content.chars() {
+            let char_link = links
+                .iter()
+                .find(|(start, end, _)| (*start..*end).contains(&column))
+                .map(|(_, _, url)| *url);
+            if char_link != link {
+                write_run(out, &span_css, &run)?;
+                run.clear();
+                if link.is_some() {
+                    write!(out, "</a>")?;
+                }
+                if let Some(url) = char_link {
+                    write!(out, r#"<a href="{}">"#, escape(url))?;
+                }
+                link = char_link;
+            }
+            run.push(c);
+            column = column.saturating_add(c.width().unwrap_or_default() as u16);
+        }
+        write_run(out, &span_css, &run)?;
+    }
+    if link.is_some() {
+        write!(out, "</a>")?;
+    }
+    writeln!(out, "</div>")?;
+    Ok(())
+}
+
+fn write_run<W: Write>(out: &mut W, css: &str, text: &str) -> Result<(), Error> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    if css.is_empty() {
+        write!(out, "{}", escape(text))?;
+    } else {
+        write!(out, r#"<span style="{css}">{}</span>"#, escape(text))?;
+    }
+    Ok(())
+}
+
+// The inline CSS of a style.
+fn css(style: Style) -> String {
+    let modifier = style.add_modifier - style.sub_modifier;
+    let (fg, bg) = colors(
+        style.fg.unwrap_or(Color::Reset),
+        style.bg.unwrap_or(Color::Reset),
+        modifier,
+    );
+    let (fg, bg) = (fg.map(hex), bg.map(hex));
+
+    let mut properties = Vec::new();
+    if let Some(fg) = fg {
+        properties.push(format!("color: {fg}"));
+    }
+    if let Some(bg) = bg {
+        properties.push(format!("background: {bg}"));
+    }
+    if modifier.contains(Modifier::BOLD) {
+        properties.push("font-weight: bold".to_owned());
+    }
+    if modifier.contains(Modifier::DIM) {
+        properties.push("opacity: 0.6".to_owned());
+    }
+    if modifier.contains(Modifier::ITALIC) {
+        properties.push("font-style: italic".to_owned());
+    }
+    let decorations: Vec<&str> = [
+        (Modifier::UNDERLINED, "underline"),
+        (Modifier::CROSSED_OUT, "line-through"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| modifier.contains(*flag))
+    .map(|(_, decoration)| decoration)
+    .collect();
+    if !decorations.is_empty() {
+        properties.push(format!("text-decoration: {}", decorations.join(" ")));
+    }
+    if modifier.contains(Modifier::HIDDEN) {
+        properties.push("visibility: hidden".to_owned());
+    }
+    properties.join("; ")
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::collections::HashMap;
+
+    use mdfrier::MdFrier;
+
+    use crate::{config::Theme, export::html, worker::sections::SectionIterator};
+
+    fn export(width: u16, text: &str) -> String {
+        let theme = Theme::default();
+        let document = MdFrier::new().unwrap().parse_document(text).unwrap();
+        let sections: Vec<_> =
+            SectionIterator::new(document.layout(width, &theme), &theme).collect();
+        let mut out = Vec::new();
+        html(&mut out, "test", width, &sections, &HashMap::new(), &theme).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn links_stay_active() {
+        let out = export(80, "See [the docs](https://example.com/?a=1&b=2) <now>.\n");
+        assert!(
+            out.contains(r#"<a href="https://example.com/?a=1&amp;b=2">"#),
+            "{out}"
+        );
+        assert!(out.contains("&lt;now&gt;"), "{out}");
+    }
+
+    #[test]
+    fn unsafe_links_are_text() {
+        let out = export(
+            80,
+            "[a](javascript:alert(1)) [b](data:text/html,hi) [c](VBScript:x) [d](JavaScript:x) \
+            [e](mailto:a@example.com) [f](other.md) [g](#top)\n",
+        );
+        assert_eq!(out.matches("<a href=").count(), 3, "{out}");
+        for url in ["mailto:a@example.com", "other.md", "#top"] {
+            assert!(out.contains(&format!(r#"<a href="{url}">"#)), "{out}");
+        }
+    }
+
+    #[test]
+    fn wrapped_links() {
+        let out = export(
+            20,
+            "Some text and [a link that wraps](https://example.com) here.\n",
+        );
+        assert_eq!(
+            out.matches(r#"<a href="https://example.com">"#).count(),
+            out.matches("</a>").count(),
+            "{out}"
+        );
+        assert!(
+            out.matches(r#"<a href="https://example.com">"#).count() > 1,
+            "{out}"
+        );
+    }
+}
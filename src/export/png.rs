@@ -0,0 +1,223 @@
+use std::{collections::HashMap, io::Write};
+
+use cosmic_text::{Attrs, Buffer, Family, Metrics, Shaping, Style as FontStyle, Weight};
+use image::{
+    ExtendedColorType, ImageEncoder as _, Pixel as _, Rgba, RgbaImage,
+    codecs::png::PngEncoder,
+    imageops::{self, FilterType},
+};
+use ratatui::style::Modifier;
+use unicode_width::UnicodeWidthStr as _;
+
+use crate::{
+    config::Theme,
+    document::{Section, SectionID},
+    error::Error,
+    export::{BACKGROUND, Figure, Run, layout, runs},
+    setup::FontRenderer,
+};
+
+// Write the sections as one tall PNG image, each cell drawn at the terminal font size of
+// `font_renderer`, and the figures composited at their cell positions.
+pub fn png<W: Write>(
+    out: &mut W,
+    font_renderer: &mut FontRenderer,
+    width: u16,
+    sections: &[Section],
+    figures: &HashMap<SectionID, Vec<Figure>>,
+    theme: &Theme,
+) -> Result<(), Error> {
+    let (buf, placed) = layout(width, sections, figures, theme);
+    let cell_width = u32::from(font_renderer.font_size.width);
+    let cell_height = u32::from(font_renderer.font_size.height);
+    let (r, g, b) = BACKGROUND;
+    let mut img = RgbaImage::from_pixel(
+        u32::from(buf.area.width) * cell_width,
+        u32::from(buf.area.height) * cell_height,
+        Rgba([r, g, b, 255]),
+    );
+
+    // Monospace fonts are about 0.6 as wide as their size.
+    let font_size = (cell_width as f32 / 0.6).min(cell_height as f32);
+    let metrics = Metrics::new(font_size, cell_height as f32);
+    for y in buf.area.top()..buf.area.bottom() {
+        for run in runs(&buf, y) {
+            draw_run(&mut img, font_renderer, metrics, &run, u32::from(y));
+        }
+    }
+
+    for (y, figure) in placed {
+        let image = figure.image.resize(
+            u32::from(figure.size.width) * cell_width,
+            u32::from(figure.size.height) * cell_height,
+            FilterType::Lanczos3,
+        );
+        imageops::overlay(
+            &mut img,
+            &image.to_rgba8(),
+            0,
+            i64::from(y) * i64::from(cell_height),
+        );
+    }
+
+    PngEncoder::new(out).write_image(
+        img.as_raw(),
+        img.width(),
+        img.height(),
+        ExtendedColorType::Rgba8,
+    )?;
+    Ok(())
+}
+
+fn draw_run(
+    img: &mut RgbaImage,
+    font_renderer: &mut FontRenderer,
+    metrics: Metrics,
+    run: &Run,
+    row: u32,
+) {
+    let cell_width = u32::from(font_renderer.font_size.width);
+    let cell_height = u32::from(font_renderer.font_size.height);
+    let left = u32::from(run.x) * cell_width;
+    let top = row * cell_height;
+    let run_width = u32::from(run.width) * cell_width;
+
+    if let Some((r, g, b)) = run.bg {
+        for y in top..top + cell_height {
+            for x in left..left + run_width {
+                img.put_pixel(x, y, Rgba([r, g, b, 255]));
+            }
+        }
+    }
+    if run.modifier.contains(Modifier::HIDDEN) {
+        return;
+    }
+
+    let (r, g, b) = run.fg;
+    let alpha = if run.modifier.contains(Modifier::DIM) {
+        153
+    } else {
+        255
+    };
+    let fg = cosmic_text::Color::rgba(r, g, b, alpha);
+    for (flag, y) in [
+        (Modifier::UNDERLINED, top + cell_height - 2),
+        (Modifier::CROSSED_OUT, top + cell_height / 2),
+    ] {
+        if run.modifier.contains(flag) {
+            for x in left..left + run_width {
+                blend(img, x as i32, y as i32, fg);
+            }
+        }
+    }
+    if run.text.trim().is_empty() {
+        return;
+    }
+
+    let FontRenderer {
+        font_system,
+        swash_cache,
+        font_name,
+        ..
+    } = font_renderer;
+    let attrs = Attrs::new()
+        .family(Family::Name(font_name))
+        .weight(if run.modifier.contains(Modifier::BOLD) {
+            Weight::BOLD
+        } else {
+            Weight::NORMAL
+        })
+        .style(if run.modifier.contains(Modifier::ITALIC) {
+            FontStyle::Italic
+        } else {
+            FontStyle::Normal
+        });
+    let mut buffer = Buffer::new(font_system, metrics);
+    buffer.set_size(font_system, None, None);
+    buffer.set_text(font_system, &run.text, &attrs, Shaping::Advanced, None);
+    buffer.shape_until_scroll(font_system, false);
+
+    for layout_run in buffer.layout_runs() {
+        for glyph in layout_run.glyphs {
+            // Each glyph goes to the cell of its text, whatever the advance of the font.
+            let column = run
+                .text
+                .get(..glyph.start)
+                .map_or(0, |text| text.width() as u32);
+            let x = (left + column * cell_width) as f32 - glyph.x;
+            let y = top as f32 + layout_run.line_y;
+            let physical = glyph.physical((x, y), 1.0);
+            swash_cache.with_pixels(font_system, physical.cache_key, fg, |dx, dy, color| {
+                blend(img, physical.x + dx, physical.y + dy, color);
+            });
+        }
+    }
+}
+
+fn blend(img: &mut RgbaImage, x: i32, y: i32, color: cosmic_text::Color) {
+    let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+        return;
+    };
+    if x >= img.width() || y >= img.height() || color.a() == 0 {
+        return;
+    }
+    img.get_pixel_mut(x, y).blend(&Rgba(color.as_rgba()));
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cosmic_text::{Attrs, Buffer, Family, Metrics, Shaping};
+    use mdfrier::MdFrier;
+    use ratatui_image::FontSize;
+
+    use crate::{
+        config::Theme, export::png, setup::export_font_renderer, worker::sections::SectionIterator,
+    };
+
+    #[test]
+    fn glyphs_fill_cells() {
+        let theme = Theme::default();
+        let mut font_renderer = export_font_renderer(None, None, FontSize::new(10, 20));
+
+        // The bundled font is found by its own family name, and advances one cell per glyph.
+        let metrics = Metrics::new(10.0 / 0.6, 20.0);
+        let font_system = &mut font_renderer.font_system;
+        let mut buffer = Buffer::new(font_system, metrics);
+        let attrs = Attrs::new().family(Family::Name(&font_renderer.font_name));
+        buffer.set_text(font_system, "mmmm", &attrs, Shaping::Advanced, None);
+        buffer.shape_until_scroll(font_system, false);
+        let line_w = buffer.layout_runs().next().unwrap().line_w;
+        assert!((line_w - 40.0).abs() < 1.0, "advance {line_w}");
+        let face = buffer.layout_runs().next().unwrap().glyphs[0].font_id;
+        let families = &font_system.db().face(face).unwrap().families;
+        assert_eq!(
+            families[0].0, font_renderer.font_name,
+            "rendered with a fallback font"
+        );
+
+        let document = MdFrier::new().unwrap().parse_document("mmmm\n").unwrap();
+        let sections: Vec<_> = SectionIterator::new(document.layout(8, &theme), &theme).collect();
+        let mut out = Vec::new();
+        png(
+            &mut out,
+            &mut font_renderer,
+            8,
+            &sections,
+            &HashMap::new(),
+            &theme,
+        )
+        .unwrap();
+        let img = image::load_from_memory(&out).unwrap().to_rgba8();
+        assert_eq!((img.width(), img.height()), (80, 20), "8x1 cells of 10x20");
+        let background = *img.get_pixel(79, 0);
+        let inked = |cell: u32| {
+            (cell * 10..(cell + 1) * 10)
+                .any(|x| (0..20).any(|y| *img.get_pixel(x, y) != background))
+        };
+        assert!((0..4).all(inked), "a glyph in each of the first 4 cells");
+        assert!(!(4..8).any(inked), "no ink past the text");
+    }
+}
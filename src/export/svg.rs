@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    io::{Cursor, Write},
+};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use image::ImageFormat;
+use ratatui::style::Modifier;
+use ratatui_image::FontSize;
+
+use crate::{
+    config::Theme,
+    document::{Section, SectionID},
+    error::Error,
+    export::{BACKGROUND, Figure, escape, hex, layout, runs},
+};
+
+// Write the sections as an SVG image, with text in `font_name`, each cell the terminal
+// `font_size`, and the figures embedded at their cell positions.
+pub fn svg<W: Write>(
+    out: &mut W,
+    font_name: &str,
+    font_size: FontSize,
+    width: u16,
+    sections: &[Section],
+    figures: &HashMap<SectionID, Vec<Figure>>,
+    theme: &Theme,
+) -> Result<(), Error> {
+    let (buf, placed) = layout(width, sections, figures, theme);
+    let cell_width = u32::from(font_size.width);
+    let cell_height = u32::from(font_size.height);
+    let image_width = u32::from(buf.area.width) * cell_width;
+    let image_height = u32::from(buf.area.height) * cell_height;
+    // Monospace fonts are about 0.6 as wide as their size.
+    let text_size = (cell_width as f32 / 0.6).min(cell_height as f32);
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{image_width}" height="{image_height}" viewBox="0 0 {image_width} {image_height}" font-family="{}, monospace" font-size="{text_size:.1}">"#,
+        escape(font_name),
+    )?;
+    writeln!(
+        out,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        hex(BACKGROUND)
+    )?;
+
+    for y in buf.area.top()..buf.area.bottom() {
+        let top = u32::from(y) * cell_height;
+        for run in runs(&buf, y) {
+            let left = u32::from(run.x) * cell_width;
+            let run_width = u32::from(run.width) * cell_width;
+            if let Some(bg) = run.bg {
+                writeln!(
+                    out,
+                    r#"<rect x="{left}" y="{top}" width="{run_width}" height="{cell_height}" fill="{}"/>"#,
+                    hex(bg)
+                )?;
+            }
+            if run.text.trim().is_empty() || run.modifier.contains(Modifier::HIDDEN) {
+                continue;
+            }
+            let mut attributes = vec![format!(r#"fill="{}""#, hex(run.fg))];
+            if run.modifier.contains(Modifier::BOLD) {
+                attributes.push(r#"font-weight="bold""#.to_owned());
+            }
+            if run.modifier.contains(Modifier::ITALIC) {
+                attributes.push(r#"font-style="italic""#.to_owned());
+            }
+            if run.modifier.contains(Modifier::DIM) {
+                attributes.push(r#"fill-opacity="0.6""#.to_owned());
+            }
+            let decorations: Vec<&str> = [
+                (Modifier::UNDERLINED, "underline"),
+                (Modifier::CROSSED_OUT, "line-through"),
+            ]
+            .into_iter()
+            .filter(|(flag, _)| run.modifier.contains(*flag))
+            .map(|(_, decoration)| decoration)
+            .collect();
+            if !decorations.is_empty() {
+                attributes.push(format!(r#"text-decoration="{}""#, decorations.join(" ")));
+            }
+            // Stretched to the cells, whatever the advance of the font.
+            writeln!(
+                out,
+                r#"<text x="{left}" y="{}" textLength="{run_width}" lengthAdjust="spacingAndGlyphs" xml:space="preserve" {}>{}</text>"#,
+                top + cell_height * 4 / 5,
+                attributes.join(" "),
+                escape(&run.text),
+            )?;
+        }
+    }
+
+    for (y, figure) in placed {
+        let mut png = Vec::new();
+        figure
+            .image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        writeln!(
+            out,
+            r#"<image x="0" y="{}" width="{}" height="{}" preserveAspectRatio="xMinYMid meet" href="data:image/png;base64,{}"/>"#,
+            u32::from(y) * cell_height,
+            u32::from(figure.size.width) * cell_width,
+            u32::from(figure.size.height) * cell_height,
+            STANDARD.encode(&png),
+        )?;
+    }
+
+    writeln!(out, "</svg>")?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::collections::HashMap;
+
+    use mdfrier::MdFrier;
+    use ratatui_image::FontSize;
+
+    use crate::{config::Theme, export::svg, worker::sections::SectionIterator};
+
+    #[test]
+    fn cells_and_escapes() {
+        let theme = Theme::default();
+        let document = MdFrier::new()
+            .unwrap()
+            .parse_document("A [link](https://example.com) <here>.\n")
+            .unwrap();
+        let sections: Vec<_> = SectionIterator::new(document.layout(40, &theme), &theme).collect();
+        let mut out = Vec::new();
+        svg(
+            &mut out,
+            "Mono",
+            FontSize::new(10, 20),
+            40,
+            &sections,
+            &HashMap::new(),
+            &theme,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(r#"width="400" height="20""#), "{out}");
+        assert!(
+            out.contains(r#"text-decoration="underline">link</text>"#),
+            "{out}"
+        );
+        assert!(out.contains("&lt;here&gt;"), "{out}");
+    }
+}
@@ -0,0 +1,380 @@
+use std::{process::Command, sync::OnceLock, time::Duration};
+
+use reqwest::{
+    Proxy,
+    header::{HeaderMap, HeaderName, HeaderValue},
+    redirect,
+};
+use url::Url;
+
+use crate::{
+    VERSION,
+    config::{
+        DEFAULT_CONNECT_TIMEOUT_SECONDS, DEFAULT_MAX_BODY_MEGABYTES, DEFAULT_TIMEOUT_SECONDS,
+        HttpConfig, HttpHeader,
+    },
+    error::Error,
+};
+
+// Like reqwest's default redirect policy.
+const MAX_REDIRECTS: usize = 10;
+
+// The `[http]` config of all requests, or the defaults if not set, e.g. in tests.
+static HTTP: OnceLock<Http> = OnceLock::new();
+
+pub fn init(config: HttpConfig) {
+    #[expect(unused_must_use)]
+    HTTP.set(Http::new(config));
+}
+
+fn http() -> &'static Http {
+    HTTP.get_or_init(|| Http::new(HttpConfig::default()))
+}
+
+// The client of images, see `worker_thread`.
+pub fn client() -> Result<reqwest::Client, Error> {
+    http().client()
+}
+
+// The client of documents, which are fetched before the worker runs.
+pub fn blocking_client() -> Result<reqwest::blocking::Client, Error> {
+    http().blocking_client()
+}
+
+// The configured headers for the host of `url`.
+pub fn headers(url: &str) -> HeaderMap {
+    http().headers(url)
+}
+
+pub fn max_body_bytes() -> u64 {
+    http().max_body_bytes()
+}
+
+struct Http {
+    config: HttpConfig,
+    // The values of `config.headers`, resolved on first use, because a `command` may be slow.
+    values: Vec<OnceLock<Option<HeaderValue>>>,
+}
+
+impl Http {
+    fn new(config: HttpConfig) -> Self {
+        let values = config.headers.iter().map(|_| OnceLock::new()).collect();
+        Http { config, values }
+    }
+
+    fn user_agent() -> String {
+        format!(
+            "mdfried/{}",
+            VERSION.get().map_or("unknown", String::as_str)
+        )
+    }
+
+    fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(
+            self.config
+                .connect_timeout_seconds
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECONDS),
+        )
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(
+            self.config
+                .timeout_seconds
+                .unwrap_or(DEFAULT_TIMEOUT_SECONDS),
+        )
+    }
+
+    fn proxy(&self) -> Result<Option<Proxy>, Error> {
+        Ok(self.config.proxy.as_deref().map(Proxy::all).transpose()?)
+    }
+
+    // Redirects are followed, except from a host with configured headers to a host without, as
+    // reqwest only removes `Authorization`, `Cookie` and the like from the redirected request.
+    fn redirect_policy(&self) -> redirect::Policy {
+        let hosts: Vec<String> = self
+            .config
+            .headers
+            .iter()
+            .map(|header| header.host.clone())
+            .collect();
+        if hosts.is_empty() {
+            return redirect::Policy::default();
+        }
+        redirect::Policy::custom(move |attempt| {
+            let from = attempt.previous().last().and_then(Url::host_str);
+            let to = attempt.url().host_str();
+            let leaves_host = hosts.iter().any(|rule| {
+                from.is_some_and(|from| matches_host(rule, from))
+                    && !to.is_some_and(|to| matches_host(rule, to))
+            });
+            if leaves_host {
+                log::warn!(
+                    "not following the redirect from {} to {}, which would receive the configured headers",
+                    from.unwrap_or_default(),
+                    to.unwrap_or_default()
+                );
+                attempt.stop()
+            } else if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        })
+    }
+
+    fn client(&self) -> Result<reqwest::Client, Error> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(Self::user_agent())
+            .connect_timeout(self.connect_timeout())
+            .timeout(self.timeout())
+            .redirect(self.redirect_policy());
+        if let Some(proxy) = self.proxy()? {
+            builder = builder.proxy(proxy);
+        }
+
+        // Attempt to mitigate a test failure on darwin.
+        // `system-configuration` is traced to reqwest via:
+        //     cargo tree -i system-configuration --target all
+        // The tests shouldn't be doing any requests, so the client building here would be the
+        // most likely source of that panic.
+        // ```
+        // thread '<unnamed>' (103071) panicked at /nix/build/nix-5646-2352996470/mdfried-0.20.1-vendor/source-registry-0/system-configuration-0.5.1/src/dynamic_store.rs:154:1:
+        // Attempted to create a NULL object.
+        // ```
+        #[cfg(test)]
+        let builder = builder.no_proxy();
+
+        Ok(builder.build()?)
+    }
+
+    fn blocking_client(&self) -> Result<reqwest::blocking::Client, Error> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .user_agent(Self::user_agent())
+            .connect_timeout(self.connect_timeout())
+            .timeout(self.timeout())
+            .redirect(self.redirect_policy());
+        if let Some(proxy) = self.proxy()? {
+            builder = builder.proxy(proxy);
+        }
+        // Tests only request a local stub server.
+        #[cfg(test)]
+        let builder = builder.no_proxy();
+        Ok(builder.build()?)
+    }
+
+    fn headers(&self, url: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+        else {
+            return headers;
+        };
+        for (header, value) in self.config.headers.iter().zip(&self.values) {
+            if !matches_host(&header.host, &host) {
+                continue;
+            }
+            let Ok(name) = HeaderName::from_bytes(header.name.as_bytes()) else {
+                log::warn!("invalid header name: {}", header.name);
+                continue;
+            };
+            if let Some(value) = value.get_or_init(|| resolve(header)) {
+                headers.insert(name, value.clone());
+            }
+        }
+        headers
+    }
+
+    fn max_body_bytes(&self) -> u64 {
+        self.config
+            .max_body_megabytes
+            .unwrap_or(DEFAULT_MAX_BODY_MEGABYTES)
+            * 1024
+            * 1024
+    }
+}
+
+// Whether `host` is `rule_host` or one of its subdomains.
+fn matches_host(rule_host: &str, host: &str) -> bool {
+    host.eq_ignore_ascii_case(rule_host)
+        || host
+            .to_ascii_lowercase()
+            .strip_suffix(&rule_host.to_ascii_lowercase())
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+fn resolve(header: &HttpHeader) -> Option<HeaderValue> {
+    let secret = if let Some(env) = &header.env {
+        match std::env::var(env) {
+            Ok(secret) => Some(secret),
+            Err(err) => {
+                log::warn!("header {} for {}: ${env}: {err}", header.name, header.host);
+                return None;
+            }
+        }
+    } else if let Some(command) = &header.command {
+        match Command::new("sh").arg("-c").arg(command).output() {
+            Ok(output) if output.status.success() => {
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+            }
+            Ok(output) => {
+                log::warn!(
+                    "header {} for {}: `{command}` failed with {}",
+                    header.name,
+                    header.host,
+                    output.status
+                );
+                return None;
+            }
+            Err(err) => {
+                log::warn!(
+                    "header {} for {}: `{command}`: {err}",
+                    header.name,
+                    header.host
+                );
+                return None;
+            }
+        }
+    } else {
+        None
+    };
+    let value = match (&header.value, secret) {
+        (Some(value), Some(secret)) => value.replace("{}", &secret),
+        (Some(value), None) => value.clone(),
+        (None, Some(secret)) => secret,
+        (None, None) => {
+            log::warn!("header {} for {} has no value", header.name, header.host);
+            return None;
+        }
+    };
+    match HeaderValue::from_str(&value) {
+        Ok(mut value) => {
+            value.set_sensitive(true);
+            Some(value)
+        }
+        Err(err) => {
+            log::warn!("header {} for {}: {err}", header.name, header.host);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::{
+        config::{HttpConfig, HttpHeader},
+        testing,
+    };
+
+    use super::{Http, matches_host};
+
+    fn header(host: &str, value: Option<&str>, command: Option<&str>) -> HttpHeader {
+        HttpHeader {
+            host: host.to_owned(),
+            name: "Authorization".to_owned(),
+            value: value.map(str::to_owned),
+            env: None,
+            command: command.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn hosts() {
+        assert!(matches_host("example.com", "example.com"));
+        assert!(matches_host("example.com", "docs.Example.com"));
+        assert!(!matches_host("example.com", "badexample.com"));
+        assert!(!matches_host("docs.example.com", "example.com"));
+    }
+
+    #[test]
+    fn header_rules() {
+        let http = Http::new(HttpConfig {
+            headers: vec![
+                header("example.com", Some("Bearer {}"), Some("echo secret")),
+                header("other.com", Some("plain"), None),
+                header("failing.com", Some("Bearer {}"), Some("false")),
+            ],
+            ..Default::default()
+        });
+        let headers = http.headers("https://docs.example.com/README.md");
+        assert_eq!(headers["authorization"], "Bearer secret");
+        assert!(headers["authorization"].is_sensitive());
+        assert_eq!(http.headers("https://other.com/")["authorization"], "plain");
+        assert!(http.headers("https://failing.com/").is_empty());
+        assert!(http.headers("https://unrelated.com/").is_empty());
+    }
+
+    // A server that answers each request with the request headers as the body, after `delay`.
+    fn echo_server(delay: Duration) -> String {
+        testing::serve("/", move |request| {
+            thread::sleep(delay);
+            testing::response("200 OK", &[], request)
+        })
+    }
+
+    #[test]
+    fn blocking_client_sends_headers() {
+        let url = echo_server(Duration::ZERO);
+        let http = Http::new(HttpConfig {
+            headers: vec![header("127.0.0.1", Some("token"), None)],
+            ..Default::default()
+        });
+        let request = http
+            .blocking_client()
+            .unwrap()
+            .get(&url)
+            .headers(http.headers(&url))
+            .send()
+            .unwrap()
+            .text()
+            .unwrap();
+        assert!(request.contains("authorization: token"), "{request}");
+        assert!(request.contains("user-agent: mdfried/"), "{request}");
+    }
+
+    #[test]
+    fn redirects_keep_headers_on_host() {
+        let echo = echo_server(Duration::ZERO);
+        // Unlike `Authorization`, reqwest keeps other headers on redirects.
+        let http = Http::new(HttpConfig {
+            headers: vec![HttpHeader {
+                name: "Private-Token".to_owned(),
+                ..header("127.0.0.1", Some("token"), None)
+            }],
+            ..Default::default()
+        });
+        let client = http.blocking_client().unwrap();
+        let get = |location: String| {
+            let url = testing::serve("/", move |_| {
+                testing::response("302 Found", &[&format!("Location: {location}")], "")
+            });
+            client.get(&url).headers(http.headers(&url)).send().unwrap()
+        };
+
+        let request = get(echo.clone()).text().unwrap();
+        assert!(request.contains("private-token: token"), "{request}");
+        // The same server, by another name.
+        let response = get(echo.replace("127.0.0.1", "localhost"));
+        assert_eq!(response.status(), 302, "redirect to another host");
+    }
+
+    #[test]
+    fn timeout() {
+        let url = echo_server(Duration::from_secs(3));
+        let http = Http::new(HttpConfig {
+            timeout_seconds: Some(1),
+            ..Default::default()
+        });
+        let err = http
+            .blocking_client()
+            .unwrap()
+            .get(&url)
+            .send()
+            .unwrap_err();
+        assert!(err.is_timeout(), "{err}");
+    }
+}
@@ -0,0 +1,636 @@
+use std::{
+    fs,
+    io::Read as _,
+    path::PathBuf,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use directories::ProjectDirs;
+use reqwest::{
+    StatusCode,
+    header::{
+        CACHE_CONTROL, CONTENT_TYPE, ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, LAST_MODIFIED,
+    },
+};
+
+use crate::{error::Error, http};
+
+// The on-disk cache of remote documents and images, unset if disabled.
+pub static HTTP_CACHE: OnceLock<HttpCache> = OnceLock::new();
+
+// With `--offline`, only cached responses are used, however stale.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+pub fn go_offline() {
+    OFFLINE.store(true, Ordering::Relaxed);
+}
+
+// Cache the responses under the XDG cache dir, up to `max_megabytes`. With `refresh`, responses
+// are stored but never read back, as for `--refresh`.
+pub fn init(max_megabytes: u64, refresh: bool) {
+    if max_megabytes == 0 {
+        return;
+    }
+    let Some(dirs) = ProjectDirs::from("", "", "mdfried") else {
+        log::warn!("no cache directory, not caching HTTP responses");
+        return;
+    };
+    let cache = HttpCache::new(
+        dirs.cache_dir().join("http"),
+        max_megabytes * 1024 * 1024,
+        refresh,
+    );
+    #[expect(unused_must_use)]
+    HTTP_CACHE.set(cache);
+}
+
+// A response, from the network or from the cache.
+pub struct Response {
+    pub status: StatusCode,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn text(self) -> String {
+        String::from_utf8(self.body)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+    }
+}
+
+// GET `url`: from the cache while fresh, revalidated once stale or with `revalidate`, and the
+// stale copy if the request fails. The `[http]` headers of the host are added to `headers`.
+pub fn get_blocking(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    mut headers: HeaderMap,
+    revalidate: bool,
+) -> Result<Response, Error> {
+    let host_headers = http::headers(url);
+    let cache = shared_cache(&host_headers);
+    headers.extend(host_headers);
+    get_blocking_with(
+        cache,
+        client,
+        url,
+        headers,
+        revalidate,
+        http::max_body_bytes(),
+    )
+}
+
+fn get_blocking_with(
+    cache: Option<&HttpCache>,
+    client: &reqwest::blocking::Client,
+    url: &str,
+    headers: HeaderMap,
+    revalidate: bool,
+    max_body_bytes: u64,
+) -> Result<Response, Error> {
+    if OFFLINE.load(Ordering::Relaxed) {
+        return offline(cache, url);
+    }
+    let (headers, entry) = match prepare(cache, url, headers, revalidate) {
+        Prepared::Fresh(response) => return Ok(response),
+        Prepared::Request(headers, entry) => (headers, entry),
+    };
+    let result = client
+        .get(url)
+        .headers(headers)
+        .send()
+        .map_err(Error::from)
+        .and_then(|response| {
+            let status = response.status();
+            let headers = response.headers().clone();
+            let mut body = Vec::new();
+            response.take(max_body_bytes + 1).read_to_end(&mut body)?;
+            if body.len() as u64 > max_body_bytes {
+                return Err(Error::ResponseTooLarge(url.to_owned()));
+            }
+            Ok((status, headers, body))
+        });
+    respond(cache, url, entry, result)
+}
+
+// The async `get_blocking`.
+pub async fn get(
+    client: &reqwest::Client,
+    url: &str,
+    mut headers: HeaderMap,
+) -> Result<Response, Error> {
+    // A header `command` blocks.
+    let host_url = url.to_owned();
+    let host_headers = tokio::task::spawn_blocking(move || http::headers(&host_url)).await?;
+    let cache = shared_cache(&host_headers);
+    headers.extend(host_headers);
+    let max_body_bytes = http::max_body_bytes();
+    if OFFLINE.load(Ordering::Relaxed) {
+        return offline(cache, url);
+    }
+    let (headers, entry) = match prepare(cache, url, headers, false) {
+        Prepared::Fresh(response) => return Ok(response),
+        Prepared::Request(headers, entry) => (headers, entry),
+    };
+    let result = async {
+        let mut response = client.get(url).headers(headers).send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() as u64 > max_body_bytes {
+                return Err(Error::ResponseTooLarge(url.to_owned()));
+            }
+        }
+        Ok((status, headers, body))
+    }
+    .await;
+    respond(cache, url, entry, result)
+}
+
+// The cache, unless the request carries `[http]` headers: the cache is keyed by URL only, so
+// their responses, e.g. of private repositories, must not be stored nor served.
+fn shared_cache(host_headers: &HeaderMap) -> Option<&'static HttpCache> {
+    if host_headers.is_empty() {
+        HTTP_CACHE.get()
+    } else {
+        None
+    }
+}
+
+// Whether `url` has a cached response that `get` returns without the network.
+pub fn is_fresh(url: &str) -> bool {
+    HTTP_CACHE
+        .get()
+        .is_some_and(|cache| cache.is_fresh(url, OFFLINE.load(Ordering::Relaxed)))
+}
+
+fn offline(cache: Option<&HttpCache>, url: &str) -> Result<Response, Error> {
+    cache
+        .and_then(|cache| cache.lookup(url))
+        .map(Entry::into_response)
+        .ok_or_else(|| Error::Offline(url.to_owned()))
+}
+
+enum Prepared {
+    Fresh(Response),
+    // The request headers, with validators of the stale entry if any.
+    Request(HeaderMap, Option<Entry>),
+}
+
+fn prepare(
+    cache: Option<&HttpCache>,
+    url: &str,
+    mut headers: HeaderMap,
+    revalidate: bool,
+) -> Prepared {
+    let Some(entry) = cache.and_then(|cache| cache.lookup(url)) else {
+        return Prepared::Request(headers, None);
+    };
+    if !revalidate && entry.meta.fresh_until > now() {
+        log::debug!("cache hit: {url}");
+        return Prepared::Fresh(entry.into_response());
+    }
+    for (name, value) in [
+        (IF_NONE_MATCH, &entry.meta.etag),
+        (IF_MODIFIED_SINCE, &entry.meta.last_modified),
+    ] {
+        if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    Prepared::Request(headers, Some(entry))
+}
+
+fn respond(
+    cache: Option<&HttpCache>,
+    url: &str,
+    entry: Option<Entry>,
+    result: Result<(StatusCode, HeaderMap, Vec<u8>), Error>,
+) -> Result<Response, Error> {
+    match (result, entry) {
+        (Err(err), Some(entry)) => {
+            log::warn!("{url}: {err}, using the cached copy");
+            Ok(entry.into_response())
+        }
+        (Ok((status, _, _)), Some(entry)) if status.is_server_error() => {
+            log::warn!("{url}: status {status}, using the cached copy");
+            Ok(entry.into_response())
+        }
+        (Ok((StatusCode::NOT_MODIFIED, headers, _)), Some(mut entry)) => {
+            log::debug!("cache revalidated: {url}");
+            if let Some(cache) = cache
+                && let Err(err) = cache.revalidated(&mut entry, &headers)
+            {
+                log::warn!("could not update the cache of {url}: {err}");
+            }
+            Ok(entry.into_response())
+        }
+        (Err(err), None) => Err(err),
+        (Ok((status, headers, body)), _) => {
+            if status.is_success()
+                && let Some(cache) = cache
+                && let Err(err) = cache.store(url, &headers, &body)
+            {
+                log::warn!("could not cache {url}: {err}");
+            }
+            Ok(Response {
+                status,
+                content_type: header(&headers, CONTENT_TYPE),
+                body,
+            })
+        }
+    }
+}
+
+pub struct HttpCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    refresh: bool,
+}
+
+struct Entry {
+    meta: Meta,
+    body: Vec<u8>,
+}
+
+impl Entry {
+    fn into_response(self) -> Response {
+        Response {
+            status: StatusCode::OK,
+            content_type: self.meta.content_type,
+            body: self.body,
+        }
+    }
+}
+
+// What is stored next to a response body, one `name value` per line.
+#[derive(Debug, Default, PartialEq)]
+struct Meta {
+    url: String,
+    content_type: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    // Unix seconds.
+    fresh_until: u64,
+}
+
+impl Meta {
+    fn parse(text: &str) -> Meta {
+        let mut meta = Meta::default();
+        for (name, value) in text.lines().filter_map(|line| line.split_once(' ')) {
+            let value = value.to_owned();
+            match name {
+                "url" => meta.url = value,
+                "content-type" => meta.content_type = Some(value),
+                "etag" => meta.etag = Some(value),
+                "last-modified" => meta.last_modified = Some(value),
+                "fresh-until" => meta.fresh_until = value.parse().unwrap_or_default(),
+                _ => {}
+            }
+        }
+        meta
+    }
+
+    fn serialize(&self) -> String {
+        let mut lines = vec![format!("url {}", self.url)];
+        for (name, value) in [
+            ("content-type", &self.content_type),
+            ("etag", &self.etag),
+            ("last-modified", &self.last_modified),
+        ] {
+            if let Some(value) = value {
+                lines.push(format!("{name} {value}"));
+            }
+        }
+        lines.push(format!("fresh-until {}", self.fresh_until));
+        lines.join("\n")
+    }
+}
+
+impl HttpCache {
+    pub fn new(dir: PathBuf, max_bytes: u64, refresh: bool) -> Self {
+        HttpCache {
+            dir,
+            max_bytes,
+            refresh,
+        }
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = format!("{:016x}", fnv1a(url.as_bytes()));
+        (
+            self.dir.join(&key),
+            self.dir.join(key).with_extension("meta"),
+        )
+    }
+
+    fn lookup(&self, url: &str) -> Option<Entry> {
+        if self.refresh {
+            return None;
+        }
+        let (body_path, meta_path) = self.paths(url);
+        let meta = Meta::parse(&fs::read_to_string(&meta_path).ok()?);
+        if meta.url != url {
+            return None;
+        }
+        let body = fs::read(body_path).ok()?;
+        // Recently used entries are evicted last.
+        if let Ok(file) = fs::File::options().append(true).open(&meta_path)
+            && let Err(err) = file.set_modified(SystemTime::now())
+        {
+            log::debug!("could not touch {}: {err}", meta_path.display());
+        }
+        Some(Entry { meta, body })
+    }
+
+    // Like `lookup`, without reading the body. When `offline`, stale entries are used too.
+    fn is_fresh(&self, url: &str, offline: bool) -> bool {
+        if self.refresh {
+            return false;
+        }
+        let (body_path, meta_path) = self.paths(url);
+        fs::read_to_string(&meta_path).is_ok_and(|text| {
+            let meta = Meta::parse(&text);
+            meta.url == url && (offline || meta.fresh_until > now()) && body_path.exists()
+        })
+    }
+
+    fn store(&self, url: &str, headers: &HeaderMap, body: &[u8]) -> Result<(), Error> {
+        let Some(fresh_until) = fresh_until(headers) else {
+            return Ok(());
+        };
+        if body.len() as u64 > self.max_bytes {
+            return Ok(());
+        }
+        let meta = Meta {
+            url: url.to_owned(),
+            content_type: header(headers, CONTENT_TYPE),
+            etag: header(headers, ETAG),
+            last_modified: header(headers, LAST_MODIFIED),
+            fresh_until,
+        };
+        fs::create_dir_all(&self.dir)?;
+        let (body_path, meta_path) = self.paths(url);
+        fs::write(body_path, body)?;
+        fs::write(meta_path, meta.serialize())?;
+        self.evict()
+    }
+
+    // Refresh the entry after a `304 Not Modified`.
+    fn revalidated(&self, entry: &mut Entry, headers: &HeaderMap) -> Result<(), Error> {
+        let Some(fresh_until) = fresh_until(headers) else {
+            return Ok(());
+        };
+        entry.meta.fresh_until = fresh_until;
+        if let Some(etag) = header(headers, ETAG) {
+            entry.meta.etag = Some(etag);
+        }
+        let (_, meta_path) = self.paths(&entry.meta.url);
+        fs::write(meta_path, entry.meta.serialize())?;
+        Ok(())
+    }
+
+    // Remove the least recently used entries while over `max_bytes`.
+    fn evict(&self) -> Result<(), Error> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_some() {
+                continue;
+            }
+            let meta_path = path.with_extension("meta");
+            let size = fs::metadata(&path)?.len();
+            let used = fs::metadata(&meta_path)
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(UNIX_EPOCH);
+            total += size;
+            entries.push((used, size, path, meta_path));
+        }
+        entries.sort_by_key(|(used, ..)| *used);
+        for (_, size, path, meta_path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            log::debug!("evicting {}", path.display());
+            fs::remove_file(path)?;
+            // Bodies without meta are not found, so not used anyway.
+            let _: Result<(), _> = fs::remove_file(meta_path);
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+fn header(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+// Until when a response may be used without revalidating, or `None` if it must not be stored.
+fn fresh_until(headers: &HeaderMap) -> Option<u64> {
+    let now = now();
+    let mut max_age = 0;
+    for directive in headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+    {
+        let directive = directive.trim().to_ascii_lowercase();
+        // The cache is shared by everyone using the cache directory.
+        if directive == "no-store" || directive == "private" {
+            return None;
+        }
+        if directive == "no-cache" {
+            return Some(now);
+        }
+        if let Some(seconds) = directive.strip_prefix("max-age=") {
+            max_age = seconds.trim_matches('"').parse().unwrap_or(0);
+        }
+    }
+    Some(now + max_age)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+// A stable hash for file names, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use reqwest::header::{CACHE_CONTROL, ETAG, HeaderMap, HeaderValue};
+
+    use super::{
+        HttpCache, Meta, Prepared, fresh_until, get_blocking_with, now, offline, prepare, respond,
+    };
+    use crate::testing;
+
+    fn cache(name: &str, max_bytes: u64) -> HttpCache {
+        let dir = testing::temp_path(&format!("http_cache_{name}"));
+        let _: Result<(), _> = fs::remove_dir_all(&dir);
+        HttpCache::new(dir, max_bytes, false)
+    }
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    #[test]
+    fn meta_roundtrip() {
+        let meta = Meta {
+            url: "https://example.com/a b".to_owned(),
+            content_type: Some("image/png".to_owned()),
+            etag: Some(r#"W/"abc""#.to_owned()),
+            last_modified: None,
+            fresh_until: 123,
+        };
+        assert_eq!(Meta::parse(&meta.serialize()), meta);
+    }
+
+    #[test]
+    fn cache_control() {
+        let now = now();
+        assert_eq!(fresh_until(&HeaderMap::new()), Some(now));
+        assert_eq!(
+            fresh_until(&headers(&[(CACHE_CONTROL, "public, max-age=300")])),
+            Some(now + 300)
+        );
+        assert_eq!(
+            fresh_until(&headers(&[(CACHE_CONTROL, "max-age=300, no-cache")])),
+            Some(now)
+        );
+        assert_eq!(fresh_until(&headers(&[(CACHE_CONTROL, "no-store")])), None);
+        assert_eq!(
+            fresh_until(&headers(&[(CACHE_CONTROL, "private, max-age=300")])),
+            None
+        );
+    }
+
+    #[test]
+    fn fresh_and_stale() {
+        let cache = cache("fresh", 1024);
+        let url = "https://example.com/fresh.png";
+        cache
+            .store(url, &headers(&[(CACHE_CONTROL, "max-age=60")]), b"fresh")
+            .unwrap();
+        assert!(matches!(
+            prepare(Some(&cache), url, HeaderMap::new(), false),
+            Prepared::Fresh(response) if response.body == b"fresh"
+        ));
+        assert!(
+            cache.is_fresh(url, false),
+            "fresh entries load without the network"
+        );
+
+        let url = "https://example.com/stale.png";
+        cache
+            .store(url, &headers(&[(ETAG, "\"v1\"")]), b"stale")
+            .unwrap();
+        let Prepared::Request(request_headers, Some(entry)) =
+            prepare(Some(&cache), url, HeaderMap::new(), false)
+        else {
+            panic!("stale entries are revalidated");
+        };
+        assert_eq!(request_headers["if-none-match"], "\"v1\"");
+        assert!(
+            !cache.is_fresh(url, false),
+            "stale entries wait for the network"
+        );
+        assert!(cache.is_fresh(url, true), "stale entries are used offline");
+
+        // Offline, the stale copy is better than nothing.
+        let offline = Err(crate::error::Error::Offline(url.to_owned()));
+        let response = respond(Some(&cache), url, Some(entry), offline).unwrap();
+        assert_eq!(response.body, b"stale");
+    }
+
+    #[test]
+    fn offline_uses_stale_copies() {
+        let cache = cache("offline", 1024);
+        cache
+            .store("https://example.com/a.png", &HeaderMap::new(), b"stale")
+            .unwrap();
+        let response = offline(Some(&cache), "https://example.com/a.png").unwrap();
+        assert_eq!(response.body, b"stale");
+        assert!(matches!(
+            offline(Some(&cache), "https://example.com/b.png"),
+            Err(crate::error::Error::Offline(url)) if url == "https://example.com/b.png"
+        ));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache("evict", 10);
+        cache.store("a", &HeaderMap::new(), b"aaaa").unwrap();
+        cache.store("b", &HeaderMap::new(), b"bbbb").unwrap();
+        assert!(cache.lookup("a").is_some());
+        cache.store("c", &HeaderMap::new(), b"cccc").unwrap();
+        assert!(cache.lookup("a").is_some());
+        assert!(cache.lookup("b").is_none());
+        assert!(cache.lookup("c").is_some());
+    }
+
+    #[test]
+    fn revalidates_with_etag() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server_requests = requests.clone();
+        let url = testing::serve("/doc.md", move |request| {
+            server_requests.lock().unwrap().push(request.to_owned());
+            if request.contains("if-none-match: \"v1\"") {
+                testing::response("304 Not Modified", &[], "")
+            } else {
+                testing::response(
+                    "200 OK",
+                    &["ETag: \"v1\"", "Content-Type: text/markdown"],
+                    "# Cached",
+                )
+            }
+        });
+
+        let cache = cache("revalidate", 1024);
+        let client = reqwest::blocking::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap();
+        for _ in 0..2 {
+            let response =
+                get_blocking_with(Some(&cache), &client, &url, HeaderMap::new(), false, 1024)
+                    .unwrap();
+            assert_eq!(response.text(), "# Cached");
+        }
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        let too_large = get_blocking_with(None, &client, &url, HeaderMap::new(), false, 4);
+        assert!(matches!(
+            too_large,
+            Err(crate::error::Error::ResponseTooLarge(_))
+        ));
+    }
+}
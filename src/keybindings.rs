@@ -1,10 +1,16 @@
 use std::{
+    io,
     num::{NonZero, NonZeroU16},
+    process::Command,
     time::Duration,
 };
 
+use crossterm::clipboard::CopyToClipboard;
 use ratatui::{
-    crossterm::event::{self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEventKind},
+    crossterm::event::{
+        self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     layout::Size,
 };
 
@@ -20,12 +26,17 @@ pub enum PollResult {
     HadInput,
     /// Input was handled but don't render yet (e.g., reload triggered, wait for events)
     SkipRender,
+    /// Run a command, e.g. an editor, with the terminal restored, then reload.
+    Suspend(Command),
     Quit,
 }
 
 pub fn poll(had_events: bool, model: &mut Model) -> Result<PollResult, Error> {
     if event::poll(if had_events {
         Duration::ZERO
+    } else if model.is_animating() {
+        // The frames are events, which are processed between polls.
+        Duration::from_millis(10)
     } else {
         Duration::from_millis(100)
     })? {
@@ -39,29 +50,74 @@ pub fn poll(had_events: bool, model: &mut Model) -> Result<PollResult, Error> {
             event::Event::Resize(new_width, new_height) => {
                 log::debug!("Resize {new_width},{new_height}");
                 if model.screen_size.width != new_width || model.screen_size.height != new_height {
-                    let screen_size = Size::new(new_width, new_height);
+                    let height = if model.inline {
+                        model.screen_size.height.min(new_height)
+                    } else {
+                        new_height
+                    };
+                    let screen_size = Size::new(new_width, height);
                     model.reload(screen_size)?;
                     return Ok(PollResult::SkipRender);
                 }
                 return Ok(PollResult::None);
             }
-            event::Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollUp => {
-                    model.scroll_by(-2);
-                    return Ok(PollResult::HadInput);
-                }
-                MouseEventKind::ScrollDown => {
-                    model.scroll_by(2);
-                    return Ok(PollResult::HadInput);
-                }
-                _ => {}
-            },
+            event::Event::Mouse(mouse) => return match_mouse(mouse, model),
             _ => {}
         }
     }
     Ok(PollResult::None)
 }
 
+fn match_mouse(mouse: MouseEvent, model: &mut Model) -> Result<PollResult, Error> {
+    if model.image_viewer.is_some() {
+        return Ok(PollResult::None);
+    }
+    let on_scrollbar = model.config.scrollbar && mouse.column + 1 >= model.screen_size.width;
+    match mouse.kind {
+        MouseEventKind::ScrollUp => {
+            model.scroll_by(-2);
+        }
+        MouseEventKind::ScrollDown => {
+            model.scroll_by(2);
+        }
+        MouseEventKind::Down(MouseButton::Left) if on_scrollbar => {
+            model.scrollbar_drag = true;
+            model.scroll_to_row(mouse.row);
+        }
+        MouseEventKind::Drag(MouseButton::Left) if model.scrollbar_drag => {
+            if !model.scroll_to_row(mouse.row) {
+                return Ok(PollResult::None);
+            }
+        }
+        MouseEventKind::Up(MouseButton::Left) => {
+            model.scrollbar_drag = false;
+            return Ok(PollResult::None);
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            model.last_error = None;
+            model.input_queue = InputQueue::None;
+            match model.click(mouse.column, mouse.row) {
+                Ok(false) => return Ok(PollResult::None),
+                Ok(true) => {}
+                Err(err) => model.set_last_error(err),
+            }
+        }
+        MouseEventKind::Down(MouseButton::Right) => {
+            // Select the link and copy its URL to the clipboard with OSC 52.
+            let Some((pointer, url)) = model.link_at(mouse.column, mouse.row) else {
+                return Ok(PollResult::None);
+            };
+            crossterm::execute!(
+                io::stderr(),
+                CopyToClipboard::to_clipboard_from(url.as_ref())
+            )?;
+            model.cursor = Cursor::Links(pointer);
+        }
+        _ => return Ok(PollResult::None),
+    }
+    Ok(PollResult::HadInput)
+}
+
 fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error> {
     let page_scroll_count = model.inner_height() as i32 - 1;
 
@@ -98,7 +154,7 @@ fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error>
                         .saturating_mul(NonZero::new(10).expect("10 != 0"))
                         .saturating_add(x);
                 }
-                InputQueue::CursorPositioningCommands => {
+                InputQueue::CursorPositioningCommands | InputQueue::Bracket(_) => {
                     model.input_queue = InputQueue::None;
                 }
                 InputQueue::Search(_) | InputQueue::Command(_) => {
@@ -121,6 +177,89 @@ fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error>
             model.position_cursor(CursorPositioning::from(x));
             model.input_queue = InputQueue::None;
         }
+        // ]c and [c jump to the next and previous change, like in vim's diff mode.
+        KeyCode::Char(x @ (']' | '[')) if model.input_queue == InputQueue::None => {
+            model.input_queue = InputQueue::Bracket(x);
+        }
+        KeyCode::Char(x) if matches!(model.input_queue, InputQueue::Bracket(_)) => {
+            let InputQueue::Bracket(bracket) =
+                std::mem::replace(&mut model.input_queue, InputQueue::None)
+            else {
+                panic!("invariant InputQueue::Bracket");
+            };
+            if x != 'c' || !model.jump_to_change(bracket == ']') {
+                return Ok(PollResult::None);
+            }
+        }
+        // Image viewer
+        KeyCode::Char('+' | '=') if model.image_viewer.is_some() => {
+            let count = model.input_queue.take_count_or_unit_i32();
+            if !model.zoom_image(count)? {
+                return Ok(PollResult::None);
+            }
+        }
+        KeyCode::Char('-') if model.image_viewer.is_some() => {
+            let count = model.input_queue.take_count_or_unit_i32();
+            if !model.zoom_image(-count)? {
+                return Ok(PollResult::None);
+            }
+        }
+        KeyCode::Char('h' | 'j' | 'k' | 'l')
+        | KeyCode::Left
+        | KeyCode::Down
+        | KeyCode::Up
+        | KeyCode::Right
+            if model.image_viewer.is_some() =>
+        {
+            let count = model.input_queue.take_count_or_unit_i32();
+            let (dx, dy) = match key.code {
+                KeyCode::Char('h') | KeyCode::Left => (-count, 0),
+                KeyCode::Char('j') | KeyCode::Down => (0, count),
+                KeyCode::Char('k') | KeyCode::Up => (0, -count),
+                _ => (count, 0),
+            };
+            if !model.pan_image(dx, dy)? {
+                return Ok(PollResult::None);
+            }
+        }
+        KeyCode::Esc if model.is_viewing_image()? => {
+            model.close_image();
+        }
+        // The document below keeps its scroll position and cursor.
+        KeyCode::Char(x)
+            if model.image_viewer.is_some()
+                && !matches!(x, 'q' | ':' | 'r')
+                && !(x == 'c' && key.modifiers.contains(KeyModifiers::CONTROL)) =>
+        {
+            return Ok(PollResult::None);
+        }
+        KeyCode::PageDown | KeyCode::PageUp | KeyCode::Enter | KeyCode::Backspace
+            if model.image_viewer.is_some() && model.input_queue == InputQueue::None =>
+        {
+            return Ok(PollResult::None);
+        }
+        // Slides
+        // Space pages down like in documents, and goes to the next slide from the bottom of one.
+        KeyCode::Char(' ') | KeyCode::Right
+            if model.slides.is_some()
+                && (key.code == KeyCode::Right || model.scrolled_to_bottom()) =>
+        {
+            if !model.slide_by(1)? {
+                return Ok(PollResult::None);
+            }
+        }
+        KeyCode::Backspace | KeyCode::Left
+            if model.slides.is_some() && model.input_queue == InputQueue::None =>
+        {
+            if !model.slide_by(-1)? {
+                return Ok(PollResult::None);
+            }
+        }
+        KeyCode::Char('s') if model.slides.is_some() => {
+            if let Some(slides) = &mut model.slides {
+                slides.show_notes = !slides.show_notes;
+            }
+        }
         // Ways to quit
         KeyCode::Char('q') => {
             return Ok(PollResult::Quit);
@@ -206,6 +345,24 @@ fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error>
             model.input_queue = InputQueue::None;
             return Ok(PollResult::SkipRender);
         }
+        KeyCode::Char('p') => {
+            let count = model.input_queue.take_count_or_unit_u16();
+            if !model.toggle_animation(count) {
+                return Ok(PollResult::None);
+            }
+        }
+        KeyCode::Char('I') => {
+            model.input_queue = InputQueue::None;
+            model.allow_remote_images()?;
+            return Ok(PollResult::SkipRender);
+        }
+        KeyCode::Char('e') => {
+            model.input_queue = InputQueue::None;
+            match model.edit_command() {
+                Ok(command) => return Ok(PollResult::Suspend(command)),
+                Err(err) => model.set_last_error(err),
+            }
+        }
         KeyCode::Char('/') => {
             model.input_queue = InputQueue::Search(String::new());
             model.cursor = Cursor::Search(String::new(), None);
@@ -231,6 +388,12 @@ fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error>
             }
         }
         KeyCode::Enter => {
+            if let Cursor::Links(pointer) = &model.cursor
+                && let Some(link) = model.selected_image(pointer).cloned()
+            {
+                model.open_image(link)?;
+                return Ok(PollResult::HadInput);
+            }
             // Open links with xdg-open
             if let Cursor::Links(CursorPointer { id, index }) = model.cursor {
                 let url = model.sections().find_map(|section| {
@@ -261,7 +424,7 @@ fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error>
                 }
             }
         }
-        KeyCode::Esc if model.is_help_screen()? => {
+        KeyCode::Esc if model.is_help_screen()? || model.is_zoomed_image()? => {
             model.history_pop()?;
         }
         KeyCode::Esc => match model.input_queue {
@@ -282,6 +445,7 @@ fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error>
             }
             InputQueue::MovementCount(_)
             | InputQueue::CursorPositioningCommands
+            | InputQueue::Bracket(_)
             | InputQueue::Command(_) => {
                 model.input_queue = InputQueue::None;
             }
@@ -289,6 +453,9 @@ fn match_keycode(key: KeyEvent, model: &mut Model) -> Result<PollResult, Error>
         KeyCode::Backspace => match &mut model.input_queue {
             // Edit input queue.
             InputQueue::None | InputQueue::CursorPositioningCommands => {}
+            InputQueue::Bracket(_) => {
+                model.input_queue = InputQueue::None;
+            }
             InputQueue::MovementCount(count) => {
                 let value = count.get();
                 if value > 10 {
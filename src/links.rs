@@ -66,7 +66,7 @@ fn span_to_ansi(span: &Span) -> Result<String, std::fmt::Error> {
     Ok(out)
 }
 
-fn modifier_to_attributes(m: Modifier) -> Attributes {
+pub fn modifier_to_attributes(m: Modifier) -> Attributes {
     let mut attrs = Attributes::default();
     if m.contains(Modifier::BOLD) {
         attrs.set(Attribute::Bold);
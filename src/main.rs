@@ -4,13 +4,22 @@ mod cursor;
 mod debug;
 mod document;
 mod error;
+mod export;
+mod http;
+mod http_cache;
 mod keybindings;
 mod links;
 mod model;
+mod print;
 mod renderer;
 mod setup;
+mod slides;
 mod sources;
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod testing;
 mod view;
+mod viewer;
 mod watch;
 mod worker;
 
@@ -18,18 +27,23 @@ mod worker;
 use std::os::fd::IntoRawFd as _;
 
 use std::{
+    collections::HashMap,
     fmt::Display,
     io::{self, Read as _},
+    ops::Range,
     path::PathBuf,
+    process::ExitCode,
     sync::{
         Arc, OnceLock, RwLock,
         mpsc::{self},
     },
+    thread::JoinHandle,
+    time::Duration,
 };
 
 use clap::{ArgMatches, arg, command, value_parser};
 use ratatui::{
-    Terminal,
+    Terminal, TerminalOptions, Viewport,
     crossterm::{
         event::{DisableMouseCapture, EnableMouseCapture},
         tty::IsTty as _,
@@ -38,18 +52,25 @@ use ratatui::{
     prelude::CrosstermBackend,
 };
 
-use mdfrier::MarkdownLink;
-use ratatui_image::{picker::ProtocolType, protocol::Protocol, sliced::SlicedProtocol};
+use mdfrier::{BlockChange, MarkdownLink};
+use ratatui_image::{
+    picker::{Picker, ProtocolType},
+    protocol::Protocol,
+    sliced::SlicedProtocol,
+};
 use setup::{SetupResult, setup_graphics};
 
 use crate::{
-    config::Config,
-    document::{Section, SectionID},
+    config::{Config, RemoteImages},
+    document::{PreviousParse, Section, SectionID, SectionPatch, SectionSource},
     error::Error,
+    export::ExportFormat,
     model::{DocumentId, Model},
     renderer::run_loop,
+    slides::Slides,
     sources::{BuiltIn, DocumentSource, SharedDocumentSource, open_source},
-    watch::watch,
+    viewer::View,
+    watch::{Watcher, poll_remote},
     worker::{ImageCache, worker_thread},
 };
 
@@ -57,14 +78,23 @@ pub const OK_END: &str = " ok.";
 
 pub static VERSION: OnceLock<String> = OnceLock::new();
 
-fn main() -> io::Result<()> {
+fn main() -> io::Result<ExitCode> {
     let mut cmd = command!() // requires `cargo` feature
         .arg(
-            arg!([SOURCE] "The markdown source.\nCan be a file path, a URL, a github repo in \"github:[owner]/[repo]\" format, or '-' or omit, for stdin.")
+            arg!([SOURCE] "The markdown source.\nCan be a file path, a URL, a github repo in \"github:[owner]/[repo][@branch][:path]\" format (likewise gitlab:, codeberg:, gitea:, sourcehut:), a file at a git revision as \"git:[rev]:[path]\", or '-' or omit, for stdin.")
         )
         .arg(arg!(-d --"deep-fry" "Extra deep fried images.").value_parser(value_parser!(bool)))
         .arg(arg!(-w --"watch" "Watch markdown file, reload on changes.").value_parser(value_parser!(bool)))
+        .arg(arg!(--"slides" "Presentation mode, show one slide at a time.").value_parser(value_parser!(bool)))
+        .arg(arg!(--"diff" <OLD> "Highlight the changes from an old version of the document, any SOURCE, e.g. \"git:HEAD:[path]\"."))
+        .arg(arg!(-i --"inline" "Render below the shell prompt instead of full screen, sized to the document.").value_parser(value_parser!(bool)))
+        .arg(arg!(-p --"print" "Render the document to stdout and exit, plain text if stdout is not a terminal.").value_parser(value_parser!(bool)))
+        .arg(arg!(--"export" <FORMAT> "Write the document as a standalone page or image (html, png, svg) to stdout and exit.").value_parser(["html", "png", "svg"]))
+        .arg(arg!(--"width" <N> "With --print or --export, the width to render at, default is the terminal width.").value_parser(value_parser!(u16)))
+        .arg(arg!(--"images" "With --print, render images and header images with the detected graphics protocol.").value_parser(value_parser!(bool)))
         .arg(arg!(-s --"setup" "Force font setup (again).").value_parser(value_parser!(bool)))
+        .arg(arg!(--"offline" "Make no network requests, remote documents and images only come from the HTTP cache.").value_parser(value_parser!(bool)))
+        .arg(arg!(--"refresh" "Fetch remote documents and images again, bypassing the HTTP cache.").value_parser(value_parser!(bool)))
         .arg(
             arg!(--"print-config" "Write out a mostly complete config file example to stdout.")
                 .value_parser(value_parser!(bool)),
@@ -100,10 +130,13 @@ fn main() -> io::Result<()> {
         Err(Error::UserAbort(msg)) => {
             println!("Abort: {msg}");
         }
-        Err(err) => eprintln!("{err}"),
+        Err(err) => {
+            eprintln!("{err}");
+            return Ok(ExitCode::FAILURE);
+        }
         _ => {}
     }
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
 #[expect(clippy::too_many_lines)]
@@ -138,29 +171,66 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
 
     let source: Option<String> = matches.get_one::<String>("SOURCE").cloned();
 
+    let print = *matches.get_one("print").unwrap_or(&false);
+    let inline = *matches.get_one("inline").unwrap_or(&false);
+    let export = matches
+        .get_one::<String>("export")
+        .and_then(|format| ExportFormat::from_extension(format));
+    // Keep stdout clean for the document.
+    let quiet = print || export.is_some();
+    // Without a terminal, print plain text.
+    let styled = io::stdout().is_tty();
+    let print_images = print && styled && *matches.get_one("images").unwrap_or(&false);
+    let read_stdin = || -> Result<String, Error> {
+        let mut text = String::new();
+        if !quiet {
+            print!("Reading stdin...");
+        }
+        io::stdin().read_to_string(&mut text)?;
+        if !quiet {
+            println!("{OK_END}");
+        }
+        Ok(text)
+    };
+
     let mut user_config = config::load_or_ask()?;
     let mut config = Config::from(user_config.clone());
+    let refresh = *matches.get_one("refresh").unwrap_or(&false);
+    http::init(config.http.clone());
+    sources::forge::init(config.forges.clone());
+    http_cache::init(config.http_cache_megabytes, refresh);
+    if *matches.get_one("offline").unwrap_or(&false) {
+        http_cache::go_offline();
+    }
 
     let (text, document_source) = match source {
-        Some(source) if source == "-" => {
-            let mut text = String::new();
-            print!("Reading stdin...");
-            io::stdin().read_to_string(&mut text)?;
-            println!("{OK_END}");
-            (text, DocumentSource::Stdin { text: None })
-        }
+        Some(source) if source == "-" => (read_stdin()?, DocumentSource::Stdin { text: None }),
         None => {
             if io::stdin().is_tty() {
                 (String::new(), DocumentSource::BuiltIn(BuiltIn::Welcome))
             } else {
-                let mut text = String::new();
-                print!("Reading stdin...");
-                io::stdin().read_to_string(&mut text)?;
-                println!("{OK_END}");
-                (text, DocumentSource::Stdin { text: None })
+                (read_stdin()?, DocumentSource::Stdin { text: None })
+            }
+        }
+        Some(source) => open_source(&source, config.url_transform_command.clone(), quiet)?,
+    };
+
+    let diff_base = match matches.get_one::<String>("diff") {
+        Some(_) if *matches.get_one("slides").unwrap_or(&false) => {
+            return Err(Error::Usage(Some("--diff does not work with --slides")));
+        }
+        Some(old) => {
+            let (old_text, old_source) =
+                open_source(old, config.url_transform_command.clone(), quiet)?;
+            if matches!(
+                old_source,
+                DocumentSource::Image { .. } | DocumentSource::Pdf { .. }
+            ) {
+                return Err(Error::Usage(Some("--diff needs a markdown document")));
             }
+            Some(old_text)
         }
-        Some(source) => open_source(&source, config.url_transform_command.clone())?,
+        None => None,
     };
 
     if text.is_empty()
@@ -173,10 +243,24 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
     {
         return Err(Error::Usage(Some("no input or empty")));
     }
+    if (quiet || inline || diff_base.is_some())
+        && matches!(
+            document_source,
+            DocumentSource::BuiltIn(BuiltIn::Welcome)
+                | DocumentSource::Image { .. }
+                | DocumentSource::Pdf { .. }
+        )
+    {
+        return Err(Error::Usage(Some(
+            "--print, --inline, --export and --diff need a markdown document",
+        )));
+    }
 
     #[cfg(not(windows))]
-    if !io::stdin().is_tty() {
-        print!("Setting stdin to /dev/tty...");
+    if !io::stdin().is_tty() && export.is_none() && (!print || print_images) {
+        if !print {
+            print!("Setting stdin to /dev/tty...");
+        }
         // Close the current stdin so that ratatui-image can read stuff from tty stdin.
         // SAFETY:
         // Calls some libc, not sure if this could be done otherwise.
@@ -193,7 +277,9 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
             // Close the original tty file descriptor
             libc::close(tty_fd);
         }
-        println!("{OK_END}");
+        if !print {
+            println!("{OK_END}");
+        }
     }
 
     let force_setup = *matches.get_one("setup").unwrap_or(&false);
@@ -207,15 +293,28 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
             _ => ProtocolType::Halfblocks,
         }));
 
-    crossterm::terminal::enable_raw_mode()?;
-
-    let (picker, renderer, has_text_size_protocol) = {
+    let (picker, renderer, has_text_size_protocol) = if export.is_some() {
+        let picker = Picker::halfblocks();
+        let renderer = setup::export_font_renderer(
+            config.font_family.as_deref(),
+            config.theme.header_color,
+            picker.font_size(),
+        );
+        (picker, Some(Box::new(renderer)), false)
+    } else if print && !print_images {
+        (Picker::halfblocks(), None, false)
+    } else {
+        crossterm::terminal::enable_raw_mode()?;
         let setup_result = setup_graphics(
             &mut user_config,
             force_setup,
             no_cap_checks,
             debug_override_protocol_type,
+            print,
         );
+        if print {
+            crossterm::terminal::disable_raw_mode()?;
+        }
         match setup_result {
             Ok(result) => match result {
                 SetupResult::Aborted => return Err(Error::UserAbort("cancelled setup")),
@@ -229,13 +328,14 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
 
     let deep_fry = *matches.get_one("deep-fry").unwrap_or(&false);
 
-    let watchmode_path = if *matches.get_one("watch").unwrap_or(&false)
-        && let DocumentSource::File { path, .. } = &document_source
-    {
-        Some(path.clone())
-    } else {
-        None
-    };
+    let watch_mode = *matches.get_one("watch").unwrap_or(&false)
+        && matches!(
+            document_source,
+            DocumentSource::File { .. }
+                | DocumentSource::Github { .. }
+                | DocumentSource::Forge { .. }
+                | DocumentSource::HyperText { .. }
+        );
 
     let document_source = SharedDocumentSource(Arc::new(RwLock::new(document_source)));
 
@@ -249,6 +349,15 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
         debug::animate_recording(event_tx.clone());
     }
 
+    if print {
+        config.osc8_links &= styled;
+        config.load_images = print_images;
+        config.max_animation_fps = 0;
+    }
+    if export.is_some() {
+        // The export loads its own images.
+        config.load_images = false;
+    }
     let config_max_image_height = config.max_image_height;
     config.theme.has_text_size_protocol = Some(has_text_size_protocol);
     let worker_config = config.clone();
@@ -263,39 +372,114 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
         config_max_image_height,
     );
 
-    let backend = CrosstermBackend::new(io::stdout());
-    let mut terminal = Terminal::new(backend)?;
-    let enable_mouse_capture = config.enable_mouse_capture;
-    if enable_mouse_capture {
-        crossterm::execute!(io::stderr(), EnableMouseCapture)?;
+    if quiet {
+        let width = matches
+            .get_one::<u16>("width")
+            .copied()
+            .or_else(|| {
+                styled
+                    .then(|| crossterm::terminal::size().ok().map(|(width, _)| width))
+                    .flatten()
+            })
+            .unwrap_or(print::DEFAULT_WIDTH);
+        let mut model = Model::new(
+            document_source,
+            cmd_tx,
+            event_rx,
+            Size::new(width, print::DEFAULT_HEIGHT),
+            config,
+        );
+        model.diff_base = diff_base;
+        model.open(text)?;
+        // Dropping the model in `print` or `to_stdout` lets the worker thread exit.
+        if let Some(format) = export {
+            export::to_stdout(model, format)?;
+        } else {
+            print::print(model, &mut io::stdout().lock(), styled)?;
+        }
+        return join_worker(worker_thread);
     }
+
+    let enable_mouse_capture = config.enable_mouse_capture;
     let watch_debounce_milliseconds = config.watch_debounce_milliseconds;
-    terminal.clear()?;
+    let watch_poll_seconds = config.watch_poll_seconds;
+    let url_transform_command = config.url_transform_command.clone();
 
     if document_source.read()? == DocumentSource::BuiltIn(BuiltIn::Welcome) {
-        cmd_tx.send(Cmd::LoadImage(None))?;
+        cmd_tx.send(Cmd::LoadLogo)?;
+    }
+    let screen_size = {
+        let (width, height) = crossterm::terminal::size()?;
+        Size::new(width, height)
+    };
+    let mut model = Model::new(
+        document_source.clone(),
+        cmd_tx,
+        event_rx,
+        screen_size,
+        config,
+    );
+    if watch_mode {
+        log::info!("watch mode");
+        model.watcher = Some(Watcher::new(
+            watch_event_tx.clone(),
+            watch_debounce_milliseconds,
+        )?);
+        model.watch_source()?;
+        if watch_poll_seconds > 0 {
+            poll_remote(
+                document_source,
+                watch_event_tx,
+                Duration::from_secs(watch_poll_seconds),
+                url_transform_command,
+            )?;
+        }
+    } else {
+        drop(watch_event_tx);
     }
-    let model = Model::new(document_source, cmd_tx, event_rx, terminal.size()?, config);
+    if *matches.get_one("slides").unwrap_or(&false) {
+        model.slides = Some(Slides::new()?);
+    }
+    model.diff_base = diff_base;
     model.open(text)?;
 
-    let debouncer = if let Some(path) = watchmode_path {
-        log::info!("watching file");
-        Some(watch(&path, watch_event_tx, watch_debounce_milliseconds)?)
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = if inline {
+        // Size the viewport to the loaded document, the status line included.
+        model.inline = true;
+        model.wait_loaded()?;
+        let height = model
+            .total_lines()
+            .saturating_add(1)
+            .min(screen_size.height);
+        model.screen_size.height = height;
+        Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::Inline(height),
+            },
+        )?
     } else {
-        drop(watch_event_tx);
-        None
+        Terminal::new(backend)?
     };
+    if enable_mouse_capture {
+        crossterm::execute!(io::stderr(), EnableMouseCapture)?;
+    }
+    terminal.clear()?;
 
     if let Err(err) = run_loop(terminal, model) {
         eprintln!("Runtime error: {err}");
     };
-    drop(debouncer);
 
     if enable_mouse_capture {
         crossterm::execute!(io::stderr(), DisableMouseCapture)?;
     }
     crossterm::terminal::disable_raw_mode()?;
 
+    join_worker(worker_thread)
+}
+
+fn join_worker(worker_thread: JoinHandle<Result<(), Error>>) -> Result<(), Error> {
     match worker_thread.join() {
         Err(e) => eprintln!("Worker thread panic: {e:?}"),
         Ok(Err(Error::ThreadClosed)) => {
@@ -308,10 +492,23 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
 }
 
 pub enum Cmd {
-    Parse(DocumentId, u16, String, Option<ImageCache>),
+    Parse(
+        DocumentId,
+        u16,
+        String,
+        Option<ImageCache>,
+        Option<PreviousParse>,
+        RemoteImages,
+        Option<String>, // The old version of the text, to render the changes from.
+    ),
     OpenUrl(String),
-    LoadImage(Option<(PathBuf, Size)>), // TODO: either included welcome logo, or a path, make an enum?
+    LoadLogo,
+    // The image of a link or an image source at a zoom and center, see `viewer`, and whether to
+    // load it again rather than reuse the last one.
+    ViewImage(String, Size, u8, (f32, f32), RemoteImages, bool),
     LoadPdf(PathBuf, Size),
+    Export(u16, String, ExportFormat, Option<PathBuf>, RemoteImages), // To a file, or stdout.
+    Animate(DocumentId, Vec<(SectionID, String, Size)>), // The animated images in view, by URL.
 }
 
 impl std::fmt::Debug for Cmd {
@@ -323,23 +520,46 @@ impl std::fmt::Debug for Cmd {
 impl Display for Cmd {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Cmd::Parse(reload_id, width, _, cache) => {
+            Cmd::Parse(reload_id, width, _, cache, previous, remote_images, diff_base) => {
                 write!(
                     f,
-                    "Cmd::Parse({reload_id:?}, {width}, <text>, cache={cache:?})",
+                    "Cmd::Parse({reload_id:?}, {width}, <text>, cache={cache:?}, incremental={}, {remote_images:?}, diff={})",
+                    previous.is_some(),
+                    diff_base.is_some()
                 )
             }
             Cmd::OpenUrl(url) => write!(f, "Cmd::Open({url})"),
-            Cmd::LoadImage(image) => write!(f, "Cmd::LoadImage({image:?})"),
+            Cmd::LoadLogo => write!(f, "Cmd::LoadLogo"),
+            Cmd::ViewImage(url, size, zoom, center, remote_images, reload) => {
+                write!(
+                    f,
+                    "Cmd::ViewImage({url}, {size:?}, {zoom}, {center:?}, {remote_images:?}, reload={reload})"
+                )
+            }
             Cmd::LoadPdf(path, size) => write!(f, "Cmd::LoadPdf({path:?}, {size:?})"),
+            Cmd::Export(width, _, format, path, remote_images) => {
+                write!(
+                    f,
+                    "Cmd::Export({width}, <text>, {format:?}, {path:?}, {remote_images:?})"
+                )
+            }
+            Cmd::Animate(document_id, sections) => {
+                write!(f, "Cmd::Animate({document_id}, {} images)", sections.len())
+            }
         }
     }
 }
 
 pub enum Event {
     NewDocument(DocumentId),
-    ParseDone(DocumentId, Option<SectionID>, String), // Only signals "parsing done", not "images ready"!
-    Parsed(DocumentId, Section),
+    ParseDone(
+        DocumentId,
+        Option<SectionID>,
+        String,
+        HashMap<Range<usize>, BlockChange>, // The changed blocks, with a diff base.
+    ), // Only signals "parsing done", not "images ready"!
+    Parsed(DocumentId, Section, SectionSource),
+    Reparsed(DocumentId, Vec<SectionPatch>),
     ImageLoaded(
         DocumentId,
         SectionID,
@@ -348,28 +568,46 @@ pub enum Event {
         bool,
     ),
     ImageFailed(DocumentId, SectionID, String, String),
+    ImageAnimated(String, bool), // Whether the image at the URL is animated, after it loaded.
+    AnimationFrame(DocumentId, SectionID, String, SlicedProtocol),
     HeaderLoaded(DocumentId, SectionID, Vec<(String, u8, Protocol)>),
     RootImageLoaded(Protocol), // Not markdown related, e.g. the welcome logo image.
+    ImageViewed(String, Protocol, View), // The image of `Cmd::ViewImage`, by URL.
     PdfPageLoaded(usize, SlicedProtocol),
-    FileChanged,
+    FileChanged(PathBuf),
+    SourceChanged(DocumentSource, String), // A polled remote source has new text.
     Scroll(i16),
-    NewSourceContent(String),
+    NewSourceContent(DocumentSource, String),
     ReferenceDefinition {
         id: String,
         url: String,
     },
     CodeLoaded(DocumentId, usize, ratatui::prelude::Text<'static>),
-    WorkerError(Error),
+    LoadDone(DocumentId), // All images, headers and code of a parse are loaded.
+    Exported(Option<PathBuf>),
+    WorkerError(Error),   // A command failed.
+    WorkerWarning(Error), // Something failed, but the worker recovered, e.g. with a default.
 }
 
 impl Display for Event {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Event::NewDocument(document_id) => write!(f, "Event::NewDocument({document_id})"),
-            Event::ParseDone(document_id, last_section_id, _text) => {
-                write!(f, "Event::ParseDone({document_id}, {last_section_id:?})")
+            Event::ParseDone(document_id, last_section_id, _text, changes) => {
+                write!(
+                    f,
+                    "Event::ParseDone({document_id}, {last_section_id:?}, {} changes)",
+                    changes.len()
+                )
             }
-            Event::Parsed(document_id, section) => {
+            Event::Reparsed(document_id, patches) => {
+                write!(
+                    f,
+                    "Event::Reparsed({document_id}, {} sections)",
+                    patches.len()
+                )
+            }
+            Event::Parsed(document_id, section, _) => {
                 write!(
                     f,
                     "Event::Parsed({document_id}, id:{}, content: {})",
@@ -388,6 +626,15 @@ impl Display for Event {
                     "Event::ImageFailed({document_id}, {section_id}, {url}, {error})"
                 )
             }
+            Event::ImageAnimated(url, animated) => {
+                write!(f, "Event::ImageAnimated({url}, {animated})")
+            }
+            Event::AnimationFrame(document_id, section_id, url, _) => {
+                write!(
+                    f,
+                    "Event::AnimationFrame({document_id}, {section_id}, {url})"
+                )
+            }
             Event::HeaderLoaded(document_id, section_id, rows) => {
                 write!(
                     f,
@@ -404,15 +651,22 @@ impl Display for Event {
                     text.to_string().chars().take(10).collect::<String>()
                 )
             }
+            Event::LoadDone(document_id) => write!(f, "Event::LoadDone({document_id})"),
+            Event::Exported(path) => write!(f, "Event::Exported({path:?})"),
             Event::ReferenceDefinition { id, url } => {
                 write!(f, "Event::ReferenceDefinition {{ id: {id}, url: {url} }}")
             }
             Event::RootImageLoaded(_) => write!(f, "Event::RootImageLoaded"),
+            Event::ImageViewed(url, _, view) => write!(f, "Event::ImageViewed({url}, {view:?})"),
             Event::PdfPageLoaded(idx, _) => write!(f, "Event::PdfPageLoaded({idx})"),
-            Event::FileChanged => write!(f, "Event::FileChanged"),
+            Event::FileChanged(path) => write!(f, "Event::FileChanged({path:?})"),
+            Event::SourceChanged(source, _) => {
+                write!(f, "Event::SourceChanged({})", source.name())
+            }
             Event::Scroll(s) => write!(f, "Event::Scroll({s})"),
-            Event::NewSourceContent(_) => write!(f, "Event::NewSource"),
+            Event::NewSourceContent(source, _) => write!(f, "Event::NewSource({source:?})"),
             Event::WorkerError(err) => write!(f, "Event::WorkerError({err})"),
+            Event::WorkerWarning(err) => write!(f, "Event::WorkerWarning({err})"),
         }
     }
 }
@@ -746,6 +1000,46 @@ Goodbye.
         teardown(model, worker);
     }
 
+    #[test]
+    fn reparse_keeps_unchanged_sections() {
+        let config = UserConfig {
+            max_image_height: Some(10),
+            ..Default::default()
+        }
+        .into();
+        let (mut model, worker, _) = setup(config);
+
+        model
+            .open(String::from(
+                "# Hello\n\nFirst paragraph.\n\n![image](./assets/NixOS.png)\n\nGoodbye.\n",
+            ))
+            .unwrap();
+        poll_parsed(&mut model);
+        poll_images_done(&mut model);
+        let before: Vec<_> = model.sections().map(|section| section.id).collect();
+
+        model
+            .reparse(
+                String::from(
+                    "# Hello\n\nFirst paragraph, edited.\n\n![image](./assets/NixOS.png)\n\nGoodbye.\n",
+                ),
+                model.screen_size.width,
+            )
+            .unwrap();
+        poll_parsed(&mut model);
+
+        let sections: Vec<&Section> = model.sections().collect();
+        assert_eq!(before.len(), sections.len());
+        assert_eq!(before[0], sections[0].id, "header kept");
+        assert!(before[1] < sections[1].id, "paragraph regenerated");
+        assert_eq!(before[2], sections[2].id, "image kept");
+        assert!(matches!(sections[2].content, SectionContent::Image(..)));
+        assert!(!model.has_pending_images(), "image not loaded again");
+        assert_eq!(before[3], sections[3].id, "paragraph kept");
+
+        teardown(model, worker);
+    }
+
     #[test]
     fn simple_resize() {
         let config = UserConfig {
@@ -828,4 +1122,26 @@ Line that should be broken up later
 
         teardown(model, worker);
     }
+
+    #[test]
+    fn print_plain() {
+        let mut config: Config = UserConfig::default().into();
+        config.osc8_links = false;
+        config.load_images = false;
+        let (mut model, worker, _) = setup(config);
+
+        model
+            .open(String::from(
+                "Some *text* in a paragraph.\n\n![image](./assets/NixOS.png)\n\n- one\n- two\n",
+            ))
+            .unwrap();
+        let mut out = Vec::new();
+        crate::print::print(model, &mut out, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Some text in a paragraph.\n\n![image](./assets/NixOS.png)\n\n- one\n- two\n"
+        );
+
+        worker.join().unwrap().unwrap();
+    }
 }
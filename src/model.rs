@@ -1,15 +1,20 @@
 use std::{
     cmp::min,
+    collections::HashSet,
+    env,
     fmt::Display,
     fs,
     num::NonZero,
-    path::Path,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Command,
     sync::mpsc::{Receiver, Sender},
+    time::{Duration, Instant},
 };
 
-use mdfrier::{SourceContent, ratatui::Theme as _};
+use mdfrier::{BlockChange, MarkdownLink, SourceContent, ratatui::Theme as _};
 use ratatui::{
-    layout::{Rect, Size},
+    layout::{Position, Rect, Size},
     style::{Color, Stylize as _},
     text::{Line, Span},
     widgets::Padding,
@@ -21,15 +26,25 @@ use ratatui_image::{protocol::Protocol, sliced::SlicedProtocol};
 
 use crate::{
     Cmd,
-    config::{Config, Padding as ConfigPadding},
+    config::{Config, Padding as ConfigPadding, RemoteImages},
     cursor::{Cursor, CursorPointer},
-    document::{Document, FindMode, FindTarget, LineExtra, Section, SectionContent},
+    document::{
+        Document, FindMode, FindTarget, LineExtra, PreviousParse, Section, SectionContent,
+        SectionID,
+    },
     error::{CommandError, Error, NavigationError},
-    sources::{BuiltIn, DocumentHistoryEntry, DocumentSource, extend_url, github_usercontent_url},
+    export::ExportFormat,
+    slides::Slides,
+    sources::{BuiltIn, DocumentHistoryEntry, DocumentSource, extend_url, git, github},
+    viewer::ImageViewer,
+    watch::{FollowChanges, SectionSnapshot, Watcher, follow_changes, normalize},
     worker::ImageCache,
 };
 use crate::{Event, sources::SharedDocumentSource};
 
+// The columns left of the document for marking changes, with `--diff`.
+const DIFF_GUTTER: u16 = 2;
+
 pub struct Model {
     pub scroll: u16,
     pub cursor: Cursor,
@@ -37,10 +52,38 @@ pub struct Model {
     pub screen_size: Size,
     pub last_error: Option<Error>,
     pub root_image_proto: Option<Protocol>,
+    // An image shown fullscreen, over the document or as the image source.
+    pub image_viewer: Option<ImageViewer>,
     pub image_pages: Vec<SlicedProtocol>,
     pub config: Config,
+    // Whether the left mouse button was pressed on the scrollbar, and is being dragged.
+    pub scrollbar_drag: bool,
+    // Watches the displayed file and its images in watch mode.
+    pub watcher: Option<Watcher>,
+    pub slides: Option<Slides>,
+    // The old version of the document's text, whose changes are highlighted.
+    pub diff_base: Option<String>,
+    // With an inline viewport, the height is fixed to the height of the document when opened.
+    pub inline: bool,
+    // The document and scroll position before the watched file changed.
+    watch_snapshot: Option<(Vec<SectionSnapshot>, u16)>,
+    // Lines to highlight after the watched file changed, until the instant.
+    changed_lines: Option<(Range<u16>, Instant)>,
     document: Document,
     document_id: DocumentId,
+    // The document whose images, headers and code are all loaded.
+    loaded: Option<DocumentId>,
+    // Whether an export has been sent to the worker, and is not written yet.
+    exporting: bool,
+    // Whether a worker command failed, and `last_error` is why.
+    worker_failed: bool,
+    // Whether remote images were allowed for this document, with `remote_images = "ask"`.
+    remote_images_allowed: bool,
+    // The URLs of the loaded images that are animated, and of the ones paused with `p`.
+    animated_images: HashSet<String>,
+    paused_images: HashSet<SectionID>,
+    // The animated images in view that the worker was last told to play.
+    playing: (DocumentId, Vec<(SectionID, String, Size)>),
     document_source: SharedDocumentSource,
     document_history: Vec<DocumentHistoryEntry>,
     cmd_tx: Sender<Cmd>,
@@ -55,6 +98,8 @@ pub enum InputQueue {
     MovementCount(NonZero<u16>),
     Search(String),
     CursorPositioningCommands,
+    // `]` or `[`, waiting for what to jump to, like vim.
+    Bracket(char),
     Command(String),
 }
 impl InputQueue {
@@ -100,7 +145,15 @@ impl Model {
             input_queue: InputQueue::None,
             cursor: Cursor::default(),
             root_image_proto: None,
+            image_viewer: None,
             image_pages: Vec::new(),
+            scrollbar_drag: false,
+            watcher: None,
+            slides: None,
+            diff_base: None,
+            inline: false,
+            watch_snapshot: None,
+            changed_lines: None,
             document: Document::default(),
             document_id: DocumentId::default(),
             document_source,
@@ -108,18 +161,68 @@ impl Model {
             cmd_tx,
             event_rx,
             last_error: None,
+            loaded: None,
+            exporting: false,
+            worker_failed: false,
+            remote_images_allowed: false,
+            animated_images: HashSet::new(),
+            paused_images: HashSet::new(),
+            playing: Default::default(),
         }
     }
 
+    // Process events until the images, headers and code of the current document are loaded.
+    //
+    // Fails if the worker does. Recoverable errors are left in `last_error`.
+    pub fn wait_loaded(&mut self) -> Result<(), Error> {
+        self.wait_until(|model| model.loaded == Some(model.document_id))
+    }
+
+    // Export the displayed document, to `path` or stdout.
+    pub fn export(&mut self, format: ExportFormat, path: Option<PathBuf>) -> Result<(), Error> {
+        self.exporting = true;
+        self.cmd_tx.send(Cmd::Export(
+            self.inner_width(),
+            self.document.text().to_owned(),
+            format,
+            path,
+            self.remote_images(),
+        ))?;
+        Ok(())
+    }
+
+    // Process events until the export is written.
+    pub fn wait_exported(&mut self) -> Result<(), Error> {
+        self.wait_until(|model| !model.exporting)
+    }
+
+    // Block on worker events until `done`, or a worker command fails.
+    fn wait_until(&mut self, done: impl Fn(&Self) -> bool) -> Result<(), Error> {
+        while !done(self) {
+            let event = self.event_rx.recv()?;
+            self.handle_events(Some(event))?;
+            if self.worker_failed {
+                self.worker_failed = false;
+                if let Some(err) = self.last_error.take() {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn has_pending_images(&self) -> bool {
         self.document.has_pending_images()
     }
 
     pub fn reload(&mut self, screen_size: Size) -> Result<(), Error> {
-        let old_width = self.config.padding.calculate_width(self.screen_size.width);
+        let old_width = self.inner_width();
         self.screen_size = screen_size;
         log::debug!("reload on {:?}", self.document_source.read()?);
+        if self.is_viewing_image()? {
+            self.view_image(true)?;
+        }
         match self.document_source.read()? {
             DocumentSource::File { path, .. } => self.reparse(fs::read_to_string(path)?, old_width),
             DocumentSource::Stdin { mut text } => self.reparse(
@@ -136,21 +239,63 @@ impl Model {
         }
     }
 
-    pub fn open(&self, text: String) -> Result<(), Error> {
+    pub fn open(&mut self, text: String) -> Result<(), Error> {
         let size = Size::new(
             self.config.padding.calculate_width(self.screen_size.width),
             self.inner_height(),
         );
         match self.document_source.read()? {
             DocumentSource::Image { path } => {
-                return Ok(self.cmd_tx.send(Cmd::LoadImage(Some((path, size))))?);
+                self.image_viewer = Some(ImageViewer::new(path.to_string_lossy().into_owned()));
+                return self.view_image(true);
             }
             DocumentSource::Pdf { path } => {
                 return Ok(self.cmd_tx.send(Cmd::LoadPdf(path, size))?);
             }
             _ => {}
         }
-        self.parse(self.document_id.open(), text, None)
+        let text = self.slide_text(text)?;
+        self.parse(self.document_id.open(), text, None, None)
+    }
+
+    // In slides mode, split the document into slides and return the current slide's text.
+    fn slide_text(&mut self, text: String) -> Result<String, Error> {
+        match &mut self.slides {
+            Some(slides) => slides.split(&text),
+            None => Ok(text),
+        }
+    }
+
+    /// Go `delta` slides forward or back in slides mode.
+    ///
+    /// Returns false if the slide did not change.
+    pub fn slide_by(&mut self, delta: isize) -> Result<bool, Error> {
+        let Some(slides) = &mut self.slides else {
+            return Ok(false);
+        };
+        if !slides.go_by(delta) {
+            return Ok(false);
+        }
+        let text = slides.text();
+        let width = self.inner_width();
+        let image_cache = self.document.take_image_protocols(width);
+        self.document = Document::default();
+        self.scroll = 0;
+        self.cursor = Cursor::None;
+        self.parse(self.document_id.open(), text, Some(image_cache), None)?;
+        Ok(true)
+    }
+
+    pub fn scrolled_to_bottom(&self) -> bool {
+        self.scroll >= self.total_lines().saturating_sub(self.inner_height())
+    }
+
+    // Slides are vertically centered if they fit on the screen.
+    pub fn top_offset(&self) -> u16 {
+        if self.slides.is_none() {
+            return 0;
+        }
+        self.inner_height().saturating_sub(self.total_lines()) / 2
     }
 
     fn open_new_source(&mut self, source: DocumentSource, text: String) -> Result<(), Error> {
@@ -160,10 +305,15 @@ impl Model {
             scroll: self.scroll,
         });
         self.document_source.write(source)?;
+        self.watch_source()?;
         self.cursor = Cursor::None;
         self.scroll = 0;
         self.input_queue = InputQueue::None;
+        self.image_viewer = None;
         self.image_pages.clear();
+        self.remote_images_allowed = false;
+        // The diff is of the document that was opened first.
+        self.diff_base = None;
         self.open(text)
     }
 
@@ -178,16 +328,26 @@ impl Model {
             .ok_or(Error::Navigation(NavigationError::NoHistory))?;
         self.document_source.write(source)?;
         self.document = document;
+        self.watch_source()?;
         self.cursor = Cursor::None;
         self.scroll = scroll;
         self.input_queue = InputQueue::None;
+        self.image_viewer = None;
         self.image_pages.clear();
+        self.remote_images_allowed = false;
 
         Ok(())
     }
 
     pub fn reparse(&mut self, text: String, old_width: u16) -> Result<(), Error> {
         log::info!("reparse with {:?}", self.screen_size);
+        if old_width == self.inner_width()
+            && let Some(previous) = self.document.previous()
+        {
+            // Only the changed sections are rendered again, see `Document::patch`.
+            let text = self.slide_text(text)?;
+            return self.parse(self.document_id.reload(), text, None, Some(previous));
+        }
         let image_cache = self.document.take_image_protocols(old_width);
         let cache = if image_cache.is_empty() {
             None
@@ -195,7 +355,34 @@ impl Model {
             Some(image_cache)
         };
         self.document = Document::default();
-        self.parse(self.document_id.reload(), text, cache)
+        let text = self.slide_text(text)?;
+        self.parse(self.document_id.reload(), text, cache, None)
+    }
+
+    fn remote_images(&self) -> RemoteImages {
+        if self.remote_images_allowed {
+            RemoteImages::Always
+        } else {
+            self.config.remote_images
+        }
+    }
+
+    // Load the remote images of this document, with `remote_images = "ask"`.
+    pub fn allow_remote_images(&mut self) -> Result<(), Error> {
+        if self.config.remote_images != RemoteImages::Ask || self.remote_images_allowed {
+            return Ok(());
+        }
+        self.remote_images_allowed = true;
+        let text = self.document.text().to_owned();
+        let image_cache = self.document.take_image_protocols(self.inner_width());
+        let cache = if image_cache.is_empty() {
+            None
+        } else {
+            Some(image_cache)
+        };
+        self.document = Document::default();
+        // The text is already the current slide.
+        self.parse(self.document_id.reload(), text, cache, None)
     }
 
     fn parse(
@@ -203,19 +390,27 @@ impl Model {
         next_document_id: DocumentId,
         mut text: String,
         image_cache: Option<ImageCache>,
+        previous: Option<PreviousParse>,
     ) -> Result<(), Error> {
         if text.is_empty() {
             log::warn!("model.parse: text is empty");
             return Ok(());
         }
-        let inner_width = self.config.padding.calculate_width(self.screen_size.width);
+        let inner_width = self.inner_width();
         if !text.ends_with('\n') {
             // mdfrier needs this, either because of its own limitation or something with
             // tree-sitter-md. Doesn't really matter as long as we're reading a file.
             text.push('\n');
         }
-        self.cmd_tx
-            .send(Cmd::Parse(next_document_id, inner_width, text, image_cache))?;
+        self.cmd_tx.send(Cmd::Parse(
+            next_document_id,
+            inner_width,
+            text,
+            image_cache,
+            previous,
+            self.remote_images(),
+            self.diff_base.clone(),
+        ))?;
         Ok(())
     }
 
@@ -227,14 +422,27 @@ impl Model {
     }
 
     pub fn block_padding(&self, area: Rect) -> Padding {
+        let gutter = if self.diff_base.is_some() {
+            DIFF_GUTTER
+        } else {
+            0
+        };
+        // The scrollbar is drawn in the rightmost column.
+        let scrollbar = u16::from(self.config.scrollbar);
         match self.config.padding {
-            ConfigPadding::AlignLeft { .. } => Padding::default(),
-            ConfigPadding::Centered { width } => Padding::horizontal(
-                area.width
+            ConfigPadding::AlignLeft { .. } => Padding::new(gutter, scrollbar, 0, 0),
+            ConfigPadding::Centered { width } => {
+                let padding = area
+                    .width
                     .checked_sub(width)
                     .map(|padding| padding / 2)
-                    .unwrap_or_default(),
-            ),
+                    .unwrap_or_default();
+                if padding < gutter {
+                    Padding::new(gutter, scrollbar, 0, 0)
+                } else {
+                    Padding::new(padding, padding.max(scrollbar), 0, 0)
+                }
+            }
         }
     }
 
@@ -243,37 +451,72 @@ impl Model {
     }
 
     pub fn process_events(&mut self) -> Result<(bool, bool, bool), Error> {
+        self.handle_events(None)
+    }
+
+    // Handle `first`, if any, and then the pending events.
+    fn handle_events(&mut self, mut first: Option<Event>) -> Result<(bool, bool, bool), Error> {
         let mut had_events = false;
         let mut had_done = false;
         let mut had_reload = false;
-        while let Ok(event) = self.event_rx.try_recv() {
+        while let Some(event) = first.take().or_else(|| self.event_rx.try_recv().ok()) {
             had_events = true;
 
-            if !matches!(event, Event::Parsed(..) | Event::CodeLoaded(..)) {
+            if !matches!(
+                event,
+                Event::Parsed(..) | Event::CodeLoaded(..) | Event::AnimationFrame(..)
+            ) {
                 log::debug!("{event}");
             }
 
             match event {
                 Event::WorkerError(err) => {
                     self.last_error = Some(err);
+                    self.worker_failed = true;
+                }
+                Event::WorkerWarning(err) => {
+                    self.last_error = Some(err);
                 }
                 Event::NewDocument(document_id) => {
                     log::info!("NewDocument {document_id}");
+                    if !self.document_id.is_same_document(&document_id) {
+                        // The sections of another document.
+                        self.paused_images.clear();
+                    }
                     self.document_id = document_id;
                 }
-                Event::ParseDone(document_id, last_section_id, text) => {
+                Event::ParseDone(document_id, last_section_id, text, changes) => {
                     if !self.document_id.is_same_document(&document_id) {
                         log::debug!("stale event, ignoring");
                         continue;
                     }
                     self.document.trim(last_section_id);
+                    self.document.set_text(&text);
+                    self.document.set_changes(changes);
                     self.reload_search();
+                    if let Some((snapshot, scroll)) = self.watch_snapshot.take() {
+                        self.follow_changes(&snapshot, scroll);
+                    }
                     if let Some(updated) = self.document_source.read()?.return_text(text) {
                         self.document_source.write(updated)?;
                     }
                     had_done = true;
                 }
-                Event::Parsed(document_id, section) => {
+                Event::LoadDone(document_id) => {
+                    self.loaded = Some(document_id);
+                }
+                Event::Exported(path) => {
+                    log::info!("exported to {path:?}");
+                    self.exporting = false;
+                }
+                Event::Reparsed(document_id, patches) => {
+                    if !self.document_id.is_same_document(&document_id) {
+                        log::debug!("stale event, ignoring");
+                        continue;
+                    }
+                    self.document.patch(patches);
+                }
+                Event::Parsed(document_id, section, source) => {
                     if !self.document_id.is_same_document(&document_id) {
                         log::debug!("stale event, ignoring");
                         continue;
@@ -285,6 +528,7 @@ impl Model {
                         section.content
                     );
 
+                    self.document.set_source(section.id, source);
                     self.document.push(section);
                 }
                 Event::ImageLoaded(document_id, section_id, link, proto, trailing_blank) => {
@@ -292,9 +536,23 @@ impl Model {
                         log::debug!("stale event, ignoring");
                         continue;
                     }
+                    self.watch_image(&link)?;
                     self.document
                         .update_image(section_id, link, proto, trailing_blank);
                 }
+                Event::ImageAnimated(url, animated) => {
+                    if animated {
+                        self.animated_images.insert(url);
+                    } else {
+                        self.animated_images.remove(&url);
+                    }
+                }
+                Event::AnimationFrame(document_id, section_id, url, proto) => {
+                    if !self.document_id.is_same_document(&document_id) {
+                        continue;
+                    }
+                    self.document.update_frame(section_id, &url, proto);
+                }
                 Event::ImageFailed(document_id, section_id, url, error) => {
                     if !self.document_id.is_same_document(&document_id) {
                         log::debug!("stale event, ignoring");
@@ -328,22 +586,43 @@ impl Model {
                 Event::RootImageLoaded(proto) => {
                     self.root_image_proto = Some(proto);
                 }
+                Event::ImageViewed(url, proto, view) => {
+                    let Some(viewer) = self.image_viewer.as_mut().filter(|v| v.url == url) else {
+                        log::debug!("stale event, ignoring");
+                        continue;
+                    };
+                    viewer.proto = Some(proto);
+                    viewer.view = view;
+                }
                 Event::PdfPageLoaded(_idx, proto) => {
                     self.image_pages.push(proto);
                 }
                 Event::ReferenceDefinition { id, url } => {
                     self.document.update_link_references(id, &url);
                 }
-                Event::FileChanged => {
-                    log::info!("reload: FileChanged");
+                Event::FileChanged(path) => {
+                    log::info!("reload: FileChanged({path:?})");
+                    self.take_watch_snapshot();
+                    // Otherwise the changed image would be reused from the image cache.
+                    self.forget_image(&path)?;
                     self.reload(self.screen_size)?;
                     had_reload = true;
                 }
+                Event::SourceChanged(source, text) => {
+                    if self.document_source.read()? != source {
+                        log::debug!("stale event, ignoring");
+                        continue;
+                    }
+                    log::info!("reload: SourceChanged");
+                    self.take_watch_snapshot();
+                    self.reparse(text, self.inner_width())?;
+                    had_reload = true;
+                }
                 Event::Scroll(delta) => {
                     self.scroll = self.scroll.saturating_add_signed(delta);
                 }
-                Event::NewSourceContent(text) => {
-                    self.open_new_source(self.document_source.read()?, text)?;
+                Event::NewSourceContent(source, text) => {
+                    self.open_new_source(source, text)?;
                 }
                 Event::CodeLoaded(document_id, section_id, text) => {
                     if !self.document_id.is_same_document(&document_id) {
@@ -363,9 +642,204 @@ impl Model {
                 }
             }
         }
+        if let Some((_, until)) = &self.changed_lines
+            && Instant::now() >= *until
+        {
+            self.changed_lines = None;
+            had_events = true;
+        }
         Ok((had_events, had_done, had_reload))
     }
 
+    fn take_watch_snapshot(&mut self) {
+        // Multiple changes may arrive before the reload is done.
+        if self.watch_snapshot.is_none() {
+            self.watch_snapshot = Some((self.document.snapshot(), self.scroll));
+        }
+    }
+
+    // In watch mode, follow the displayed document after navigation.
+    pub fn watch_source(&mut self) -> Result<(), Error> {
+        let Some(watcher) = &mut self.watcher else {
+            return Ok(());
+        };
+        match self.document_source.read()? {
+            DocumentSource::File { path, .. }
+            | DocumentSource::Image { path }
+            | DocumentSource::Pdf { path } => watcher.set_document(Some(&path))?,
+            _ => watcher.set_document(None)?,
+        }
+        let links: Vec<_> = self
+            .document
+            .iter()
+            .filter_map(|section| match &section.content {
+                SectionContent::Image(link, ..) => Some(link.clone()),
+                _ => None,
+            })
+            .collect();
+        for link in &links {
+            self.watch_image(link)?;
+        }
+        Ok(())
+    }
+
+    // The local path of an image link, relative to the document file.
+    fn image_path(&self, link: &MarkdownLink) -> Result<Option<PathBuf>, Error> {
+        if link.url.starts_with("https://") || link.url.starts_with("http://") {
+            return Ok(None);
+        }
+        Ok(match self.document_source.read()? {
+            DocumentSource::File {
+                basepath: Some(basepath),
+                ..
+            } => Some(basepath.join(&link.url)),
+            DocumentSource::File { .. } => Some(PathBuf::from(&link.url)),
+            _ => None,
+        })
+    }
+
+    fn watch_image(&mut self, link: &MarkdownLink) -> Result<(), Error> {
+        if self.watcher.is_none() {
+            return Ok(());
+        }
+        if let Some(path) = self.image_path(link)?
+            && let Some(watcher) = &mut self.watcher
+        {
+            watcher.add(&path)?;
+        }
+        Ok(())
+    }
+
+    fn forget_image(&mut self, path: &Path) -> Result<(), Error> {
+        let mut forget = Vec::new();
+        for section in self.document.iter() {
+            if let SectionContent::Image(link, ..) = &section.content
+                && let Some(image_path) = self.image_path(link)?
+                && normalize(&image_path) == path
+            {
+                forget.push(link.url.clone());
+            }
+        }
+        self.document.forget_images(&forget);
+        Ok(())
+    }
+
+    // Scroll to what changed in the watched file, or keep the position anchored to the content.
+    fn follow_changes(&mut self, snapshot: &[SectionSnapshot], scroll: u16) {
+        let FollowChanges { scroll, changed } = follow_changes(
+            snapshot,
+            &self.document.snapshot(),
+            scroll,
+            self.inner_height(),
+        );
+        self.scroll = scroll.min(self.total_lines().saturating_sub(self.inner_height()));
+        let highlight = Duration::from_millis(self.config.watch_highlight_milliseconds);
+        self.changed_lines = changed
+            .filter(|_| !highlight.is_zero())
+            .map(|lines| (lines, Instant::now() + highlight));
+    }
+
+    // The animated images in view, with their section and size.
+    fn animated_in_view(&self) -> Vec<(SectionID, &str, Size)> {
+        let top = i32::from(self.scroll) - i32::from(self.top_offset());
+        let bottom = top + i32::from(self.inner_height());
+        let mut y = 0;
+        let mut images = Vec::new();
+        for section in self.document.iter() {
+            if y >= bottom {
+                break;
+            }
+            let height = i32::from(section.height);
+            if y + height > top
+                && let SectionContent::Image(link, _, size, _) = &section.content
+                && self.animated_images.contains(&link.url)
+            {
+                images.push((section.id, link.url.as_str(), *size));
+            }
+            y += height;
+        }
+        images
+    }
+
+    /// Tell the worker which animated images to play, if that changed after scrolling, loading, or
+    /// pausing.
+    pub fn animate(&mut self) -> Result<(), Error> {
+        if self.config.max_animation_fps == 0 {
+            return Ok(());
+        }
+        let playing: Vec<_> = self
+            .animated_in_view()
+            .into_iter()
+            .filter(|(id, _, _)| !self.paused_images.contains(id))
+            .map(|(id, url, size)| (id, url.to_owned(), size))
+            .collect();
+        if self.playing.0 == self.document_id && self.playing.1 == playing {
+            return Ok(());
+        }
+        self.cmd_tx
+            .send(Cmd::Animate(self.document_id, playing.clone()))?;
+        self.playing = (self.document_id, playing);
+        Ok(())
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.playing.1.is_empty()
+    }
+
+    /// Pause or play the selected animated image, or else the `nth` animated image in view.
+    ///
+    /// Returns false if there is no such image.
+    pub fn toggle_animation(&mut self, nth: u16) -> bool {
+        let selected = match &self.cursor {
+            Cursor::Links(pointer) => self
+                .selected_image(pointer)
+                .filter(|link| self.animated_images.contains(&link.url))
+                .map(|_| pointer.id),
+            _ => None,
+        };
+        let Some(section_id) = selected.or_else(|| {
+            self.animated_in_view()
+                .get(usize::from(nth).saturating_sub(1))
+                .map(|(id, _, _)| *id)
+        }) else {
+            return false;
+        };
+        if !self.paused_images.remove(&section_id) {
+            self.paused_images.insert(section_id);
+        }
+        true
+    }
+
+    // How the line at `y` differs from the diff base, see `--diff`.
+    pub fn line_change(&self, y: u16) -> Option<BlockChange> {
+        self.document.change(y)
+    }
+
+    /// Scroll to the next or previous change from the diff base.
+    ///
+    /// Returns false if there is none.
+    pub fn jump_to_change(&mut self, forward: bool) -> bool {
+        let starts = self.document.change_starts();
+        let target = if forward {
+            starts.into_iter().find(|y| *y > self.scroll)
+        } else {
+            starts.into_iter().rev().find(|y| *y < self.scroll)
+        };
+        let Some(target) = target else {
+            return false;
+        };
+        let max_scroll = self.total_lines().saturating_sub(self.inner_height());
+        if forward && self.scroll >= max_scroll {
+            return false;
+        }
+        self.scroll = target.min(max_scroll);
+        true
+    }
+
+    pub fn changed_lines(&self) -> Option<&Range<u16>> {
+        self.changed_lines.as_ref().map(|(lines, _)| lines)
+    }
+
     fn reload_search(&mut self) {
         let old_cursor = std::mem::take(&mut self.cursor);
         match old_cursor {
@@ -403,6 +877,21 @@ impl Model {
         true
     }
 
+    /// Scroll proportionally to a screen row, as if dragging a scrollbar.
+    ///
+    /// Returns false the scroll did not change.
+    pub fn scroll_to_row(&mut self, row: u16) -> bool {
+        let last_row = self.inner_height().saturating_sub(1).max(1);
+        let max_scroll = self.total_lines().saturating_sub(self.inner_height());
+        let scroll =
+            (u32::from(max_scroll) * u32::from(row.min(last_row)) / u32::from(last_row)) as u16;
+        if scroll == self.scroll {
+            return false;
+        }
+        self.scroll = scroll;
+        true
+    }
+
     pub fn visible_lines(&self) -> (i16, i16) {
         let start_y = self.scroll as i16;
         let end_y = start_y + (self.inner_height() as i16).saturating_sub(1);
@@ -472,15 +961,54 @@ impl Model {
                     ))),
                 };
             }
-            DocumentSource::Github { repo, branch } => {
+            DocumentSource::Github { repo, branch, path } => {
                 if Url::parse(&link_url).is_ok() {
                     if let Err(err) = open::that(&link_url) {
                         log::error!("{err}");
                     }
+                } else if let Some(path) = github::link_path(&repo, &branch, &path, &link_url) {
+                    // Stay a github source, so that its links resolve from the new path.
+                    self.cmd_tx
+                        .send(Cmd::OpenUrl(github::source(&repo, &branch, &path)))?;
                 } else {
-                    let url = github_usercontent_url(&repo, &branch, &link_url)?;
-                    self.cmd_tx.send(Cmd::OpenUrl(url))?;
+                    let url = github::link_url(&repo, &branch, &path, &link_url)?;
+                    self.cmd_tx.send(Cmd::OpenUrl(url.to_string()))?;
+                }
+            }
+            DocumentSource::Forge {
+                forge,
+                repo,
+                branch,
+                path,
+            } => {
+                if Url::parse(&link_url).is_ok() {
+                    if let Err(err) = open::that(&link_url) {
+                        log::error!("{err}");
+                    }
+                } else if let Some(path) = forge.link_path(&repo, &branch, &path, &link_url) {
+                    self.cmd_tx
+                        .send(Cmd::OpenUrl(forge.source(&repo, &branch, &path)))?;
+                } else {
+                    let url = forge.link_url(&repo, &branch, &path, &link_url)?;
+                    self.cmd_tx.send(Cmd::OpenUrl(url.to_string()))?;
+                }
+            }
+            DocumentSource::Git { repo, rev, path } => {
+                if Url::parse(&link_url).is_ok() {
+                    return Ok(open::that(&link_url)?);
                 }
+                let Some(path) = git::link_path(&path, &link_url) else {
+                    return Err(Error::Git(format!(
+                        "{link_url} is outside of the repository"
+                    )));
+                };
+                if !path.ends_with(".md") {
+                    return Err(Error::Git(format!(
+                        "{rev}:{path} is not a markdown document"
+                    )));
+                }
+                let text = git::read_to_string(&repo, &rev, &path)?;
+                return self.open_new_source(DocumentSource::Git { repo, rev, path }, text);
             }
             DocumentSource::HyperText { url } => {
                 if Url::parse(&link_url).is_ok() {
@@ -524,6 +1052,137 @@ impl Model {
         )
     }
 
+    // Open the document file, or the file of a `git:` source, at another revision.
+    pub fn open_rev(&mut self, rev: &str) -> Result<(), Error> {
+        let (repo, path) = match self.document_source.read()? {
+            DocumentSource::Git { repo, path, .. } => (repo, path),
+            DocumentSource::File { path, .. } => {
+                git::locate(&path).map_err(|_err| Error::Command(CommandError::NoRevisions))?
+            }
+            _ => return Err(Error::Command(CommandError::NoRevisions)),
+        };
+        let text = git::read_to_string(&repo, rev, &path)?;
+        let rev = rev.to_owned();
+        self.open_new_source(DocumentSource::Git { repo, rev, path }, text)
+    }
+
+    /// Translate a screen position into document x and y, if it's inside the document area.
+    fn document_position(&self, column: u16, row: u16) -> Option<(u16, u16)> {
+        let row = row.checked_sub(self.top_offset())?;
+        if row >= self.inner_height() {
+            return None;
+        }
+        let padding = self.block_padding(Rect::from((Position::ORIGIN, self.screen_size)));
+        let x = column.checked_sub(padding.left)?;
+        if x >= self.inner_width() {
+            return None;
+        }
+        Some((x, self.scroll.saturating_add(row)))
+    }
+
+    fn inner_width(&self) -> u16 {
+        let width = self.config.padding.calculate_width(self.screen_size.width);
+        // Keep room for the gutter and the scrollbar, see `block_padding`.
+        let mut reserved = u16::from(self.config.scrollbar);
+        if self.diff_base.is_some() {
+            reserved += DIFF_GUTTER;
+        }
+        width.min(self.screen_size.width.saturating_sub(reserved))
+    }
+
+    /// Returns the link under a screen position, if any.
+    pub fn link_at(&self, column: u16, row: u16) -> Option<(CursorPointer, SourceContent)> {
+        let (x, y) = self.document_position(column, row)?;
+        let pointer = self.document.link_at(x, y, self.inner_width())?;
+        let url = self.url_at_pointer(&pointer)?;
+        Some((pointer, url))
+    }
+
+    /// Mouse click: select and open a link, view an image fullscreen, or select a task.
+    ///
+    /// Returns false if nothing was clicked.
+    pub fn click(&mut self, column: u16, row: u16) -> Result<bool, Error> {
+        if let Some((pointer, url)) = self.link_at(column, row) {
+            self.cursor = Cursor::Links(pointer);
+            self.open_link(url.to_string())?;
+            return Ok(true);
+        }
+        if let Some((_, y)) = self.document_position(column, row)
+            && let Some(link) = self.document.image_at(y).cloned()
+        {
+            self.open_image(link)?;
+            return Ok(true);
+        }
+        if let Some((x, y)) = self.document_position(column, row)
+            && let Some(pointer) = self.document.task_at(x, y)
+        {
+            self.cursor = Cursor::Links(pointer);
+            return Ok(true);
+        }
+        if matches!(self.cursor, Cursor::Links(_)) {
+            self.cursor = Cursor::None;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Show an image of the document fullscreen, until `close_image`.
+    pub fn open_image(&mut self, link: MarkdownLink) -> Result<(), Error> {
+        self.image_viewer = Some(ImageViewer::new(link.url));
+        self.input_queue = InputQueue::None;
+        self.view_image(true)
+    }
+
+    pub fn close_image(&mut self) {
+        self.image_viewer = None;
+    }
+
+    // Load the viewed image at its zoom and center, for the screen size.
+    fn view_image(&self, reload: bool) -> Result<(), Error> {
+        let Some(viewer) = &self.image_viewer else {
+            return Ok(());
+        };
+        let size = Size::new(
+            self.config.padding.calculate_width(self.screen_size.width),
+            self.inner_height(),
+        );
+        Ok(self.cmd_tx.send(Cmd::ViewImage(
+            viewer.url.clone(),
+            size,
+            viewer.zoom,
+            viewer.view.center,
+            self.remote_images(),
+            reload,
+        ))?)
+    }
+
+    /// Zoom the viewed image in, or out with negative `steps`.
+    ///
+    /// Returns false if nothing changed.
+    pub fn zoom_image(&mut self, steps: i32) -> Result<bool, Error> {
+        if !self.image_viewer.as_mut().is_some_and(|v| v.zoom_by(steps)) {
+            return Ok(false);
+        }
+        self.view_image(false)?;
+        Ok(true)
+    }
+
+    /// Pan the viewed image by `dx` and `dy` steps.
+    ///
+    /// Returns false if nothing changed.
+    pub fn pan_image(&mut self, dx: i32, dy: i32) -> Result<bool, Error> {
+        if !self.image_viewer.as_mut().is_some_and(|v| v.pan_by(dx, dy)) {
+            return Ok(false);
+        }
+        self.view_image(false)?;
+        Ok(true)
+    }
+
+    /// The image selected with the link cursor, if any.
+    pub fn selected_image(&self, pointer: &CursorPointer) -> Option<&MarkdownLink> {
+        self.document.image_by_id(pointer.id)
+    }
+
     /// Returns the URL of the currently selected link, if any.
     pub fn selected_link_url(&self, pointer: &CursorPointer) -> Option<SourceContent> {
         self.url_at_pointer(pointer)
@@ -683,6 +1342,56 @@ impl Model {
         self.document.iter()
     }
 
+    /// The headers leading up to the top of the viewport, outermost first.
+    pub fn breadcrumb(&self) -> Vec<String> {
+        self.breadcrumb_at(self.scroll)
+    }
+
+    /// The headers of the top of the viewport, if the innermost has been scrolled out of view.
+    pub fn sticky_breadcrumb(&self) -> Vec<String> {
+        let Some(above) = self.scroll.checked_sub(1) else {
+            return Vec::new();
+        };
+        // A header right at the top is fully in view, no need to pin anything.
+        if let Some((y, section)) = self.document.section_at(self.scroll)
+            && y == self.scroll
+            && matches!(
+                section.content,
+                SectionContent::Header(..) | SectionContent::HeaderPlaceholder(..)
+            )
+        {
+            return Vec::new();
+        }
+        self.breadcrumb_at(above)
+    }
+
+    // The headers leading up to and including line `at`.
+    fn breadcrumb_at(&self, at: u16) -> Vec<String> {
+        let mut crumbs: Vec<(SectionID, String, u8)> = Vec::new();
+        let mut y = 0;
+        for section in self.document.iter() {
+            if y > at {
+                break;
+            }
+            if let SectionContent::Header(text, tier, _)
+            | SectionContent::HeaderPlaceholder(text, tier, _) = &section.content
+            {
+                // Long headers may be split into multiple rows with the same id.
+                if let Some((id, last_text, _)) = crumbs.last_mut()
+                    && *id == section.id
+                {
+                    last_text.push(' ');
+                    last_text.push_str(text);
+                } else {
+                    crumbs.retain(|(_, _, t)| t < tier);
+                    crumbs.push((section.id, text.clone(), *tier));
+                }
+            }
+            y += section.height;
+        }
+        crumbs.into_iter().map(|(_, text, _)| text).collect()
+    }
+
     pub fn position_cursor(&mut self, positioning: CursorPositioning) {
         if let Some(pointer_y) = self.cursor.pointer().and_then(|p| self.document.get_y(p)) {
             let (from, to) = self.visible_lines();
@@ -712,6 +1421,16 @@ impl Model {
                 _ => {
                     if let Some(path) = command.strip_prefix("open ") {
                         self.open_file(path, None)
+                    } else if let Some(rev) = command.strip_prefix("rev ") {
+                        self.open_rev(rev.trim())
+                    } else if let Some(path) = command.strip_prefix("write ") {
+                        let path = PathBuf::from(path.trim());
+                        let format = ExportFormat::from_path(&path).ok_or_else(|| {
+                            Error::Command(CommandError::UnknownExportFormat(
+                                path.display().to_string(),
+                            ))
+                        })?;
+                        self.export(format, Some(path))
                     } else {
                         Err(Error::Command(CommandError::UnknownCommand(command)))
                     }
@@ -720,10 +1439,50 @@ impl Model {
         }
     }
 
+    // The command to edit the document file, at the line at the top of the viewport.
+    pub fn edit_command(&self) -> Result<Command, Error> {
+        let DocumentSource::File { path, .. } = self.document_source.read()? else {
+            return Err(Error::Command(CommandError::NotEditable));
+        };
+        let line = self.source_line(&fs::read_to_string(&path)?);
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_owned());
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("{editor} +{line} \"$FILE\""))
+            .env("FILE", path);
+        Ok(command)
+    }
+
+    // The 1-based line of the document's source `text` at the top of the viewport.
+    fn source_line(&self, text: &str) -> usize {
+        let offset = self.document.source_offset(self.scroll).unwrap_or_default();
+        let newlines = |text: &str| text.bytes().take(offset).filter(|b| *b == b'\n').count();
+        1 + match &self.slides {
+            // The offset is in the slide's text.
+            Some(slides) => slides.line() + newlines(&slides.text()),
+            None => newlines(text),
+        }
+    }
+
     pub fn is_help_screen(&self) -> Result<bool, Error> {
         Ok(self.document_source.read()? == DocumentSource::BuiltIn(BuiltIn::Help))
     }
 
+    // An image source opened from a document, e.g. by following a link to it.
+    pub fn is_zoomed_image(&self) -> Result<bool, Error> {
+        Ok(!self.document_history.is_empty()
+            && matches!(self.document_source.read()?, DocumentSource::Image { .. }))
+    }
+
+    // An image of the document shown fullscreen with `open_image`.
+    pub fn is_viewing_image(&self) -> Result<bool, Error> {
+        Ok(self.image_viewer.is_some()
+            && !matches!(self.document_source.read()?, DocumentSource::Image { .. }))
+    }
+
     pub fn set_last_error(&mut self, err: Error) {
         log::error!("Last error: {err}");
         self.last_error = Some(err);
@@ -787,18 +1546,22 @@ impl From<char> for CursorPositioning {
 #[expect(clippy::unwrap_used)]
 mod tests {
 
-    use std::sync::mpsc;
+    use std::{collections::HashSet, sync::mpsc};
 
-    use mdfrier::SourceContent;
-    use ratatui::text::Line;
+    use image::{DynamicImage, RgbaImage};
+    use mdfrier::{MarkdownLink, SourceContent};
+    use ratatui::{layout::Size, text::Line};
+    use ratatui_image::{picker::Picker, sliced::SlicedProtocol};
 
     use crate::{
         Cmd, DocumentId, Event,
         config::UserConfig,
         cursor::{Cursor, CursorPointer},
-        document::{Document, LineExtra, LinkReference, Section, SectionContent},
+        document::{Document, LineExtra, LinkReference, Section, SectionContent, SectionSource},
+        error::Error,
         model::{InputQueue, Model},
-        sources::SharedDocumentSource,
+        sources::{DocumentSource, SharedDocumentSource},
+        testing,
     };
 
     /// Test model, 80x20 screen size.
@@ -815,11 +1578,26 @@ mod tests {
             cmd_tx,
             event_rx,
             document_id: DocumentId::default(),
+            loaded: None,
+            exporting: false,
+            worker_failed: false,
+            remote_images_allowed: false,
+            animated_images: HashSet::new(),
+            paused_images: HashSet::new(),
+            playing: Default::default(),
             document_source: SharedDocumentSource::test(),
             document_history: Vec::new(),
             last_error: None,
             root_image_proto: None,
+            image_viewer: None,
             image_pages: Vec::new(),
+            scrollbar_drag: false,
+            watcher: None,
+            slides: None,
+            diff_base: None,
+            inline: false,
+            watch_snapshot: None,
+            changed_lines: None,
         }
     }
 
@@ -1204,4 +1982,421 @@ mod tests {
         model.cursor_next(4);
         assert_cursor_link(&model, &links[3]);
     }
+
+    #[test]
+    fn link_at_wrapped_link() {
+        let mut model = test_model();
+        let link = SourceContent::from("http://a.com");
+        model.document.push(Section {
+            id: 1,
+            height: 1,
+            content: SectionContent::Lines(vec![(Line::from("text"), vec![])]),
+        });
+        model.document.push(Section {
+            id: 2,
+            height: 2,
+            content: SectionContent::Lines(vec![
+                (Line::from("prefix link"), vec![]),
+                (
+                    Line::from("desc suffix"),
+                    vec![LineExtra::Link {
+                        source: link.clone(),
+                        start: 7,
+                        end: 4,
+                        lines: Some(1),
+                        reference: LinkReference::None,
+                    }],
+                ),
+            ]),
+        });
+
+        assert_eq!(model.link_at(7, 0), None);
+        assert_eq!(model.link_at(6, 1), None);
+        let (pointer, url) = model.link_at(7, 1).unwrap();
+        assert_eq!(pointer, CursorPointer { id: 2, index: 0 });
+        assert_eq!(url, link);
+        assert!(model.link_at(3, 2).is_some());
+        assert_eq!(model.link_at(4, 2), None);
+
+        model.scroll = 1;
+        assert!(model.link_at(3, 1).is_some());
+        assert_eq!(model.link_at(3, 2), None);
+    }
+
+    #[test]
+    fn scroll_to_row() {
+        let mut model = test_model();
+        for id in 0..100 {
+            model.document.push(Section {
+                id,
+                height: 1,
+                content: SectionContent::Lines(vec![(Line::from("text"), vec![])]),
+            });
+        }
+        // 19 inner rows, 81 lines of max scroll.
+        assert!(model.scroll_to_row(18));
+        assert_eq!(model.scroll, 81);
+        assert!(model.scroll_to_row(9));
+        assert_eq!(model.scroll, 40);
+        assert!(!model.scroll_to_row(9));
+        assert!(model.scroll_to_row(0));
+        assert_eq!(model.scroll, 0);
+    }
+
+    #[test]
+    fn breadcrumb() {
+        let mut model = test_model();
+        let headers = [(1, "Install"), (2, "Linux"), (3, "Nix"), (2, "macOS")];
+        for (id, (tier, text)) in headers.into_iter().enumerate() {
+            model.document.push(Section {
+                id: id * 2,
+                height: 2,
+                content: SectionContent::Header(text.to_owned(), tier, None),
+            });
+            model.document.push(Section {
+                id: id * 2 + 1,
+                height: 10,
+                content: SectionContent::Lines(vec![(Line::from("text"), vec![])]),
+            });
+        }
+
+        assert_eq!(model.breadcrumb(), vec!["Install"]);
+        model.scroll = 30;
+        assert_eq!(model.breadcrumb(), vec!["Install", "Linux", "Nix"]);
+        model.scroll = 36;
+        assert_eq!(model.breadcrumb(), vec!["Install", "macOS"]);
+    }
+
+    #[test]
+    fn sticky_breadcrumb() {
+        let mut model = test_model();
+        let headers = [(1, "Install"), (2, "Linux")];
+        for (id, (tier, text)) in headers.into_iter().enumerate() {
+            model.document.push(Section {
+                id: id * 2,
+                height: 2,
+                content: SectionContent::Header(text.to_owned(), tier, None),
+            });
+            model.document.push(Section {
+                id: id * 2 + 1,
+                height: 10,
+                content: SectionContent::Lines(vec![(Line::from("text"), vec![])]),
+            });
+        }
+
+        assert!(model.sticky_breadcrumb().is_empty());
+        model.scroll = 1;
+        assert_eq!(model.sticky_breadcrumb(), vec!["Install"]);
+        // The "Linux" header is in view.
+        model.scroll = 12;
+        assert!(model.sticky_breadcrumb().is_empty());
+        model.scroll = 13;
+        assert_eq!(model.sticky_breadcrumb(), vec!["Install", "Linux"]);
+    }
+
+    #[test]
+    fn views_selected_image() {
+        let mut model = test_model();
+        let (cmd_tx, cmd_rx) = mpsc::channel::<Cmd>();
+        model.cmd_tx = cmd_tx;
+        let link = SourceContent::from("http://a.com");
+        let line = |id| Section {
+            id,
+            height: 10,
+            content: SectionContent::Lines(vec![(
+                Line::from("http://a.com"),
+                vec![LineExtra::Link {
+                    source: link.clone(),
+                    start: 0,
+                    end: 11,
+                    lines: None,
+                    reference: LinkReference::None,
+                }],
+            )]),
+        };
+        let size = Size::new(4, 2);
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(8, 8));
+        let proto = SlicedProtocol::new(&Picker::halfblocks(), image, Some(size)).unwrap();
+        model.document.push(line(1));
+        model.document.push(Section {
+            id: 2,
+            height: 2,
+            content: SectionContent::Image(
+                MarkdownLink {
+                    url: "diagram.png".to_owned(),
+                    description: String::new(),
+                },
+                proto,
+                size,
+                size,
+            ),
+        });
+        model.document.push(line(3));
+
+        // The image is selected between the links.
+        model.cursor_next(2);
+        let Cursor::Links(pointer) = &model.cursor else {
+            panic!("expected a link cursor");
+        };
+        let pointer = pointer.clone();
+        let image = model.selected_image(&pointer).cloned().unwrap();
+        assert_eq!(image.url, "diagram.png");
+        model.cursor_next(1);
+        assert_cursor_link(&model, &link);
+        model.cursor_prev(1);
+        assert_eq!(model.cursor, Cursor::Links(pointer.clone()));
+
+        let scroll = model.scroll;
+        model.open_image(image).unwrap();
+        assert!(model.is_viewing_image().unwrap());
+        let Ok(Cmd::ViewImage(url, _, 0, _, _, true)) = cmd_rx.try_recv() else {
+            panic!("expected Cmd::ViewImage");
+        };
+        assert_eq!(url, "diagram.png");
+
+        assert!(model.zoom_image(2).unwrap());
+        assert!(matches!(
+            cmd_rx.try_recv(),
+            Ok(Cmd::ViewImage(_, _, 2, _, _, false))
+        ));
+        assert!(model.zoom_image(-5).unwrap());
+        assert!(!model.zoom_image(-1).unwrap());
+
+        // Back to the same place in the document.
+        model.close_image();
+        assert!(!model.is_viewing_image().unwrap());
+        assert_eq!(model.scroll, scroll);
+        assert_eq!(model.cursor, Cursor::Links(pointer));
+    }
+
+    #[test]
+    fn wait_loaded_despite_warning() {
+        let (event_tx, event_rx) = mpsc::channel::<Event>();
+        let mut model = Model {
+            event_rx,
+            ..test_model()
+        };
+        let warning = Error::CodeHighlight("unknown code theme: nope".to_owned());
+        event_tx.send(Event::WorkerWarning(warning)).unwrap();
+        event_tx
+            .send(Event::LoadDone(DocumentId::default()))
+            .unwrap();
+        model.wait_loaded().unwrap();
+        assert!(
+            matches!(model.last_error, Some(Error::CodeHighlight(_))),
+            "the warning should be kept"
+        );
+    }
+
+    #[test]
+    fn wait_loaded_fails_with_worker() {
+        let (event_tx, event_rx) = mpsc::channel::<Event>();
+        let mut model = Model {
+            event_rx,
+            ..test_model()
+        };
+        event_tx
+            .send(Event::WorkerError(Error::MarkdownParse))
+            .unwrap();
+        assert!(
+            matches!(model.wait_loaded(), Err(Error::MarkdownParse)),
+            "the worker error should be returned"
+        );
+
+        // Rather than waiting forever.
+        drop(event_tx);
+        assert!(
+            matches!(model.wait_loaded(), Err(Error::ThreadClosed)),
+            "a closed worker should fail"
+        );
+    }
+
+    // A paragraph of 2 lines and a code block of 3 lines, with the source of each line.
+    const EDIT_TEXT: &str = "Intro\nmore\n\n```\na\nb\nc\n```\n";
+
+    fn edit_model() -> Model {
+        let mut model = test_model();
+        for (id, height, range, lines) in [
+            (0, 2, 0..11, vec![0..11, 0..11]),
+            (1, 3, 12..26, vec![16..18, 18..20, 20..22]),
+        ] {
+            model.document.push(Section {
+                id,
+                height,
+                content: SectionContent::Lines(Vec::new()),
+            });
+            model
+                .document
+                .set_source(id, SectionSource { range, lines });
+        }
+        model
+    }
+
+    #[test]
+    fn source_line() {
+        let mut model = edit_model();
+        // Lines of a paragraph are at its start, each line of code at its own line.
+        for (scroll, line) in [(0, 1), (1, 1), (2, 5), (3, 6), (4, 7)] {
+            model.scroll = scroll;
+            assert_eq!(model.source_line(EDIT_TEXT), line, "scrolled to {scroll}");
+        }
+    }
+
+    #[test]
+    fn edit_command() {
+        let mut model = edit_model();
+        assert!(
+            matches!(
+                model.edit_command(),
+                Err(Error::Command(crate::error::CommandError::NotEditable))
+            ),
+            "only files are editable"
+        );
+
+        let path = testing::temp_path("edit_test").with_extension("md");
+        std::fs::write(&path, EDIT_TEXT).unwrap();
+        model
+            .document_source
+            .write(DocumentSource::File {
+                path: path.clone(),
+                basepath: None,
+            })
+            .unwrap();
+        model.scroll = 3;
+        let command = model.edit_command();
+        std::fs::remove_file(&path).unwrap();
+        let command = command.unwrap();
+
+        let script = command.get_args().nth(1).unwrap().to_string_lossy();
+        assert!(script.ends_with(" +6 \"$FILE\""), "{script}");
+        let file = command
+            .get_envs()
+            .find_map(|(key, value)| (key == "FILE").then_some(value))
+            .flatten();
+        assert_eq!(file, Some(path.as_os_str()));
+    }
+
+    #[test]
+    fn git_link_errors() {
+        let mut model = test_model();
+        model
+            .document_source
+            .write(DocumentSource::Git {
+                repo: testing::temp_path("git_link_test"),
+                rev: "v1".to_owned(),
+                path: "docs/guide.md".to_owned(),
+            })
+            .unwrap();
+        let error = |model: &mut Model, link: &str| {
+            model
+                .open_link(link.to_owned())
+                .map_err(|err| err.to_string())
+                .unwrap_err()
+        };
+        assert_eq!(
+            error(&mut model, "diagram.png"),
+            "Git error: v1:docs/diagram.png is not a markdown document"
+        );
+        assert_eq!(
+            error(&mut model, "../../README.md"),
+            "Git error: ../../README.md is outside of the repository"
+        );
+    }
+
+    #[test]
+    fn toggle_animation() {
+        let mut model = test_model();
+        let size = Size::new(4, 2);
+        // The same animation twice.
+        for id in [1, 2] {
+            let image = DynamicImage::ImageRgba8(RgbaImage::new(8, 8));
+            let proto = SlicedProtocol::new(&Picker::halfblocks(), image, Some(size)).unwrap();
+            model.document.push(Section {
+                id,
+                height: 2,
+                content: SectionContent::Image(
+                    MarkdownLink {
+                        url: "anim.gif".to_owned(),
+                        description: String::new(),
+                    },
+                    proto,
+                    size,
+                    size,
+                ),
+            });
+        }
+        model.animated_images.insert("anim.gif".to_owned());
+
+        // Only the selected one.
+        model.cursor = Cursor::Links(CursorPointer { id: 2, index: 0 });
+        assert!(model.toggle_animation(1), "the selected image toggles");
+        assert_eq!(model.paused_images, HashSet::from([2]));
+
+        // Else the nth in view.
+        model.cursor = Cursor::None;
+        assert!(model.toggle_animation(1), "the first image toggles");
+        assert_eq!(model.paused_images, HashSet::from([1, 2]));
+        assert!(model.toggle_animation(2), "the second image toggles");
+        assert_eq!(model.paused_images, HashSet::from([1]));
+        assert!(!model.toggle_animation(3), "there is no third image");
+    }
+
+    #[test]
+    fn click_task() {
+        let mut model = test_model();
+        let task = || vec![LineExtra::Task { start: 2, end: 5 }];
+        model.document.push(Section {
+            id: 0,
+            height: 2,
+            content: SectionContent::Lines(vec![
+                (Line::from("• [ ] todo"), task()),
+                (Line::from("• [ ] more"), task()),
+            ]),
+        });
+        let left = model
+            .block_padding(ratatui::layout::Rect::new(0, 0, 80, 20))
+            .left;
+        let row = model.top_offset();
+        assert!(
+            !model.click(left, row + 1).unwrap(),
+            "bullet is not clickable"
+        );
+        assert_eq!(model.cursor, Cursor::None);
+        assert!(
+            model.click(left + 4, row + 1).unwrap(),
+            "checkbox is clickable"
+        );
+        assert_eq!(
+            model.cursor,
+            Cursor::Links(CursorPointer { id: 0, index: 1 })
+        );
+        assert_eq!(
+            model.selected_link_url(&CursorPointer { id: 0, index: 1 }),
+            None
+        );
+    }
+
+    #[test]
+    fn scrolled_to_bottom() {
+        let mut model = test_model();
+        model.document.push(Section {
+            id: 0,
+            height: 30,
+            content: SectionContent::Lines(Vec::new()),
+        });
+        assert!(!model.scrolled_to_bottom(), "a page down is left");
+        model.scroll_by(100);
+        assert!(model.scrolled_to_bottom(), "at the last page");
+    }
+
+    #[test]
+    fn scrollbar_column() {
+        let mut model = test_model();
+        assert_eq!(model.inner_width(), 80);
+        model.config.scrollbar = true;
+        // The content ends before the scrollbar.
+        assert_eq!(model.inner_width(), 79);
+        let area = ratatui::layout::Rect::new(0, 0, 80, 20);
+        assert_eq!(model.block_padding(area).right, 1);
+    }
 }
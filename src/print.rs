@@ -0,0 +1,100 @@
+use std::{io::Write, num::NonZeroUsize};
+
+use ratatui::{
+    buffer::{Buffer, CellDiffOption},
+    crossterm::{
+        queue,
+        style::{
+            Attribute, Print, ResetColor, SetAttribute, SetAttributes, SetBackgroundColor,
+            SetForegroundColor,
+        },
+    },
+    layout::{Rect, Size},
+    prelude::IntoCrossterm as _,
+    style::{Color, Modifier},
+};
+use unicode_width::UnicodeWidthStr as _;
+
+use crate::{error::Error, links::modifier_to_attributes, model::Model, view::view};
+
+// The width to print at when not given, and stdout is not a terminal.
+pub const DEFAULT_WIDTH: u16 = 80;
+// The screen height while loading, the document is printed at its full height.
+pub const DEFAULT_HEIGHT: u16 = 24;
+
+// Render the whole document to `out` once it is loaded, for `--print`.
+//
+// Without `styled`, only the text is written, e.g. when stdout is not a terminal.
+pub fn print<W: Write>(mut model: Model, out: &mut W, styled: bool) -> Result<(), Error> {
+    model.wait_loaded()?;
+    // E.g. an unknown code theme, that the worker fell back from.
+    if let Some(err) = model.last_error.take() {
+        eprintln!("{err}");
+    }
+
+    // The view always keeps a status line at the bottom, which is not printed.
+    let height = model.total_lines().saturating_add(1);
+    model.screen_size = Size::new(model.screen_size.width, height);
+    let mut buf = Buffer::empty(Rect::new(0, 0, model.screen_size.width, height));
+    view(&model, &mut buf);
+
+    for y in 0..height - 1 {
+        write_row(out, &buf, y, styled)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Write one row of the buffer, without trailing blanks.
+fn write_row<W: Write>(out: &mut W, buf: &Buffer, y: u16, styled: bool) -> Result<(), Error> {
+    let area = buf.area;
+    let end = (area.left()..area.right())
+        .rev()
+        .find(|x| {
+            let cell = &buf[(*x, y)];
+            cell.symbol() != " "
+                || (styled && cell.bg != Color::Reset)
+                || cell.diff_option != CellDiffOption::None
+        })
+        .map_or(area.left(), |x| x + 1);
+
+    let mut style = None;
+    let mut to_skip = 0;
+    for x in area.left()..end {
+        let cell = &buf[(x, y)];
+        if cell.diff_option == CellDiffOption::Skip {
+            continue;
+        }
+        if to_skip > 0 {
+            to_skip -= 1;
+            continue;
+        }
+        let width = match cell.diff_option {
+            CellDiffOption::ForcedWidth(width) => {
+                // E.g. OSC 8 links, which bring their own styles.
+                style = None;
+                NonZeroUsize::from(width).get()
+            }
+            _ => cell.symbol().width(),
+        };
+        to_skip = width.saturating_sub(1);
+        if styled && style != Some((cell.fg, cell.bg, cell.modifier)) {
+            style = Some((cell.fg, cell.bg, cell.modifier));
+            queue!(
+                out,
+                SetAttribute(Attribute::Reset),
+                SetForegroundColor(cell.fg.into_crossterm()),
+                SetBackgroundColor(cell.bg.into_crossterm()),
+            )?;
+            if cell.modifier != Modifier::empty() {
+                queue!(out, SetAttributes(modifier_to_attributes(cell.modifier)))?;
+            }
+        }
+        queue!(out, Print(cell.symbol()))?;
+    }
+    if styled {
+        queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
@@ -1,12 +1,23 @@
 use std::{
+    io,
+    process::Command,
     sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError},
     thread,
 };
 
-use ratatui::{DefaultTerminal, buffer::Buffer, layout::Position};
+use ratatui::{
+    DefaultTerminal,
+    buffer::Buffer,
+    crossterm::{
+        self,
+        event::{DisableMouseCapture, EnableMouseCapture},
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::Position,
+};
 
 use crate::{
-    error::Error,
+    error::{CommandError, Error},
     keybindings::{self, PollResult},
     model::Model,
     view::view,
@@ -28,19 +39,17 @@ use crate::{
 /// movement speed is consistent, and there is also no other "input buildup".
 pub fn run_loop(mut terminal: DefaultTerminal, mut model: Model) -> Result<(), Error> {
     // Quick, say hi!
-    terminal.draw(|frame| {
-        let cursor_position = view(&model, frame.buffer_mut());
-        if let Some(cursor_position) = cursor_position {
-            frame.set_cursor_position(cursor_position);
-        }
-    })?;
+    let mut buf = Buffer::empty(model.screen_size.into());
+    let cursor_position = view(&model, &mut buf);
+    draw(&mut terminal, &mut buf, cursor_position)?;
 
     // Send the buffer back and forth to avoid allocating every frame, also serves as "dropped"
     // signal, when not returned already.
-    let (buf_in_tx, buf_in_rx) = mpsc::sync_channel::<(Buffer, Option<Position>)>(1);
+    // The bool clears the terminal before drawing, e.g. after an editor ran.
+    let (buf_in_tx, buf_in_rx) = mpsc::sync_channel::<(Buffer, Option<Position>, bool)>(1);
     let (buf_out_tx, buf_out_rx) = mpsc::sync_channel::<Buffer>(1);
     buf_out_tx
-        .send(Buffer::empty(model.screen_size.into()))
+        .send(buf)
         .expect("unreachable: channel has capacity 1");
 
     let render_thread = thread::Builder::new()
@@ -48,6 +57,8 @@ pub fn run_loop(mut terminal: DefaultTerminal, mut model: Model) -> Result<(), E
         .spawn(move || -> Result<(), Error> { render(terminal, buf_in_rx, buf_out_tx) })?;
 
     let mut dropped = false;
+    // The buffer taken back from the render thread while suspended.
+    let mut spare = None;
     loop {
         let (had_events, _, had_reload) = model.process_events()?;
 
@@ -56,12 +67,31 @@ pub fn run_loop(mut terminal: DefaultTerminal, mut model: Model) -> Result<(), E
             PollResult::None => (false, false),
             PollResult::HadInput => (true, false),
             PollResult::SkipRender => (true, true),
+            PollResult::Suspend(command) => {
+                // Wait for the render thread to finish, so that it does not draw over the command.
+                let buf = buf_out_rx
+                    .recv()
+                    .map_err(|err| Error::Thread(format!("no buffer: {err}")))?;
+                spare = Some(buf);
+                if let Err(err) = suspend(command, model.config.enable_mouse_capture) {
+                    model.set_last_error(err);
+                }
+                model.reload(model.screen_size)?;
+                (true, false)
+            }
         };
 
+        model.animate()?;
+
         let should_render = dropped || ((had_events || had_input) && !skip_render && !had_reload);
 
         if should_render {
-            let mut buf = match buf_out_rx.try_recv() {
+            let clear = spare.is_some();
+            let received = match spare.take() {
+                Some(buf) => Ok(buf),
+                None => buf_out_rx.try_recv(),
+            };
+            let mut buf = match received {
                 Err(err) => match err {
                     TryRecvError::Disconnected => {
                         log::warn!("no buffer: disconnected");
@@ -80,7 +110,7 @@ pub fn run_loop(mut terminal: DefaultTerminal, mut model: Model) -> Result<(), E
             };
 
             let cursor_position = view(&model, &mut buf);
-            if let Err(err) = buf_in_tx.try_send((buf, cursor_position)) {
+            if let Err(err) = buf_in_tx.try_send((buf, cursor_position, clear)) {
                 match err {
                     TrySendError::Full(_) => {
                         // How did we get here?
@@ -108,23 +138,14 @@ pub fn run_loop(mut terminal: DefaultTerminal, mut model: Model) -> Result<(), E
 
 fn render(
     mut terminal: DefaultTerminal,
-    buf_in: Receiver<(Buffer, Option<Position>)>,
+    buf_in: Receiver<(Buffer, Option<Position>, bool)>,
     buf_out: SyncSender<Buffer>,
 ) -> Result<(), Error> {
-    while let Ok((mut buf, cursor_position)) = buf_in.recv() {
-        let mut hide_cursor = false;
-        terminal.draw(|frame| {
-            std::mem::swap(frame.buffer_mut(), &mut buf);
-            // frame.set_cursor_position(cursor_position);
-            if let Some(cursor_position) = cursor_position {
-                frame.set_cursor_position(cursor_position);
-            } else {
-                hide_cursor = true;
-            }
-        })?;
-        if hide_cursor {
-            terminal.hide_cursor()?;
+    while let Ok((mut buf, cursor_position, clear)) = buf_in.recv() {
+        if clear {
+            terminal.clear()?;
         }
+        draw(&mut terminal, &mut buf, cursor_position)?;
 
         // Guaranteed to be empty since we hold the buffer.
         buf_out
@@ -132,5 +153,57 @@ fn render(
             .map_err(|err| Error::Thread(format!("could not return buffer: {err}")))?;
     }
     // Cursor might be in weird places, prompt or whatever should always show at the bottom now.
-    Ok(terminal.set_cursor_position((0, terminal.size()?.height - 1))?)
+    let area = terminal.get_frame().area();
+    Ok(terminal.set_cursor_position((0, area.bottom() - 1))?)
+}
+
+// Draw a buffer that was rendered at the origin into the terminal's viewport, which is below the
+// shell prompt with an inline viewport.
+fn draw(
+    terminal: &mut DefaultTerminal,
+    buf: &mut Buffer,
+    cursor_position: Option<Position>,
+) -> Result<(), Error> {
+    let mut hide_cursor = false;
+    terminal.draw(|frame| {
+        let area = frame.area();
+        let origin = buf.area;
+        buf.area.x = area.x;
+        buf.area.y = area.y;
+        std::mem::swap(frame.buffer_mut(), buf);
+        buf.area.x = origin.x;
+        buf.area.y = origin.y;
+        if let Some(Position { x, y }) = cursor_position {
+            frame.set_cursor_position((area.x + x, area.y + y));
+        } else {
+            hide_cursor = true;
+        }
+    })?;
+    if hide_cursor {
+        terminal.hide_cursor()?;
+    }
+    Ok(())
+}
+
+// Run `command`, e.g. an editor, with the terminal restored to its normal mode.
+//
+// The TUI is drawn on the normal screen, so that quitting leaves the document on the terminal.
+// The editor gets the alternate screen, which is left after it, with the TUI as it was.
+fn suspend(mut command: Command, mouse_capture: bool) -> Result<(), Error> {
+    if mouse_capture {
+        crossterm::execute!(io::stderr(), DisableMouseCapture)?;
+    }
+    crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()?;
+    let status = command.status();
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(io::stdout(), LeaveAlternateScreen)?;
+    if mouse_capture {
+        crossterm::execute!(io::stderr(), EnableMouseCapture)?;
+    }
+    let status = status?;
+    if !status.success() {
+        return Err(Error::Command(CommandError::Editor(status.to_string())));
+    }
+    Ok(())
 }
@@ -4,7 +4,7 @@ pub mod notification;
 
 use std::time::Duration;
 
-use cosmic_text::{Color, FontSystem, SwashCache};
+use cosmic_text::{Color, FontSystem, SwashCache, fontdb::Source};
 use image::Rgba;
 use ratatui_image::{
     FontSize,
@@ -62,11 +62,14 @@ pub fn setup_graphics(
     force_font_setup: bool,
     no_cap_checks: bool,
     debug_override_protocol_type: Option<ProtocolType>,
+    quiet: bool,
 ) -> Result<SetupResult, Error> {
     let (mut picker, background_color) = if no_cap_checks {
         (Picker::halfblocks(), None)
     } else {
-        print!("Detecting supported graphics protocols...");
+        if !quiet {
+            print!("Detecting supported graphics protocols...");
+        }
         let picker = Picker::from_query_stdio_with_options(QueryStdioOptions {
             timeout: config
                 .stdio_query_timeout_ms
@@ -76,7 +79,9 @@ pub fn setup_graphics(
             terminal_background_color_osc: true,
             ..Default::default()
         })?;
-        println!(" {:?}.", picker.protocol_type());
+        if !quiet {
+            println!(" {:?}.", picker.protocol_type());
+        }
         let mut bg = None;
         if picker.protocol_type() == ProtocolType::Sixel {
             for cap in picker.capabilities() {
@@ -99,12 +104,8 @@ pub fn setup_graphics(
         return Ok(SetupResult::AsciiArt(picker));
     }
 
-    let mut font_system = FontSystem::new_with_fonts([
-        cosmic_text::fontdb::Source::Binary(std::sync::Arc::new(JETBRAINS_MONO)),
-        cosmic_text::fontdb::Source::Binary(std::sync::Arc::new(CASCADIA_CODE)),
-    ]);
+    let mut font_system = font_system();
     let db = font_system.db_mut();
-    db.load_system_fonts();
 
     let all_font_families: Vec<String> = db
         .faces()
@@ -167,13 +168,68 @@ pub fn setup_graphics(
             SwashCache::new(),
             font_name,
             font_size,
-            config.theme.as_ref().and_then(|theme| {
-                theme.header_color.map(|ratatui_color| match ratatui_color {
-                    ratatui::style::Color::Rgb(r, g, b) => Color::rgba(r, g, b, 255),
-                    _ => Color::rgba(255, 255, 255, 255),
-                })
-            }),
+            header_font_color(config.theme.as_ref().and_then(|theme| theme.header_color)),
             background_color,
         )),
     ))
 }
+
+// A font renderer without a terminal, for exports: the configured font if it exists, or else the
+// bundled one.
+pub fn export_font_renderer(
+    font_family: Option<&str>,
+    header_color: Option<ratatui::style::Color>,
+    font_size: FontSize,
+) -> FontRenderer {
+    let font_system = font_system();
+    let families: Vec<&str> = font_system
+        .db()
+        .faces()
+        .map(|faceinfo| faceinfo.families[0].0.as_str())
+        .collect();
+    let font_name = font_family
+        .filter(|font_family| families.contains(font_family))
+        .map(ToOwned::to_owned)
+        .or_else(|| bundled_font_family(&font_system))
+        .unwrap_or_else(|| "monospace".to_owned());
+    FontRenderer::new(
+        font_system,
+        SwashCache::new(),
+        font_name,
+        font_size,
+        header_font_color(header_color),
+        None,
+    )
+}
+
+// The family of `JETBRAINS_MONO`, as named in the font itself.
+fn bundled_font_family(font_system: &FontSystem) -> Option<String> {
+    font_system
+        .db()
+        .faces()
+        .find(|faceinfo| match &faceinfo.source {
+            Source::Binary(data) => {
+                let data: &[u8] = (**data).as_ref();
+                data.as_ptr() == JETBRAINS_MONO.as_ptr()
+            }
+            _ => false,
+        })
+        .map(|faceinfo| faceinfo.families[0].0.clone())
+}
+
+// The bundled fonts and the system fonts.
+fn font_system() -> FontSystem {
+    let mut font_system = FontSystem::new_with_fonts([
+        Source::Binary(std::sync::Arc::new(JETBRAINS_MONO)),
+        Source::Binary(std::sync::Arc::new(CASCADIA_CODE)),
+    ]);
+    font_system.db_mut().load_system_fonts();
+    font_system
+}
+
+fn header_font_color(header_color: Option<ratatui::style::Color>) -> Option<Color> {
+    header_color.map(|ratatui_color| match ratatui_color {
+        ratatui::style::Color::Rgb(r, g, b) => Color::rgba(r, g, b, 255),
+        _ => Color::rgba(255, 255, 255, 255),
+    })
+}
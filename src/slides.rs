@@ -0,0 +1,96 @@
+use mdfrier::{MdFrier, Slide};
+
+use crate::error::Error;
+
+// Presentation mode (`--slides`), the document is shown one slide at a time.
+pub struct Slides {
+    parser: MdFrier,
+    slides: Vec<Slide>,
+    current: usize,
+    pub show_notes: bool,
+}
+
+impl Slides {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Slides {
+            parser: MdFrier::new()?,
+            slides: Vec::new(),
+            current: 0,
+            show_notes: false,
+        })
+    }
+
+    // Split the (possibly reloaded) document text into slides, and return the current slide text.
+    pub fn split(&mut self, text: &str) -> Result<String, Error> {
+        self.slides = self.parser.slides(text)?;
+        self.current = self.current.min(self.slides.len().saturating_sub(1));
+        Ok(self.text())
+    }
+
+    // Go `delta` slides forward or back, returns false if already at the first or last slide.
+    pub fn go_by(&mut self, delta: isize) -> bool {
+        let next = self
+            .current
+            .saturating_add_signed(delta)
+            .min(self.slides.len().saturating_sub(1));
+        if next == self.current {
+            return false;
+        }
+        self.current = next;
+        true
+    }
+
+    pub fn text(&self) -> String {
+        self.slides
+            .get(self.current)
+            .map(|slide| slide.text.clone())
+            .unwrap_or_default()
+    }
+
+    // The 0-based line of the document where the current slide starts.
+    pub fn line(&self) -> usize {
+        self.slides
+            .get(self.current)
+            .map(|slide| slide.line)
+            .unwrap_or_default()
+    }
+
+    pub fn notes(&self) -> &[String] {
+        self.slides
+            .get(self.current)
+            .map(|slide| slide.notes.as_slice())
+            .unwrap_or_default()
+    }
+
+    // The 1-based slide number and the slide count.
+    pub fn counter(&self) -> (usize, usize) {
+        (self.current + 1, self.slides.len())
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigate_and_reload() {
+        let mut slides = Slides::new().unwrap();
+        let text = slides
+            .split("# One\n\n---\n\n# Two\n<!-- note -->\n")
+            .unwrap();
+        assert_eq!(text, "# One");
+        assert_eq!(slides.counter(), (1, 2));
+
+        assert!(!slides.go_by(-1));
+        assert!(slides.go_by(1));
+        assert!(!slides.go_by(1));
+        assert_eq!(slides.text(), "# Two");
+        assert_eq!(slides.notes(), ["note"]);
+
+        // Reloading with fewer slides keeps the position in bounds.
+        let text = slides.split("# Only\n").unwrap();
+        assert_eq!(text, "# Only");
+        assert_eq!(slides.counter(), (1, 1));
+    }
+}
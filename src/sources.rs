@@ -8,13 +8,20 @@ use std::{
 };
 
 use ghrepo::GHRepo;
-use reqwest::header::CONTENT_TYPE;
+use reqwest::header::HeaderMap;
 use url::Url;
 
+pub mod forge;
+pub mod git;
+pub mod github;
+pub mod html;
+
 use crate::{
-    OK_END, VERSION,
+    OK_END,
+    config::Forge,
     document::Document,
     error::{Error, NavigationError},
+    http, http_cache,
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -29,6 +36,22 @@ pub enum DocumentSource {
     Github {
         repo: GHRepo,
         branch: String,
+        // Relative to the repository root.
+        path: String,
+    },
+    // A repository of GitLab, Gitea or sourcehut, like `Github`.
+    Forge {
+        forge: Forge,
+        repo: String,
+        branch: String,
+        path: String,
+    },
+    // A file at a revision of a local git repository.
+    Git {
+        repo: PathBuf,
+        rev: String,
+        // Relative to the repository root.
+        path: String,
     },
     HyperText {
         url: Url,
@@ -43,6 +66,27 @@ pub enum DocumentSource {
 }
 
 impl DocumentSource {
+    // A short name for display, e.g. in the status line.
+    pub fn name(&self) -> String {
+        match self {
+            DocumentSource::File { path, .. }
+            | DocumentSource::Image { path }
+            | DocumentSource::Pdf { path } => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            DocumentSource::Stdin { .. } => String::from("stdin"),
+            DocumentSource::Github { repo, path, .. } if path == "README.md" => repo.to_string(),
+            DocumentSource::Github { repo, path, .. } => format!("{repo}:{path}"),
+            DocumentSource::Forge {
+                forge, repo, path, ..
+            } => forge.name(repo, path),
+            DocumentSource::Git { rev, path, .. } => format!("git:{rev}:{path}"),
+            DocumentSource::HyperText { url } => url.to_string(),
+            DocumentSource::BuiltIn(builtin) => builtin.to_string(),
+        }
+    }
+
     pub fn return_text(self, returned_text: String) -> Option<Self> {
         match self {
             DocumentSource::Stdin { .. } => Some(DocumentSource::Stdin {
@@ -154,106 +198,110 @@ impl std::ops::Deref for SharedDocumentSource {
     }
 }
 
+// Open a document source, printing progress to stdout unless `quiet`.
 pub fn open_source(
     source: &str,
     url_transform_command: Option<String>,
+    quiet: bool,
 ) -> Result<(String, DocumentSource), Error> {
-    use ghrepo::GHRepo;
-    use url::Url;
-
-    use core::str::FromStr as _;
-    if let Some(handle) = source.strip_prefix("github:")
-        && let Ok(repo) = GHRepo::from_str(handle)
-    {
-        // We only want to try github if the user explicitly prefixed with "github:...".
-        // Otherwise a path like "dir/file.md" would be a valid GHRepo and cause a useless request.
-        let owner = repo.owner();
-        let name = repo.name();
-        let client = reqwest::blocking::Client::builder()
-            .user_agent(format!(
-                "mdfried/{}",
-                VERSION.get().unwrap_or(&"unknown".to_owned())
-            ))
-            .build()?;
-        for branch in ["master", "main"] {
-            let url = format!(
-                "https://raw.githubusercontent.com/{owner}/{name}/refs/heads/{branch}/README.md"
-            );
+    let progress = |message: fmt::Arguments<'_>| {
+        if !quiet {
+            print!("{message}");
+        }
+    };
+    if let Some(github::Spec { repo, branch, path }) = github::Spec::parse(source) {
+        let client = http::blocking_client()?;
+        let branches = match branch {
+            Some(branch) => vec![branch],
+            None => match github::default_branch(&client, &repo) {
+                Ok(branch) => vec![branch],
+                Err(err) => {
+                    log::warn!("{err}");
+                    vec!["master".to_owned(), "main".to_owned()]
+                }
+            },
+        };
+        for branch in branches {
+            let url = github::usercontent_url(&repo, &branch, &path)?;
             log::info!("trying github URL: {url}");
-            print!("Fetching URL {url}...");
-            let response = client.get(&url).send()?;
-            if response.status().is_success() {
-                println!("{OK_END}");
+            progress(format_args!("Fetching URL {url}..."));
+            let response =
+                http_cache::get_blocking(&client, url.as_str(), HeaderMap::new(), false)?;
+            if response.status.is_success() {
+                progress(format_args!("{OK_END}\n"));
+                return Ok((
+                    response.text(),
+                    DocumentSource::Github { repo, branch, path },
+                ));
+            } else {
+                progress(format_args!("error.\n"));
+            }
+        }
+        return Err(Error::Io(io::Error::other(format!(
+            "failed to request {path} of github:{repo}"
+        ))));
+    } else if let Some(forge::Spec {
+        forge,
+        repo,
+        branch,
+        path,
+    }) = forge::Spec::parse(source)
+    {
+        let client = http::blocking_client()?;
+        let branches = match branch {
+            Some(branch) => vec![branch],
+            None => match forge.default_branch(&client, &repo) {
+                Ok(branch) => vec![branch],
+                Err(err) => {
+                    log::warn!("{err}");
+                    vec!["main".to_owned(), "master".to_owned()]
+                }
+            },
+        };
+        for branch in branches {
+            let url = forge.usercontent_url(&repo, &branch, &path)?;
+            log::info!("trying forge URL: {url}");
+            progress(format_args!("Fetching URL {url}..."));
+            let response =
+                http_cache::get_blocking(&client, url.as_str(), HeaderMap::new(), false)?;
+            if response.status.is_success() {
+                progress(format_args!("{OK_END}\n"));
                 return Ok((
-                    response.text()?,
-                    DocumentSource::Github {
+                    response.text(),
+                    DocumentSource::Forge {
+                        forge,
                         repo,
-                        branch: branch.to_owned(),
+                        branch,
+                        path,
                     },
                 ));
             } else {
-                println!("error.");
+                progress(format_args!("error.\n"));
             }
         }
         return Err(Error::Io(io::Error::other(format!(
-            "failed to request https://raw.githubusercontent.com/{owner}/{name}/refs/heads/[master|main]/README.md"
+            "failed to request {path} of {}:{repo}",
+            forge.prefix
         ))));
+    } else if let Some(git::Spec { rev, path }) = git::Spec::parse(source) {
+        let (repo, path) = git::locate(&path)?;
+        return Ok((
+            git::read_to_string(&repo, &rev, &path)?,
+            DocumentSource::Git { repo, rev, path },
+        ));
     } else if let Ok(url) = Url::parse(source) {
         log::info!("requesting URL: {url}");
-        print!("Fetching URL {url}...");
-        let client = reqwest::blocking::Client::builder()
-            .user_agent(format!(
-                "mdfried/{}",
-                VERSION.get().unwrap_or(&"unknown".to_owned())
-            ))
-            .build()?;
-        let response = client.get(url.as_ref()).send()?;
-        if response.status().is_success() {
-            println!("{OK_END}");
-            log::debug!(
-                "have url_transform_command? {}, content_type: {:?}",
-                url_transform_command.is_some(),
-                response.headers().get(CONTENT_TYPE)
-            );
-            if let Some(url_transform_command) = url_transform_command
-                && let Some(content_type) = response.headers().get(CONTENT_TYPE)
-                && content_type
-                    .to_str()
-                    .map_err(|err| {
-                        Error::Io(io::Error::other(format!("content-type error: {err}")))
-                    })?
-                    .starts_with("text/html")
-            {
-                let mut child = Command::new("sh")
-                    .arg("-c")
-                    .arg(url_transform_command)
-                    .env("URL", url.as_ref())
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .spawn()?;
-
-                let Some(stdin) = child.stdin.as_mut() else {
-                    return Err(Error::Io(io::Error::other(
-                        "url_transform_command pipe error",
-                    )));
-                };
-                stdin.write_all(response.text()?.as_bytes())?;
-
-                let output = child.wait_with_output()?;
-
-                return Ok((
-                    String::from_utf8(output.stdout)
-                        .map_err(|_err| Error::Io(io::Error::other("response not utf-8")))?,
-                    DocumentSource::HyperText { url },
-                ));
+        progress(format_args!("Fetching URL {url}..."));
+        return match fetch_url(&url, url_transform_command, false) {
+            Ok(text) => {
+                progress(format_args!("{OK_END}\n"));
+                Ok((text, DocumentSource::HyperText { url }))
             }
-            return Ok((response.text()?, DocumentSource::HyperText { url }));
-        } else {
-            println!("error.");
-            return Err(Error::Io(io::Error::other(format!(
-                "failed to request {url}"
-            ))));
-        }
+            Err(err) => {
+                progress(format_args!("error.\n"));
+                Err(err)
+            }
+        };
     }
 
     let path = PathBuf::from(source);
@@ -280,32 +328,94 @@ pub fn open_source(
     ))
 }
 
-pub fn github_usercontent_url(repo: &GHRepo, branch: &str, path: &str) -> Result<String, Error> {
-    let path_url = Url::parse(&format!("https://dummy.com/{}", path))?; // :rolling_eyes:
+// Fetch a markdown document, transforming HTML with `url_transform_command` if configured, or
+// converting it natively otherwise.
+//
+// With `revalidate`, a cached copy is only used if the server confirms it is unchanged.
+pub fn fetch_url(
+    url: &Url,
+    url_transform_command: Option<String>,
+    revalidate: bool,
+) -> Result<String, Error> {
+    let response = http_cache::get_blocking(
+        &http::blocking_client()?,
+        url.as_ref(),
+        HeaderMap::new(),
+        revalidate,
+    )?;
+    if !response.status.is_success() {
+        return Err(Error::Io(io::Error::other(format!(
+            "failed to request {url}"
+        ))));
+    }
+    log::debug!(
+        "have url_transform_command? {}, content_type: {:?}",
+        url_transform_command.is_some(),
+        response.content_type
+    );
+    let is_html = response
+        .content_type
+        .as_ref()
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    if is_html && let Some(url_transform_command) = url_transform_command {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(url_transform_command)
+            .env("URL", url.as_ref())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let Some(stdin) = child.stdin.as_mut() else {
+            return Err(Error::Io(io::Error::other(
+                "url_transform_command pipe error",
+            )));
+        };
+        stdin.write_all(&response.body)?;
 
-    let mut url = Url::parse("https://raw.githubusercontent.com")?;
-    let segs = path_url.path_segments().ok_or(Error::UrlParse(None))?;
-    url.path_segments_mut()
-        .or(Err(Error::UrlParse(None)))?
-        .extend(&[repo.owner(), repo.name(), branch])
-        .extend(segs);
-    url.set_query(path_url.query());
-    url.set_fragment(path_url.fragment());
+        let output = child.wait_with_output()?;
 
-    Ok(url.to_string())
+        return String::from_utf8(output.stdout)
+            .map_err(|_err| Error::Io(io::Error::other("response not utf-8")));
+    }
+    let text = response.text();
+    if is_html {
+        if let Some(markdown) = html::to_markdown(&text, url) {
+            return Ok(markdown);
+        }
+        log::warn!("could not parse HTML of {url}");
+    }
+    Ok(text)
 }
 
-pub fn extend_url(mut url: Url, path: &str) -> Result<String, Error> {
-    let path_url = Url::parse(&format!("https://dummy.com/{}", path))?; // :rolling_eyes:
-
-    let segs = path_url.path_segments().ok_or(Error::UrlParse(None))?;
-    url.path_segments_mut()
-        .or(Err(Error::UrlParse(None)))?
-        .extend(segs);
-    url.set_query(path_url.query());
-    url.set_fragment(path_url.fragment());
+// Fetch the text of a remote source again, or `None` for local sources, e.g. to poll for changes.
+pub fn refetch(
+    source: &DocumentSource,
+    url_transform_command: Option<String>,
+) -> Result<Option<String>, Error> {
+    match source {
+        DocumentSource::HyperText { url } => fetch_url(url, url_transform_command, true).map(Some),
+        DocumentSource::Github { repo, branch, path } => {
+            let url = github::usercontent_url(repo, branch, path)?;
+            fetch_url(&url, None, true).map(Some)
+        }
+        DocumentSource::Forge {
+            forge,
+            repo,
+            branch,
+            path,
+        } => {
+            let url = forge.usercontent_url(repo, branch, path)?;
+            fetch_url(&url, None, true).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
 
-    Ok(url.to_string())
+// Resolve a relative link of the document at `url`, like a browser: relative to the document's
+// directory, or to the host if it starts with `/`.
+pub fn extend_url(url: Url, path: &str) -> Result<String, Error> {
+    Ok(url.join(path)?.to_string())
 }
 
 pub struct DocumentHistoryEntry {
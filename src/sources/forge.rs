@@ -0,0 +1,340 @@
+use std::sync::OnceLock;
+
+use reqwest::header::HeaderMap;
+use url::Url;
+
+use crate::{
+    config::{Forge, ForgeKind},
+    error::Error,
+    http_cache,
+};
+
+// The configured `[[forges]]`, followed by the built-in ones, which they can override.
+static FORGES: OnceLock<Vec<Forge>> = OnceLock::new();
+
+pub fn init(config: Vec<Forge>) {
+    #[expect(unused_must_use)]
+    FORGES.set(with_builtins(config));
+}
+
+fn forges() -> &'static [Forge] {
+    FORGES.get_or_init(|| with_builtins(Vec::new()))
+}
+
+fn with_builtins(mut forges: Vec<Forge>) -> Vec<Forge> {
+    let builtin = |prefix: &str, kind, url: Option<&str>| Forge {
+        prefix: prefix.to_owned(),
+        kind,
+        url: url.map(str::to_owned),
+        raw_url: None,
+    };
+    forges.extend([
+        builtin("gitlab", ForgeKind::Gitlab, Some("https://gitlab.com")),
+        builtin("codeberg", ForgeKind::Gitea, Some("https://codeberg.org")),
+        builtin("gitea", ForgeKind::Gitea, None),
+        builtin("sourcehut", ForgeKind::Sourcehut, Some("https://git.sr.ht")),
+    ]);
+    forges
+}
+
+// A document in a repository of a forge, like `github::Spec`.
+//
+// Accepts `<prefix>:<repo>[@<ref>][:<path>]`, e.g. `gitlab:group/subgroup/project@v2:docs/`,
+// where `<repo>` starts with the host if the forge has no `url`.
+#[derive(Debug, PartialEq)]
+pub struct Spec {
+    pub forge: Forge,
+    pub repo: String,
+    pub branch: Option<String>,
+    pub path: String,
+}
+
+impl Spec {
+    pub fn parse(source: &str) -> Option<Spec> {
+        Self::parse_with(forges(), source)
+    }
+
+    fn parse_with(forges: &[Forge], source: &str) -> Option<Spec> {
+        let (prefix, handle) = source.split_once(':')?;
+        let forge = forges.iter().find(|forge| forge.prefix == prefix)?;
+
+        let (handle, path) = match handle.split_once(':') {
+            Some((handle, path)) => (handle, path),
+            None => (handle, ""),
+        };
+        let (repo, branch) = match handle.split_once('@') {
+            Some((repo, branch)) => (repo, Some(branch.to_owned())),
+            None => (handle, None),
+        };
+
+        let repo = repo.trim_matches('/');
+        let segments = repo.split('/').collect::<Vec<_>>();
+        let min_segments = if forge.url.is_some() { 2 } else { 3 };
+        if segments.len() < min_segments || segments.iter().any(|segment| segment.is_empty()) {
+            return None;
+        }
+        if forge.kind != ForgeKind::Gitlab && segments.len() > min_segments {
+            // Only GitLab has nested groups.
+            return None;
+        }
+
+        let path = path.trim_start_matches('/');
+        let path = if path.is_empty() || path.ends_with('/') {
+            format!("{path}README.md")
+        } else {
+            path.to_owned()
+        };
+        Some(Spec {
+            forge: forge.clone(),
+            repo: repo.to_owned(),
+            branch,
+            path,
+        })
+    }
+}
+
+impl Forge {
+    // The base URL and the repository path, without the host if `repo` starts with it.
+    fn base(&self, repo: &str) -> (String, String) {
+        match &self.url {
+            Some(url) => (url.trim_end_matches('/').to_owned(), repo.to_owned()),
+            None => match repo.split_once('/') {
+                Some((host, repo)) => (format!("https://{host}"), repo.to_owned()),
+                None => (format!("https://{repo}"), String::new()),
+            },
+        }
+    }
+
+    fn raw_url_template(&self) -> &str {
+        match (&self.raw_url, self.kind) {
+            (Some(raw_url), _) => raw_url,
+            (None, ForgeKind::Gitlab) => "{url}/{repo}/-/raw/{ref}/{path}",
+            (None, ForgeKind::Gitea) => "{url}/{repo}/raw/branch/{ref}/{path}",
+            (None, ForgeKind::Sourcehut) => "{url}/{repo}/blob/{ref}/{path}",
+        }
+    }
+
+    // The `<prefix>:` source of a document, the inverse of `Spec::parse`.
+    pub fn source(&self, repo: &str, branch: &str, path: &str) -> String {
+        format!("{}:{repo}@{branch}:{path}", self.prefix)
+    }
+
+    // A short name for display, e.g. `gitlab:group/project`.
+    pub fn name(&self, repo: &str, path: &str) -> String {
+        if path == "README.md" {
+            format!("{}:{repo}", self.prefix)
+        } else {
+            format!("{}:{repo}:{path}", self.prefix)
+        }
+    }
+
+    pub fn default_branch(
+        &self,
+        client: &reqwest::blocking::Client,
+        repo: &str,
+    ) -> Result<String, Error> {
+        let (url, repo) = self.base(repo);
+        let clone_url = match self.kind {
+            ForgeKind::Sourcehut => format!("{url}/{repo}"),
+            ForgeKind::Gitlab | ForgeKind::Gitea => format!("{url}/{repo}.git"),
+        };
+        default_branch(client, &clone_url)
+    }
+
+    // The raw content URL of the repository root at `branch`, ending in a slash.
+    fn root_url(&self, repo: &str, branch: &str) -> Result<Url, Error> {
+        let (url, repo) = self.base(repo);
+        let root = self
+            .raw_url_template()
+            .replace("{url}", &url)
+            .replace("{repo}", &repo)
+            .replace("{ref}", branch)
+            .replace("{path}", "");
+        if !root.ends_with('/') {
+            return Err(Error::Generic(format!(
+                "raw_url of {} must end with /{{path}}",
+                self.prefix
+            )));
+        }
+        Ok(Url::parse(&root)?)
+    }
+
+    // The raw content URL of `path`, relative to the repository root.
+    pub fn usercontent_url(&self, repo: &str, branch: &str, path: &str) -> Result<Url, Error> {
+        Ok(self
+            .root_url(repo, branch)?
+            .join(path.trim_start_matches('/'))?)
+    }
+
+    pub fn link_url(
+        &self,
+        repo: &str,
+        branch: &str,
+        path: &str,
+        link_url: &str,
+    ) -> Result<Url, Error> {
+        resolve_link(&self.root_url(repo, branch)?, path, link_url)
+    }
+
+    pub fn link_path(
+        &self,
+        repo: &str,
+        branch: &str,
+        path: &str,
+        link_url: &str,
+    ) -> Option<String> {
+        resolve_link_path(&self.root_url(repo, branch).ok()?, path, link_url)
+    }
+}
+
+// The default branch of the repository at `clone_url`, as advertised by git's smart HTTP protocol,
+// which every forge serves, and which unlike their REST APIs has no rate limit for anonymous
+// requests.
+pub fn default_branch(
+    client: &reqwest::blocking::Client,
+    clone_url: &str,
+) -> Result<String, Error> {
+    let url = format!("{clone_url}/info/refs?service=git-upload-pack");
+    log::info!("discovering default branch: {url}");
+    let response = http_cache::get_blocking(client, &url, HeaderMap::new(), false)?;
+    if !response.status.is_success() {
+        return Err(Error::Generic(format!(
+            "failed to request {url}: {}",
+            response.status
+        )));
+    }
+    head_symref(&response.text())
+        .ok_or_else(|| Error::Generic(format!("no default branch advertised by {url}")))
+}
+
+// The branch of `symref=HEAD:refs/heads/<branch>` in the capabilities of a ref advertisement.
+fn head_symref(advertisement: &str) -> Option<String> {
+    let (_, rest) = advertisement.split_once("symref=HEAD:refs/heads/")?;
+    let branch: String = rest
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '\0')
+        .collect();
+    (!branch.is_empty()).then_some(branch)
+}
+
+// The URL of a link in the document at `path`, below the raw content URL `root` of a repository.
+// Relative links resolve from the document's directory, absolute ones from the repository root,
+// like forges render them.
+pub fn resolve_link(root: &Url, path: &str, link_url: &str) -> Result<Url, Error> {
+    if let Some(link_url) = link_url.strip_prefix('/') {
+        Ok(root.join(link_url)?)
+    } else {
+        Ok(root.join(path.trim_start_matches('/'))?.join(link_url)?)
+    }
+}
+
+// The path of a link in the document at `path`, relative to the repository root, or `None` if
+// it leaves the repository.
+pub fn resolve_link_path(root: &Url, path: &str, link_url: &str) -> Option<String> {
+    let url = resolve_link(root, path, link_url).ok()?;
+    url.path().strip_prefix(root.path()).map(str::to_owned)
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use crate::config::{Forge, ForgeKind};
+
+    use super::{Spec, head_symref, with_builtins};
+
+    fn forges() -> Vec<Forge> {
+        with_builtins(vec![Forge {
+            prefix: "work".to_owned(),
+            kind: ForgeKind::Gitlab,
+            url: Some("https://gitlab.example.com/".to_owned()),
+            raw_url: None,
+        }])
+    }
+
+    fn raw_url(source: &str, branch: &str) -> String {
+        let spec = Spec::parse_with(&forges(), source).unwrap();
+        spec.forge
+            .usercontent_url(&spec.repo, branch, &spec.path)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn parse() {
+        let forges = forges();
+        let spec = Spec::parse_with(&forges, "work:group/sub/project@v2:docs/").unwrap();
+        assert_eq!(spec.forge.prefix, "work");
+        assert_eq!(spec.repo, "group/sub/project");
+        assert_eq!(spec.branch.as_deref(), Some("v2"));
+        assert_eq!(spec.path, "docs/README.md");
+        assert_eq!(
+            Spec::parse_with(&forges, &spec.forge.source(&spec.repo, "v2", &spec.path)),
+            Some(spec)
+        );
+
+        assert!(Spec::parse_with(&forges, "gitea:codeberg.org/owner/repo").is_some());
+        assert!(Spec::parse_with(&forges, "gitea:owner/repo").is_none());
+        assert!(Spec::parse_with(&forges, "codeberg:owner/repo/extra").is_none());
+        assert!(Spec::parse_with(&forges, "gitlab:project").is_none());
+        assert!(Spec::parse_with(&forges, "https://gitlab.com/group/project").is_none());
+        assert!(Spec::parse_with(&forges, "C:\\docs\\README.md").is_none());
+    }
+
+    #[test]
+    fn raw_urls() {
+        assert_eq!(
+            raw_url("work:group/project:docs/guide.md", "main"),
+            "https://gitlab.example.com/group/project/-/raw/main/docs/guide.md"
+        );
+        assert_eq!(
+            raw_url("gitea:git.example.com/owner/repo", "main"),
+            "https://git.example.com/owner/repo/raw/branch/main/README.md"
+        );
+        assert_eq!(
+            raw_url("codeberg:owner/repo", "main"),
+            "https://codeberg.org/owner/repo/raw/branch/main/README.md"
+        );
+        assert_eq!(
+            raw_url("sourcehut:~user/repo:doc/index.md", "master"),
+            "https://git.sr.ht/~user/repo/blob/master/doc/index.md"
+        );
+    }
+
+    #[test]
+    fn links() {
+        let spec = Spec::parse_with(&forges(), "gitlab:group/project:docs/guide.md").unwrap();
+        let forge = &spec.forge;
+        assert_eq!(
+            forge
+                .link_url(&spec.repo, "main", &spec.path, "img/a.png")
+                .unwrap()
+                .as_str(),
+            "https://gitlab.com/group/project/-/raw/main/docs/img/a.png"
+        );
+        assert_eq!(
+            forge
+                .link_url(&spec.repo, "main", &spec.path, "/logo.svg")
+                .unwrap()
+                .as_str(),
+            "https://gitlab.com/group/project/-/raw/main/logo.svg"
+        );
+        assert_eq!(
+            forge
+                .link_path(&spec.repo, "main", &spec.path, "../README.md#top")
+                .as_deref(),
+            Some("README.md")
+        );
+        assert_eq!(
+            forge.link_path(&spec.repo, "main", &spec.path, "../../../other/README.md"),
+            None
+        );
+    }
+
+    #[test]
+    fn default_branch() {
+        let advertisement = "001e# service=git-upload-pack\n0000015547b4a1ff HEAD\0multi_ack \
+            symref=HEAD:refs/heads/trunk object-format=sha1 agent=git/github-1\n";
+        assert_eq!(head_symref(advertisement), Some("trunk".to_owned()));
+        assert_eq!(head_symref("0000"), None);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    env,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use crate::error::Error;
+
+// A document in the history of a local repository, `git:<rev>:<path>`, e.g. `git:v1.0:README.md`.
+//
+// The path is relative to the current directory, like for files.
+#[derive(Debug, PartialEq)]
+pub struct Spec {
+    pub rev: String,
+    pub path: PathBuf,
+}
+
+impl Spec {
+    pub fn parse(source: &str) -> Option<Spec> {
+        let (rev, path) = source.strip_prefix("git:")?.split_once(':')?;
+        if rev.is_empty() || path.is_empty() {
+            return None;
+        }
+        Some(Spec {
+            rev: rev.to_owned(),
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+// The repository containing `path`, which need not exist in the worktree, and the path relative to
+// its root.
+pub fn locate(path: &Path) -> Result<(PathBuf, String), Error> {
+    let path = normalize(&env::current_dir()?.join(path));
+    let Some(dir) = path.ancestors().skip(1).find(|dir| dir.is_dir()) else {
+        return Err(Error::Git(format!("no directory of {}", path.display())));
+    };
+    let root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim_end());
+    // The root is canonical, so the directory must be too, to strip it.
+    let relative = dir
+        .canonicalize()?
+        .strip_prefix(root.canonicalize()?)
+        .map_err(|_err| {
+            Error::Git(format!(
+                "{} is outside of {}",
+                path.display(),
+                root.display()
+            ))
+        })?
+        .join(path.strip_prefix(dir).unwrap_or(&path));
+    let relative = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Ok((root, relative))
+}
+
+// The contents of `path` at `rev`.
+pub fn read(repo: &Path, rev: &str, path: &str) -> Result<Vec<u8>, Error> {
+    // git would parse it as an option.
+    if rev.starts_with('-') {
+        return Err(Error::Git(format!("invalid revision: {rev}")));
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["cat-file", "blob", &format!("{rev}:{path}")])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+pub fn read_to_string(repo: &Path, rev: &str, path: &str) -> Result<String, Error> {
+    String::from_utf8(read(repo, rev, path)?)
+        .map_err(|_err| Error::Git(format!("{rev}:{path} is not utf-8")))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(Error::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// The path of a link in the document at `path`, relative to the repository root, or `None` if it
+// is a URL or leaves the repository. Absolute links are relative to the root, like on forges.
+pub fn link_path(path: &str, link_url: &str) -> Option<String> {
+    if url::Url::parse(link_url).is_ok() {
+        return None;
+    }
+    let link_url = link_url.split(['#', '?']).next().unwrap_or_default();
+    let mut segments: Vec<&str> = match link_url.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => {
+            let mut segments: Vec<_> = path.split('/').collect();
+            segments.pop();
+            segments
+        }
+    };
+    for segment in link_url.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+// Resolve `.` and `..` without touching the filesystem, because the path may only exist at `rev`.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::{fs, path::PathBuf, process::Command};
+
+    use super::{Spec, link_path, locate, read_to_string};
+    use crate::testing;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Spec::parse("git:v1.0:docs/guide.md"),
+            Some(Spec {
+                rev: "v1.0".to_owned(),
+                path: PathBuf::from("docs/guide.md"),
+            })
+        );
+        assert_eq!(Spec::parse("git:README.md"), None);
+        assert_eq!(Spec::parse("github:owner/repo"), None);
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            link_path("docs/guide.md", "img/a.png").as_deref(),
+            Some("docs/img/a.png")
+        );
+        assert_eq!(
+            link_path("docs/guide.md", "../README.md#usage").as_deref(),
+            Some("README.md")
+        );
+        assert_eq!(
+            link_path("docs/guide.md", "/assets/logo.svg").as_deref(),
+            Some("assets/logo.svg")
+        );
+        assert_eq!(link_path("README.md", "../other/README.md"), None);
+        assert_eq!(link_path("README.md", "https://example.com/a.md"), None);
+    }
+
+    #[test]
+    fn reads_revisions() {
+        let dir = testing::temp_path("git_test");
+        let _: Result<(), _> = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        fs::write(dir.join("docs/guide.md"), "# v1").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "v1"]);
+        git(&["tag", "v1"]);
+        fs::write(dir.join("docs/guide.md"), "# v2").unwrap();
+        git(&["commit", "-q", "-a", "-m", "v2"]);
+        fs::remove_dir_all(dir.join("docs")).unwrap();
+
+        // The file is gone from the worktree, but still in the history.
+        let (repo, path) = locate(&dir.join("docs/../docs/guide.md")).unwrap();
+        assert_eq!(path, "docs/guide.md");
+        assert_eq!(read_to_string(&repo, "v1", &path).unwrap(), "# v1");
+        assert_eq!(read_to_string(&repo, "HEAD", &path).unwrap(), "# v2");
+        read_to_string(&repo, "nope", &path).unwrap_err();
+        let option = read_to_string(&repo, "--output=/dev/null", &path).unwrap_err();
+        assert_eq!(
+            option.to_string(),
+            "Git error: invalid revision: --output=/dev/null",
+            "a revision must not be passed as an option"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
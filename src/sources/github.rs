@@ -0,0 +1,203 @@
+use std::str::FromStr as _;
+
+use ghrepo::GHRepo;
+use url::Url;
+
+use crate::{error::Error, sources::forge};
+
+// A document in a GitHub repository, as given on the command line or opened from a link.
+//
+// Accepts `github:owner/repo`, `github:owner/repo@branch:docs/guide.md`,
+// `github:owner/repo/tree/v2[/dir]`, `github:owner/repo/blob/main/docs/guide.md`, and the same
+// as `https://github.com/...` URLs. Without a branch, the default branch is discovered.
+#[derive(Debug, PartialEq)]
+pub struct Spec {
+    pub repo: GHRepo,
+    pub branch: Option<String>,
+    pub path: String,
+}
+
+impl Spec {
+    pub fn parse(source: &str) -> Option<Spec> {
+        // We only want to try github if the user explicitly prefixed with "github:...", or pasted
+        // a github.com URL. Otherwise a path like "dir/file.md" would be a valid GHRepo and cause
+        // a useless request.
+        let handle = if let Some(handle) = source.strip_prefix("github:") {
+            handle.to_owned()
+        } else {
+            let url = Url::parse(source).ok()?;
+            if url.host_str() != Some("github.com") && url.host_str() != Some("www.github.com") {
+                return None;
+            }
+            url.path().trim_start_matches('/').to_owned()
+        };
+        let handle = handle.as_str();
+
+        let (handle, path) = match handle.split_once(':') {
+            Some((handle, path)) => (handle, Some(path)),
+            None => (handle, None),
+        };
+        let (handle, branch) = match handle.split_once('@') {
+            Some((handle, branch)) => (handle, Some(branch.to_owned())),
+            None => (handle, None),
+        };
+
+        let mut segments = handle.trim_end_matches('/').split('/');
+        let owner = segments.next()?;
+        let name = segments.next()?;
+        let name = name.strip_suffix(".git").unwrap_or(name);
+        let repo = GHRepo::from_str(&format!("{owner}/{name}")).ok()?;
+
+        let (branch, path) = match segments.next() {
+            None => (branch, path.unwrap_or_default().to_owned()),
+            Some(kind @ ("tree" | "blob")) if branch.is_none() && path.is_none() => {
+                let branch = segments.next()?.to_owned();
+                let mut path = segments.collect::<Vec<_>>().join("/");
+                if kind == "tree" && !path.is_empty() {
+                    path.push('/');
+                }
+                (Some(branch), path)
+            }
+            Some(_) => return None,
+        };
+
+        let path = path.trim_start_matches('/');
+        let path = if path.is_empty() || path.ends_with('/') {
+            format!("{path}README.md")
+        } else {
+            path.to_owned()
+        };
+        Some(Spec { repo, branch, path })
+    }
+}
+
+// The `github:` source of a document, the inverse of `Spec::parse`.
+pub fn source(repo: &GHRepo, branch: &str, path: &str) -> String {
+    format!("github:{repo}@{branch}:{path}")
+}
+
+// The default branch of `repo`, see `forge::default_branch`.
+pub fn default_branch(client: &reqwest::blocking::Client, repo: &GHRepo) -> Result<String, Error> {
+    forge::default_branch(
+        client,
+        &format!("https://github.com/{}/{}.git", repo.owner(), repo.name()),
+    )
+}
+
+// The raw content URL of the repository root at `branch`, ending in a slash.
+fn root_url(repo: &GHRepo, branch: &str) -> Result<Url, Error> {
+    let mut url = Url::parse("https://raw.githubusercontent.com")?;
+    url.path_segments_mut()
+        .or(Err(Error::UrlParse(None)))?
+        .extend(&[repo.owner(), repo.name()])
+        .extend(branch.split('/'))
+        .push("");
+    Ok(url)
+}
+
+// The raw content URL of `path`, relative to the repository root.
+pub fn usercontent_url(repo: &GHRepo, branch: &str, path: &str) -> Result<Url, Error> {
+    Ok(root_url(repo, branch)?.join(path.trim_start_matches('/'))?)
+}
+
+// The raw content URL of a link in the document at `path`, see `forge::resolve_link`.
+pub fn link_url(repo: &GHRepo, branch: &str, path: &str, link_url: &str) -> Result<Url, Error> {
+    forge::resolve_link(&root_url(repo, branch)?, path, link_url)
+}
+
+pub fn link_path(repo: &GHRepo, branch: &str, path: &str, link_url: &str) -> Option<String> {
+    forge::resolve_link_path(&root_url(repo, branch).ok()?, path, link_url)
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use ghrepo::GHRepo;
+
+    use super::{Spec, link_path, link_url};
+
+    fn spec(repo: &str, branch: Option<&str>, path: &str) -> Option<Spec> {
+        Some(Spec {
+            repo: GHRepo::from_str(repo).unwrap(),
+            branch: branch.map(str::to_owned),
+            path: path.to_owned(),
+        })
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Spec::parse("github:owner/repo"),
+            spec("owner/repo", None, "README.md")
+        );
+        assert_eq!(
+            Spec::parse("github:owner/repo@dev:docs/guide.md"),
+            spec("owner/repo", Some("dev"), "docs/guide.md")
+        );
+        assert_eq!(
+            Spec::parse("github:owner/repo:docs/"),
+            spec("owner/repo", None, "docs/README.md")
+        );
+        assert_eq!(
+            Spec::parse("github:owner/repo/tree/v2"),
+            spec("owner/repo", Some("v2"), "README.md")
+        );
+        assert_eq!(
+            Spec::parse("github:owner/repo/tree/v2/docs"),
+            spec("owner/repo", Some("v2"), "docs/README.md")
+        );
+        assert_eq!(
+            Spec::parse("https://github.com/owner/repo/blob/main/docs/guide.md"),
+            spec("owner/repo", Some("main"), "docs/guide.md")
+        );
+        assert_eq!(
+            Spec::parse("https://github.com/owner/repo.git"),
+            spec("owner/repo", None, "README.md")
+        );
+        assert_eq!(Spec::parse("docs/guide.md"), None);
+        assert_eq!(Spec::parse("https://example.com/owner/repo"), None);
+        assert_eq!(Spec::parse("https://github.com/owner/repo/issues"), None);
+        assert_eq!(
+            Spec::parse(&super::source(
+                &GHRepo::from_str("owner/repo").unwrap(),
+                "dev",
+                "docs/guide.md"
+            )),
+            spec("owner/repo", Some("dev"), "docs/guide.md")
+        );
+    }
+
+    #[test]
+    fn links() {
+        let repo = GHRepo::from_str("owner/repo").unwrap();
+        let base = "https://raw.githubusercontent.com/owner/repo/feature/x";
+        assert_eq!(
+            link_url(&repo, "feature/x", "docs/guide.md", "img/a.png")
+                .unwrap()
+                .as_str(),
+            format!("{base}/docs/img/a.png")
+        );
+        assert_eq!(
+            link_url(&repo, "feature/x", "docs/guide.md", "../README.md#top")
+                .unwrap()
+                .as_str(),
+            format!("{base}/README.md#top")
+        );
+        assert_eq!(
+            link_url(&repo, "feature/x", "docs/guide.md", "/assets/logo.svg")
+                .unwrap()
+                .as_str(),
+            format!("{base}/assets/logo.svg")
+        );
+        assert_eq!(
+            link_path(&repo, "main", "docs/guide.md", "other.md#usage").as_deref(),
+            Some("docs/other.md")
+        );
+        assert_eq!(
+            link_path(&repo, "main", "README.md", "../../other/README.md"),
+            None
+        );
+    }
+}
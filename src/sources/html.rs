@@ -0,0 +1,935 @@
+use std::fmt::Write as _;
+
+use arborium::tree_sitter::{Node, Parser};
+use url::Url;
+
+// Deeper elements are dropped, instead of overflowing the stack on a pathological page.
+const MAX_DEPTH: usize = 200;
+
+// Elements that flow within a paragraph. Everything else starts a block.
+const INLINE: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "br", "cite", "code", "data", "del", "dfn", "em", "font", "i",
+    "img", "ins", "kbd", "label", "mark", "q", "s", "samp", "small", "span", "strike", "strong",
+    "sub", "sup", "time", "tt", "u", "var",
+];
+
+// Elements without content or end tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+// Elements that are never part of the content.
+const SKIPPED: &[&str] = &[
+    "aside", "button", "canvas", "dialog", "footer", "form", "head", "iframe", "input", "link",
+    "meta", "nav", "noscript", "object", "select", "svg", "template", "textarea", "title",
+];
+
+// Words in the class or id of boilerplate, and of content, which wins.
+const NEGATIVE: &[&str] = &[
+    "ad",
+    "ads",
+    "advert",
+    "banner",
+    "breadcrumb",
+    "breadcrumbs",
+    "comment",
+    "comments",
+    "cookie",
+    "footer",
+    "menu",
+    "nav",
+    "navbar",
+    "newsletter",
+    "popup",
+    "promo",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+    "sponsor",
+    "subscribe",
+    "toc",
+];
+const POSITIVE: &[&str] = &[
+    "article", "body", "content", "entry", "main", "post", "story", "text",
+];
+
+// Convert an HTML page to markdown, for opening web pages without a `url_transform_command`.
+//
+// Like a browser's reader mode, only the main content is kept: the element whose paragraphs have
+// the most text, without navigation, sidebars and the like. Links and images are resolved against
+// the page's `url`, like a browser does, because they are relative to the page's file and not to
+// its directory.
+pub fn to_markdown(html: &str, url: &Url) -> Option<String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&arborium::lang_html::language().into())
+        .ok()?;
+    let tree = parser.parse(html, None)?;
+    let mut root = Element {
+        tag: String::new(),
+        attributes: Vec::new(),
+        children: children(tree.root_node(), html, 0),
+    };
+    let base = root
+        .find("base")
+        .and_then(|base| base.attribute("href"))
+        .and_then(|href| url.join(href).ok())
+        .unwrap_or_else(|| url.clone());
+    root.resolve_links(&base);
+
+    let content = main_content(&root);
+    let mut blocks = Vec::new();
+    if !content.contains("h1")
+        && let Some(title) = root.find("title").or_else(|| root.find("h1"))
+    {
+        let title = collapse(&title.text());
+        if !title.is_empty() {
+            blocks.push(format!("# {title}"));
+        }
+    }
+    block_children(&content.children, &mut blocks);
+    let mut markdown = blocks.join("\n\n");
+    markdown.push('\n');
+    Some(markdown)
+}
+
+#[derive(Debug)]
+enum Dom {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug)]
+struct Element {
+    // Lowercase.
+    tag: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Dom>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Dom::Element(element) => Some(element),
+            Dom::Text(_) => None,
+        })
+    }
+
+    // The first descendant with `tag`, depth first.
+    fn find(&self, tag: &str) -> Option<&Element> {
+        self.elements().find_map(|element| {
+            (element.tag == tag)
+                .then_some(element)
+                .or_else(|| element.find(tag))
+        })
+    }
+
+    fn contains(&self, tag: &str) -> bool {
+        self.find(tag).is_some()
+    }
+
+    // Make the links and images of the element and its descendants absolute. Links within the
+    // page stay as they are.
+    fn resolve_links(&mut self, base: &Url) {
+        for (name, value) in &mut self.attributes {
+            if matches!(name.as_str(), "href" | "src" | "data-src")
+                && !value.is_empty()
+                && !value.starts_with('#')
+                && let Ok(url) = base.join(value)
+            {
+                *value = url.to_string();
+            }
+        }
+        for child in &mut self.children {
+            if let Dom::Element(element) = child {
+                element.resolve_links(base);
+            }
+        }
+    }
+
+    // All text, with whitespace as in the source.
+    fn text(&self) -> String {
+        let mut text = String::new();
+        self.push_text(&mut text);
+        text
+    }
+
+    fn push_text(&self, text: &mut String) {
+        for child in &self.children {
+            match child {
+                Dom::Text(t) => text.push_str(t),
+                Dom::Element(element) => element.push_text(text),
+            }
+        }
+    }
+
+    // The length of the text in links, to tell navigation from content.
+    fn link_text_len(&self) -> usize {
+        self.elements()
+            .map(|element| {
+                if element.tag == "a" {
+                    element.text().trim().len()
+                } else {
+                    element.link_text_len()
+                }
+            })
+            .sum()
+    }
+
+    // Whether the class, id or role marks the element as boilerplate.
+    fn is_boilerplate(&self) -> bool {
+        if SKIPPED.contains(&self.tag.as_str())
+            || self.attribute("hidden").is_some()
+            || self.attribute("aria-hidden") == Some("true")
+            || matches!(
+                self.attribute("role"),
+                Some("navigation" | "complementary" | "banner" | "contentinfo")
+            )
+        {
+            return true;
+        }
+        let words = self.class_words();
+        !words.iter().any(|word| POSITIVE.contains(&word.as_str()))
+            && words.iter().any(|word| NEGATIVE.contains(&word.as_str()))
+    }
+
+    // The words of the class and id, e.g. `site-nav` is `site` and `nav`.
+    fn class_words(&self) -> Vec<String> {
+        [self.attribute("class"), self.attribute("id")]
+            .into_iter()
+            .flatten()
+            .flat_map(|value| value.split(|c: char| c.is_whitespace() || c == '-' || c == '_'))
+            .filter(|word| !word.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect()
+    }
+}
+
+// The element tree of the children of `node`, with the whitespace between them, which is not part
+// of the text nodes of the grammar.
+fn children(node: Node<'_>, html: &str, depth: usize) -> Vec<Dom> {
+    let mut children = Vec::new();
+    if depth > MAX_DEPTH {
+        return children;
+    }
+    let mut end = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if let Some(end) = end
+            && let Some(gap) = html.get(end..child.start_byte())
+            && !gap.is_empty()
+        {
+            children.push(Dom::Text(gap.to_owned()));
+        }
+        end = Some(child.end_byte());
+        match child.kind() {
+            "element" => {
+                if let Some(mut element) = element(child, html, depth) {
+                    // The grammar nests what follows a void element like `<br>` in it.
+                    let following = if VOID.contains(&element.tag.as_str()) {
+                        std::mem::take(&mut element.children)
+                    } else {
+                        Vec::new()
+                    };
+                    children.push(Dom::Element(element));
+                    children.extend(following);
+                }
+            }
+            "text" => children.push(Dom::Text(source(child, html).to_owned())),
+            "entity" => children.push(Dom::Text(decode_entity(source(child, html)))),
+            // Scripts, styles, comments, the doctype, and the tags of this element.
+            _ => {}
+        }
+    }
+    // Implicitly closed elements end after the whitespace that follows them.
+    if let Some(end) = end
+        && let Some(gap) = html.get(end..node.end_byte())
+        && !gap.is_empty()
+    {
+        children.push(Dom::Text(gap.to_owned()));
+    }
+    children
+}
+
+fn element(node: Node<'_>, html: &str, depth: usize) -> Option<Element> {
+    let tag_node = node.child(0)?;
+    if !matches!(tag_node.kind(), "start_tag" | "self_closing_tag") {
+        return None;
+    }
+    let mut tag = String::new();
+    let mut attributes = Vec::new();
+    let mut cursor = tag_node.walk();
+    for child in tag_node.children(&mut cursor) {
+        match child.kind() {
+            "tag_name" => tag = source(child, html).to_ascii_lowercase(),
+            "attribute" => {
+                let mut name = String::new();
+                let mut value = String::new();
+                let mut cursor = child.walk();
+                for part in child.children(&mut cursor) {
+                    match part.kind() {
+                        "attribute_name" => name = source(part, html).to_ascii_lowercase(),
+                        "attribute_value" => value = decode_entities(source(part, html)),
+                        "quoted_attribute_value" => {
+                            if let Some(inner) = part
+                                .child(1)
+                                .filter(|inner| inner.kind() == "attribute_value")
+                            {
+                                value = decode_entities(source(inner, html));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                attributes.push((name, value));
+            }
+            _ => {}
+        }
+    }
+    Some(Element {
+        tag,
+        attributes,
+        children: children(node, html, depth + 1),
+    })
+}
+
+fn source<'a>(node: Node<'_>, html: &'a str) -> &'a str {
+    html.get(node.byte_range()).unwrap_or_default()
+}
+
+// An ancestor wins over its best child with at least this share of its score, e.g. an article of
+// several sections over its largest section.
+const ANCESTOR_SCORE_RATIO: f64 = 0.7;
+
+// An element with paragraphs, and the element it is in.
+struct Candidate<'a> {
+    element: &'a Element,
+    parent: Option<&'a Element>,
+    score: f64,
+}
+
+// The element with the most paragraph text, like readability's scoring: each paragraph adds to its
+// parent, and half to its grandparent, and link-heavy or boilerplate-looking elements lose.
+fn main_content(root: &Element) -> &Element {
+    let mut candidates = Vec::new();
+    score_paragraphs(root, &mut Vec::new(), &mut candidates);
+    for candidate in &mut candidates {
+        let text_len = candidate.element.text().trim().len().max(1);
+        let link_density = candidate.element.link_text_len() as f64 / text_len as f64;
+        candidate.score *= 1.0 - link_density.min(1.0);
+    }
+    let find = |element: &Element| {
+        candidates
+            .iter()
+            .find(|candidate| std::ptr::eq(candidate.element, element))
+    };
+    // The first of the best, in the order they were found.
+    let mut best = candidates
+        .iter()
+        .fold(None, |best: Option<&Candidate>, candidate| match best {
+            Some(best) if best.score >= candidate.score => Some(best),
+            _ => Some(candidate),
+        });
+    while let Some(child) = best
+        && let Some(parent) = child.parent.and_then(find)
+        && parent.score >= child.score * ANCESTOR_SCORE_RATIO
+    {
+        best = Some(parent);
+    }
+    best.map(|candidate| candidate.element)
+        .or_else(|| root.find("main"))
+        .or_else(|| root.find("article"))
+        .or_else(|| root.find("body"))
+        .unwrap_or(root)
+}
+
+fn score_paragraphs<'a>(
+    element: &'a Element,
+    ancestors: &mut Vec<&'a Element>,
+    candidates: &mut Vec<Candidate<'a>>,
+) {
+    if element.is_boilerplate() {
+        return;
+    }
+    if matches!(element.tag.as_str(), "p" | "pre" | "td" | "blockquote") {
+        let text = collapse(&element.text());
+        if text.len() >= 25 {
+            let score =
+                1.0 + text.matches(',').count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+            for ((index, ancestor), share) in ancestors.iter().enumerate().rev().zip([1.0, 0.5]) {
+                if let Some(candidate) = candidates
+                    .iter_mut()
+                    .find(|candidate| std::ptr::eq(candidate.element, *ancestor))
+                {
+                    candidate.score += score * share;
+                    continue;
+                }
+                let words = ancestor.class_words();
+                let bonus = if words.iter().any(|word| POSITIVE.contains(&word.as_str())) {
+                    25.0
+                } else {
+                    0.0
+                };
+                candidates.push(Candidate {
+                    element: ancestor,
+                    parent: index
+                        .checked_sub(1)
+                        .and_then(|index| ancestors.get(index))
+                        .copied(),
+                    score: bonus + score * share,
+                });
+            }
+        }
+    }
+    ancestors.push(element);
+    for child in element.elements() {
+        score_paragraphs(child, ancestors, candidates);
+    }
+    ancestors.pop();
+}
+
+// Render the children of an element as markdown blocks, wrapping runs of inline content in
+// paragraphs.
+fn block_children(children: &[Dom], blocks: &mut Vec<String>) {
+    let mut paragraph = String::new();
+    for child in children {
+        match child {
+            Dom::Text(text) => paragraph.push_str(&escape(text)),
+            Dom::Element(element) if INLINE.contains(&element.tag.as_str()) => {
+                inline(element, &mut paragraph);
+            }
+            Dom::Element(element) => {
+                push_paragraph(&mut paragraph, blocks);
+                block(element, blocks);
+            }
+        }
+    }
+    push_paragraph(&mut paragraph, blocks);
+}
+
+fn push_paragraph(paragraph: &mut String, blocks: &mut Vec<String>) {
+    let text = finish_inline(&std::mem::take(paragraph));
+    if text.is_empty() {
+        return;
+    }
+    // Not a header, quote, or list item.
+    if text.starts_with(['#', '>', '-', '+']) {
+        blocks.push(format!("\\{text}"));
+    } else {
+        blocks.push(text);
+    }
+}
+
+fn block(element: &Element, blocks: &mut Vec<String>) {
+    if element.is_boilerplate() {
+        return;
+    }
+    match element.tag.as_str() {
+        tag @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+            let text = inline_text(element);
+            if !text.is_empty() {
+                let tier = usize::from(tag.as_bytes()[1] - b'0');
+                blocks.push(format!("{} {text}", "#".repeat(tier)));
+            }
+        }
+        "ul" | "ol" | "menu" => list(element, blocks),
+        "blockquote" => {
+            let mut quoted = Vec::new();
+            block_children(&element.children, &mut quoted);
+            if !quoted.is_empty() {
+                blocks.push(prefix_lines(&quoted.join("\n\n"), "> ", ">"));
+            }
+        }
+        "pre" => code_block(element, blocks),
+        "hr" => blocks.push("---".to_owned()),
+        "table" => table(element, blocks),
+        "dt" => {
+            let text = inline_text(element);
+            if !text.is_empty() {
+                blocks.push(format!("**{text}**"));
+            }
+        }
+        _ => block_children(&element.children, blocks),
+    }
+}
+
+fn list(element: &Element, blocks: &mut Vec<String>) {
+    let mut number: usize = element
+        .attribute("start")
+        .and_then(|start| start.trim().parse().ok())
+        .unwrap_or(1);
+    let mut items = Vec::new();
+    for item in element.elements().filter(|child| child.tag == "li") {
+        let marker = if element.tag == "ol" {
+            format!("{number}. ")
+        } else {
+            "- ".to_owned()
+        };
+        number += 1;
+        let mut item_blocks = Vec::new();
+        block_children(&item.children, &mut item_blocks);
+        let mut text = String::new();
+        for (i, item_block) in item_blocks.iter().enumerate() {
+            if i > 0 {
+                // Nested lists stay tight.
+                let nested = item_block.starts_with("- ")
+                    || item_block.split_once(". ").is_some_and(|(number, _)| {
+                        !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())
+                    });
+                text.push_str(if nested { "\n" } else { "\n\n" });
+            }
+            text.push_str(item_block);
+        }
+        let indent = " ".repeat(marker.len());
+        let indented = prefix_lines(&text, &indent, "");
+        items.push(format!("{marker}{}", indented.trim_start()));
+    }
+    if !items.is_empty() {
+        blocks.push(items.join("\n"));
+    }
+}
+
+fn code_block(element: &Element, blocks: &mut Vec<String>) {
+    let text = element.text();
+    let code = text
+        .strip_prefix('\n')
+        .unwrap_or(&text)
+        .trim_end_matches(['\n', ' ', '\t']);
+    if code.trim().is_empty() {
+        return;
+    }
+    let language = [Some(element), element.find("code")]
+        .into_iter()
+        .flatten()
+        .filter_map(|element| element.attribute("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    blocks.push(format!("{fence}{language}\n{code}\n{fence}"));
+}
+
+fn table(element: &Element, blocks: &mut Vec<String>) {
+    // Tables for the page layout are containers.
+    if element.contains("table")
+        || ["p", "div", "ul", "ol", "pre", "h1", "h2", "h3"]
+            .iter()
+            .any(|tag| element.contains(tag))
+    {
+        block_children(&element.children, blocks);
+        return;
+    }
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut row_elements: Vec<&Element> = Vec::new();
+    for child in element.elements() {
+        match child.tag.as_str() {
+            "tr" => row_elements.push(child),
+            "thead" | "tbody" | "tfoot" => {
+                row_elements.extend(child.elements().filter(|row| row.tag == "tr"));
+            }
+            "caption" => {
+                let caption = inline_text(child);
+                if !caption.is_empty() {
+                    blocks.push(caption);
+                }
+            }
+            _ => {}
+        }
+    }
+    for row in row_elements {
+        let cells: Vec<String> = row
+            .elements()
+            .filter(|cell| matches!(cell.tag.as_str(), "th" | "td"))
+            .map(|cell| inline_text(cell).replace('\n', " ").replace('|', "\\|"))
+            .collect();
+        if !cells.is_empty() {
+            rows.push(cells);
+        }
+    }
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    if columns == 0 {
+        return;
+    }
+    let mut lines = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let cells = (0..columns).map(|column| row.get(column).map_or("", String::as_str));
+        lines.push(format!("| {} |", cells.collect::<Vec<_>>().join(" | ")));
+        if i == 0 {
+            lines.push(format!("|{}", " --- |".repeat(columns)));
+        }
+    }
+    blocks.push(lines.join("\n"));
+}
+
+// The inline markdown of an element's content, on one line except for hard breaks.
+fn inline_text(element: &Element) -> String {
+    let mut text = String::new();
+    for child in &element.children {
+        match child {
+            Dom::Text(t) => text.push_str(&escape(t)),
+            Dom::Element(element) => inline(element, &mut text),
+        }
+    }
+    finish_inline(&text)
+}
+
+// Marks a `<br>` until whitespace is collapsed.
+const HARD_BREAK: char = '\u{1}';
+
+fn inline(element: &Element, text: &mut String) {
+    if element.is_boilerplate() {
+        return;
+    }
+    match element.tag.as_str() {
+        "br" => text.push(HARD_BREAK),
+        "img" => {
+            let Some(src) = element
+                .attribute("src")
+                .filter(|src| !src.is_empty() && !src.starts_with("data:"))
+                .or_else(|| element.attribute("data-src"))
+            else {
+                return;
+            };
+            let alt = collapse(element.attribute("alt").unwrap_or_default());
+            write!(text, "![{}]({})", escape(&alt), link_destination(src))
+                .expect("write to string");
+        }
+        "a" => {
+            let inner = inline_text(element);
+            match element.attribute("href") {
+                Some(href) if !href.is_empty() && !href.starts_with("javascript:") => {
+                    if !inner.is_empty() {
+                        write!(text, "[{inner}]({})", link_destination(href))
+                            .expect("write to string");
+                    }
+                }
+                _ => text.push_str(&inner),
+            }
+        }
+        "code" | "kbd" | "samp" | "tt" => {
+            let code = collapse(&element.text());
+            if !code.is_empty() {
+                let fence = "`".repeat(longest_run(&code, '`') + 1);
+                let padding = if code.starts_with('`') || code.ends_with('`') {
+                    " "
+                } else {
+                    ""
+                };
+                write!(text, "{fence}{padding}{code}{padding}{fence}").expect("write to string");
+            }
+        }
+        "strong" | "b" => emphasis(element, "**", text),
+        "em" | "i" | "cite" | "dfn" | "var" => emphasis(element, "*", text),
+        "del" | "s" | "strike" => emphasis(element, "~~", text),
+        _ => {
+            for child in &element.children {
+                match child {
+                    Dom::Text(t) => text.push_str(&escape(t)),
+                    Dom::Element(element) => inline(element, text),
+                }
+            }
+        }
+    }
+}
+
+// Wrap the content in `delimiter`, keeping the surrounding whitespace outside, where markdown
+// needs it.
+fn emphasis(element: &Element, delimiter: &str, text: &mut String) {
+    let mut inner = String::new();
+    for child in &element.children {
+        match child {
+            Dom::Text(t) => inner.push_str(&escape(t)),
+            Dom::Element(element) => inline(element, &mut inner),
+        }
+    }
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        text.push_str(&inner);
+        return;
+    }
+    if inner.starts_with(char::is_whitespace) {
+        text.push(' ');
+    }
+    write!(text, "{delimiter}{trimmed}{delimiter}").expect("write to string");
+    if inner.ends_with(char::is_whitespace) {
+        text.push(' ');
+    }
+}
+
+fn link_destination(url: &str) -> String {
+    if url.contains([' ', '(', ')']) {
+        format!("<{url}>")
+    } else {
+        url.to_owned()
+    }
+}
+
+// Collapse whitespace like a browser, and turn the hard break markers into markdown.
+fn finish_inline(text: &str) -> String {
+    collapse(text)
+        .split(HARD_BREAK)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\\\n")
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn prefix_lines(text: &str, prefix: &str, empty_prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                empty_prefix.to_owned()
+            } else {
+                format!("{prefix}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in text.chars() {
+        if ch == c {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    longest
+}
+
+// Escape the text of the page, so that it is not read as markdown.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '~') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(rest.get(..start).unwrap_or_default());
+        let after = rest.get(start..).unwrap_or_default();
+        match after.find(';').filter(|end| *end <= 10) {
+            Some(end) => {
+                decoded.push_str(&decode_entity(after.get(..=end).unwrap_or_default()));
+                rest = after.get(end + 1..).unwrap_or_default();
+            }
+            None => {
+                decoded.push('&');
+                rest = after.get(1..).unwrap_or_default();
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+// An entity like `&amp;` or `&#8212;`, or itself if unknown.
+fn decode_entity(entity: &str) -> String {
+    let Some(name) = entity
+        .strip_prefix('&')
+        .and_then(|entity| entity.strip_suffix(';'))
+    else {
+        return entity.to_owned();
+    };
+    let decoded = if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => number.parse().ok(),
+        };
+        code.and_then(char::from_u32)
+    } else {
+        match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            "bull" => Some('•'),
+            "middot" => Some('·'),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "trade" => Some('™'),
+            "deg" => Some('°'),
+            "times" => Some('×'),
+            "euro" => Some('€'),
+            _ => None,
+        }
+    };
+    decoded.map_or_else(|| entity.to_owned(), String::from)
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use url::Url;
+
+    use super::to_markdown;
+
+    fn page_url() -> Url {
+        Url::parse("https://example.com/blog/post.html").unwrap()
+    }
+
+    #[test]
+    fn article() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head><title>Ignored &amp; unused</title><style>p { color: red }</style></head>
+<body>
+  <nav class="site-nav"><a href="/">Home</a> <a href="/blog">Blog</a></nav>
+  <div class="sidebar"><p>Subscribe to the newsletter, it is great, really, you will love it.</p></div>
+  <article class="post">
+    <h1>Frying   markdown</h1>
+    <p>Markdown is <em>fried</em> in the <b>terminal</b>, with <a href="../docs/guide.html">relative links</a>,
+      and images like <img src="img/logo.png" alt="the logo">.</p>
+    <p>Second line<br>after a break, and 1 &lt; 2 &mdash; with <code>code_span</code>.</p>
+    <h2 id="list">A list</h2>
+    <ul>
+      <li>One</li>
+      <li>Two
+        <ol start="3"><li>Three</li><li>Four</li></ol>
+      </li>
+    </ul>
+    <pre><code class="language-rust">fn main() {
+    println!("*hi*");
+}
+</code></pre>
+    <table>
+      <thead><tr><th>Key</th><th>Action</th></tr></thead>
+      <tbody><tr><td><code>q</code></td><td>Quit | exit</td></tr></tbody>
+    </table>
+    <blockquote><p>Quoted, with a comma, and more text to score.</p></blockquote>
+  </article>
+  <footer><p>Copyright, all rights reserved, nothing to see here at all.</p></footer>
+  <script>document.write("<p>nope</p>")</script>
+</body>
+</html>"#;
+        assert_eq!(
+            to_markdown(html, &page_url()).unwrap(),
+            r#"# Frying markdown
+
+Markdown is *fried* in the **terminal**, with [relative links](https://example.com/docs/guide.html), and images like ![the logo](https://example.com/blog/img/logo.png).
+
+Second line\
+after a break, and 1 \< 2 — with `code_span`.
+
+## A list
+
+- One
+- Two
+  3. Three
+  4. Four
+
+```rust
+fn main() {
+    println!("*hi*");
+}
+```
+
+| Key | Action |
+| --- | --- |
+| `q` | Quit \| exit |
+
+> Quoted, with a comma, and more text to score.
+"#
+        );
+    }
+
+    #[test]
+    fn title_without_h1() {
+        let html = "<html><head><title>Page title</title></head><body>\
+            <div id=\"content\"><p>Some content that is long enough to count, with commas, too.</p>\
+            </div></body></html>";
+        assert_eq!(
+            to_markdown(html, &page_url()).unwrap(),
+            "# Page title\n\nSome content that is long enough to count, with commas, too.\n"
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let html = "<html><body><main>\
+            <section><h2>One</h2><p>The first section, with a paragraph that is long enough.</p>\
+            <p>And another paragraph of the first section, to make it the larger one.</p></section>\
+            <section><h2>Two</h2><p>The second section, with a paragraph that is long enough.</p>\
+            </section></main></body></html>";
+        assert_eq!(
+            to_markdown(html, &page_url()).unwrap(),
+            "## One\n\nThe first section, with a paragraph that is long enough.\n\n\
+            And another paragraph of the first section, to make it the larger one.\n\n\
+            ## Two\n\nThe second section, with a paragraph that is long enough.\n"
+        );
+    }
+
+    #[test]
+    fn fragment() {
+        assert_eq!(
+            to_markdown(
+                "Just <i>some</i> text, and a <a href=\"javascript:void(0)\">button</a>",
+                &page_url()
+            )
+            .unwrap(),
+            "Just *some* text, and a button\n"
+        );
+    }
+
+    #[test]
+    fn relative_urls() {
+        let html = r##"<p><img src="img/a.png" alt="a"> <img src="../a.png" alt="b">
+            <img src="/a.png" alt="c"> <a href="other.html">other</a> <a href="#top">top</a>
+            <a href="https://elsewhere.org/x">x</a></p>"##;
+        assert_eq!(
+            to_markdown(html, &page_url()).unwrap(),
+            "![a](https://example.com/blog/img/a.png) ![b](https://example.com/a.png) \
+            ![c](https://example.com/a.png) [other](https://example.com/blog/other.html) \
+            [top](#top) [x](https://elsewhere.org/x)\n"
+        );
+    }
+
+    #[test]
+    fn base_element() {
+        let html = r#"<html><head><base href="/static/"></head><body><p><img src="a.png" alt="a"></p></body></html>"#;
+        assert_eq!(
+            to_markdown(html, &page_url()).unwrap(),
+            "![a](https://example.com/static/a.png)\n"
+        );
+    }
+}
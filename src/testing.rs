@@ -0,0 +1,47 @@
+// Fixtures shared by the tests of several modules.
+
+use std::{
+    io::{Read as _, Write as _},
+    net::TcpListener,
+    path::PathBuf,
+    thread,
+};
+
+// A path under the temp dir for the files of a test, unique to the test process.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mdfried_{name}_{}", std::process::id()))
+}
+
+// A local HTTP server, that answers each request with `respond` of the lowercased request.
+//
+// Returns the URL of `path` on the server.
+pub fn serve<F>(path: &str, respond: F) -> String
+where
+    F: Fn(&str) -> String + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}{path}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 1024];
+            let len = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..len]).to_lowercase();
+            // The client may have given up already, e.g. after a timeout.
+            let _: Result<(), _> = stream.write_all(respond(&request).as_bytes());
+        }
+    });
+    url
+}
+
+// An HTTP response with a `status` like "200 OK", `headers` like "ETag: \"v1\"", and `body`.
+pub fn response(status: &str, headers: &[&str], body: &str) -> String {
+    let headers: String = headers
+        .iter()
+        .map(|header| format!("{header}\r\n"))
+        .collect();
+    format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
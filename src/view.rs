@@ -1,3 +1,5 @@
+mod status_line;
+
 use unicode_width::UnicodeWidthStr as _;
 
 use ratatui::{
@@ -5,10 +7,13 @@ use ratatui::{
     layout::{Alignment, Constraint, Layout, Position, Rect, Size},
     style::{Color, Style, Stylize as _},
     text::{Line, Span},
-    widgets::{Block, BorderType, Paragraph, Widget},
+    widgets::{
+        Block, BorderType, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+        StatefulWidget as _, Widget,
+    },
 };
 
-use mdfrier::{Mapper as _, SourceContent, ratatui::Theme as _};
+use mdfrier::{BlockChange, Mapper as _, SourceContent, ratatui::Theme as _};
 use ratatui_image::{
     Image,
     sliced::{SignedPosition, SlicedImage},
@@ -16,11 +21,13 @@ use ratatui_image::{
 
 use crate::{
     big_text::BigText,
+    config::StickyHeader,
     cursor::{Cursor, CursorPointer},
     document::{LineExtra, SectionContent},
     links::Osc8Link,
     model::{InputQueue, Model},
     sources::{BuiltIn, DocumentSource},
+    view::status_line::format_status_line,
 };
 
 pub const WELCOME_LOGO_SIZE: (u16, u16) = (32, 8);
@@ -41,107 +48,48 @@ pub fn view(model: &Model, buf: &mut Buffer) -> Option<Position> {
         _ => None,
     };
 
-    let mut y: i32 = 0 - (model.scroll as i32);
-    for section in model.sections() {
-        if y + (section.height as i32) < 0 {
-            y += section.height as i32;
-            continue;
-        }
-        match &section.content {
-            SectionContent::Lines(lines) | SectionContent::Code(_, lines) => {
-                section_lines(
-                    lines,
-                    buf,
-                    &mut y,
-                    inner_area,
-                    model,
-                    &selected_url,
-                    section.id,
-                );
-            }
-            SectionContent::Image(_markdown_link, sliced_proto, _size, _max_size) => {
-                // TODO: just fix up inner_area at once
-                let mut inner_area = inner_area;
-                inner_area.height -= 1;
-                SlicedImage::new(sliced_proto, SignedPosition { x: 0, y: y as i16 })
-                    .render(inner_area, buf);
-                // Trailing blanks, or the lack thereof, are indicated by `section.height`.
-                // That is, if there is a trailing blank line, then `section.height = size.height + 1`.
-                y += section.height as i32;
-            }
-            SectionContent::ImagePlaceholder(_, lines) => {
-                for (line, _extras) in lines.iter() {
-                    if y < 0 {
-                        y += 1;
-                        continue; // skip this line.
-                    }
-                    let p = Paragraph::new(line.clone());
-                    render_lines(p, 1, y as u16, inner_area, buf);
-                    y += 1;
-                }
-            }
-            SectionContent::Header(text, tier, proto) => {
-                // Only render headers if fully in view
-                if y >= 0 && (y as u16) < inner_area.bottom() - 2 {
-                    if let Some(proto) = proto {
-                        let img = Image::new(proto);
-                        render_lines(img, section.height, y as u16, inner_area, buf);
-                    } else {
-                        let big_text = BigText::new(text, *tier, model.config.theme.header_color);
-                        render_lines(big_text, 2, y as u16, inner_area, buf);
-                    }
-                }
-                y += section.height as i32;
-            }
-            SectionContent::HeaderPlaceholder(_, _, lines) => {
-                for (line, _) in lines.iter() {
-                    if y < 0 {
-                        y += 1;
-                        continue; // skip this line.
-                    }
-                    let line = if let Some(header_color) = model.config.theme.header_color {
-                        line.clone().fg(header_color)
-                    } else {
-                        line.clone()
-                    };
-                    let p = Paragraph::new(line);
-                    render_lines(p, 1, y as u16, inner_area, buf);
-                    y += 1;
-                }
-                y += 1;
-            }
-        }
-        if y >= inner_area.height as i32 - 1 {
-            // Do not render into last line, nor beyond area.
-            break;
-        }
+    if model.image_viewer.is_none() {
+        document_sections(model, inner_area, buf, &selected_url);
     }
 
     let content_area = Rect {
         height: inner_area.height.saturating_sub(1),
         ..inner_area
     };
-    builtin_override_view(model, content_area, buf);
+    if model.image_viewer.is_some() {
+        render_image(model, content_area, buf);
+    } else {
+        builtin_override_view(model, content_area, buf);
+        diff_changes(model, content_area, buf);
+        changed_lines(model, content_area, buf);
+        sticky_header(model, content_area, buf);
+        slide_notes(model, content_area, buf);
+    }
 
     let status_line_y = inner_area.height - 1;
 
     let total = model.total_lines();
     let inner_h = model.inner_height();
     if total > 0 && inner_h > 0 {
-        let inner_h = inner_h as u32;
-        let total_pages = (total as u32).div_ceil(inner_h);
-        let at_end = model.scroll as u32 + inner_h >= total as u32;
-        let page = if at_end {
-            total_pages
-        } else {
-            model.scroll as u32 / inner_h + 1
-        };
-        let page_text = format!("{page}/{total_pages}");
-        let page_width = page_text.len() as u16;
-        let x = buf.area().width.saturating_sub(page_width);
-        Paragraph::new(page_text)
+        if model.config.scrollbar {
+            let mut state = ScrollbarState::new(total.saturating_sub(inner_h) as usize)
+                .position(model.scroll as usize)
+                .viewport_content_length(inner_h as usize);
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .track_style(Style::default().fg(Color::Indexed(237)))
+                .thumb_style(Style::default().fg(Color::DarkGray))
+                .render(Rect::new(0, 0, buf.area().width, inner_h), buf, &mut state);
+        }
+
+        let status_text = status_line_text(model, total, inner_h);
+        let status_width = (status_text.width() as u16).min(buf.area().width);
+        let x = buf.area().width.saturating_sub(status_width);
+        Paragraph::new(status_text)
+            .alignment(Alignment::Right)
             .fg(Color::DarkGray)
-            .render(Rect::new(x, status_line_y, page_width, 1), buf);
+            .render(Rect::new(x, status_line_y, status_width, 1), buf);
     }
 
     let mut cursor_position = None; // Position::from((0, buf.area.height - 1));
@@ -150,13 +98,28 @@ pub fn view(model: &Model, buf: &mut Buffer) -> Option<Position> {
         let width = line.width() as u16;
         let searchbar = Paragraph::new(line).fg(Color::Red);
         searchbar.render(Rect::new(0, status_line_y, width, 1), buf);
+    } else if model.input_queue == InputQueue::None
+        && let Some(viewer) = &model.image_viewer
+    {
+        let line = Line::from(vec![
+            Span::from(viewer.url.as_str()).fg(Color::DarkGray),
+            Span::from(format!(" {}%", viewer.zoom_percent())).fg(Color::Indexed(32)),
+        ]);
+        let width = line.width() as u16;
+        Paragraph::new(line).render(Rect::new(0, status_line_y, width, 1), buf);
     } else {
         match &model.input_queue {
             InputQueue::None => match &model.cursor {
                 Cursor::None => {}
-                Cursor::Links(_) => {
+                Cursor::Links(pointer) => {
                     let (fg, bg) = (Color::Indexed(15), Color::Indexed(32));
-                    let line = if model.config.theme.hide_urls()
+                    let line = if let Some(image) = model.selected_image(pointer) {
+                        // Image URLs are not in the text.
+                        Line::from(vec![
+                            Span::from("Image ").fg(bg),
+                            Span::from(image.url.as_str()).fg(fg).bg(bg),
+                        ])
+                    } else if model.config.theme.hide_urls()
                         && let Some(selected_url) = selected_url
                     {
                         let url_display = selected_url.as_ref().to_owned();
@@ -205,8 +168,12 @@ pub fn view(model: &Model, buf: &mut Buffer) -> Option<Position> {
                 searchbar.render(Rect::new(0, status_line_y, width, 1), buf);
                 cursor_position = Some(Position::from((width, buf.area.height - 1)));
             }
-            InputQueue::CursorPositioningCommands => {
-                let line = Line::from(Span::from("z").fg(Color::Indexed(32)));
+            InputQueue::CursorPositioningCommands | InputQueue::Bracket(_) => {
+                let prefix = match model.input_queue {
+                    InputQueue::Bracket(bracket) => bracket.to_string(),
+                    _ => "z".to_owned(),
+                };
+                let line = Line::from(Span::from(prefix).fg(Color::Indexed(32)));
                 let width = line.width() as u16;
                 let searchbar = Paragraph::new(line);
                 searchbar.render(Rect::new(0, status_line_y, width, 1), buf);
@@ -228,6 +195,239 @@ pub fn view(model: &Model, buf: &mut Buffer) -> Option<Position> {
     cursor_position
 }
 
+fn document_sections(
+    model: &Model,
+    inner_area: Rect,
+    buf: &mut Buffer,
+    selected_url: &Option<SourceContent>,
+) {
+    let mut y: i32 = model.top_offset() as i32 - (model.scroll as i32);
+    for section in model.sections() {
+        if y + (section.height as i32) < 0 {
+            y += section.height as i32;
+            continue;
+        }
+        match &section.content {
+            SectionContent::Lines(lines) | SectionContent::Code(_, lines) => {
+                section_lines(
+                    lines,
+                    buf,
+                    &mut y,
+                    inner_area,
+                    model,
+                    selected_url,
+                    section.id,
+                );
+            }
+            SectionContent::Image(_markdown_link, sliced_proto, size, _max_size) => {
+                // TODO: just fix up inner_area at once
+                let mut inner_area = inner_area;
+                inner_area.height -= 1;
+                SlicedImage::new(sliced_proto, SignedPosition { x: 0, y: y as i16 })
+                    .render(inner_area, buf);
+                if let Cursor::Links(pointer) = &model.cursor
+                    && pointer.id == section.id
+                {
+                    selected_image_marker(y, size.height, inner_area, buf);
+                }
+                // Trailing blanks, or the lack thereof, are indicated by `section.height`.
+                // That is, if there is a trailing blank line, then `section.height = size.height + 1`.
+                y += section.height as i32;
+            }
+            SectionContent::ImagePlaceholder(_, lines) => {
+                for (line, _extras) in lines.iter() {
+                    if y < 0 {
+                        y += 1;
+                        continue; // skip this line.
+                    }
+                    let p = Paragraph::new(line.clone());
+                    render_lines(p, 1, y as u16, inner_area, buf);
+                    y += 1;
+                }
+            }
+            SectionContent::Header(text, tier, proto) => {
+                // Only render headers if fully in view
+                if y >= 0 && (y as u16) < inner_area.bottom() - 2 {
+                    if let Some(proto) = proto {
+                        let img = Image::new(proto);
+                        render_lines(img, section.height, y as u16, inner_area, buf);
+                    } else {
+                        let big_text = BigText::new(text, *tier, model.config.theme.header_color);
+                        render_lines(big_text, 2, y as u16, inner_area, buf);
+                    }
+                }
+                y += section.height as i32;
+            }
+            SectionContent::HeaderPlaceholder(_, _, lines) => {
+                for (line, _) in lines.iter() {
+                    if y < 0 {
+                        y += 1;
+                        continue; // skip this line.
+                    }
+                    let line = if let Some(header_color) = model.config.theme.header_color {
+                        line.clone().fg(header_color)
+                    } else {
+                        line.clone()
+                    };
+                    let p = Paragraph::new(line);
+                    render_lines(p, 1, y as u16, inner_area, buf);
+                    y += 1;
+                }
+                y += 1;
+            }
+        }
+        if y >= inner_area.height as i32 - 1 {
+            // Do not render into last line, nor beyond area.
+            break;
+        }
+    }
+}
+
+// A bar left of the image selected with the link cursor, if there is room.
+fn selected_image_marker(y: i32, height: u16, area: Rect, buf: &mut Buffer) {
+    let Some(x) = area.x.checked_sub(1) else {
+        return;
+    };
+    let style = Style::new().fg(Color::Indexed(32));
+    for row in y.max(0)..(y + i32::from(height)).min(i32::from(area.height)) {
+        buf.set_string(x, area.y + row as u16, "▌", style);
+    }
+}
+
+// Mark the lines that changed from the diff base in the gutter, and highlight added lines.
+// Removed lines are already struck through.
+fn diff_changes(model: &Model, area: Rect, buf: &mut Buffer) {
+    for y in 0..area.height {
+        let Some(line) = (y + model.scroll).checked_sub(model.top_offset()) else {
+            continue;
+        };
+        let (marker, color, bg) = match model.line_change(line) {
+            Some(BlockChange::Added) => ("+", Color::Green, Some(Color::Indexed(22))),
+            Some(BlockChange::Removed) => ("-", Color::Red, None),
+            None => continue,
+        };
+        if let Some(x) = area.x.checked_sub(2) {
+            buf.set_string(x, area.y + y, marker, Style::new().fg(color));
+        }
+        if let Some(bg) = bg {
+            buf.set_style(
+                Rect::new(area.x, area.y + y, area.width, 1),
+                Style::new().bg(bg),
+            );
+        }
+    }
+}
+
+// Briefly highlight what changed after the watched file was reloaded.
+fn changed_lines(model: &Model, area: Rect, buf: &mut Buffer) {
+    let Some(lines) = model.changed_lines() else {
+        return;
+    };
+    let style = Style::new().bg(model.config.theme.link_bg());
+    for line in lines.clone() {
+        let Some(y) = (line + model.top_offset()).checked_sub(model.scroll) else {
+            continue;
+        };
+        if y >= area.height {
+            break;
+        }
+        buf.set_style(Rect::new(area.x, area.y + y, area.width, 1), style);
+    }
+}
+
+fn sticky_header(model: &Model, area: Rect, buf: &mut Buffer) {
+    let mut crumbs = model.sticky_breadcrumb();
+    let text = match model.config.sticky_header {
+        StickyHeader::Off => return,
+        StickyHeader::Innermost => match crumbs.pop() {
+            Some(text) => text,
+            None => return,
+        },
+        StickyHeader::Parents if crumbs.is_empty() => return,
+        StickyHeader::Parents => crumbs.join(" › "),
+    };
+    let mut style = Style::default().bold().bg(model.config.theme.code_bg());
+    if let Some(header_color) = model.config.theme.header_color {
+        style = style.fg(header_color);
+    }
+    Paragraph::new(text)
+        .style(style)
+        .render(Rect { height: 1, ..area }, buf);
+}
+
+fn slide_notes(model: &Model, area: Rect, buf: &mut Buffer) {
+    let Some(slides) = &model.slides else {
+        return;
+    };
+    if !slides.show_notes || slides.notes().is_empty() {
+        return;
+    }
+    let lines: Vec<Line> = slides
+        .notes()
+        .iter()
+        .flat_map(|note| note.lines())
+        .map(Line::from)
+        .collect();
+    let height = (lines.len() as u16 + 2).min(area.height / 2);
+    let notes_area = Rect {
+        y: area.bottom().saturating_sub(height),
+        height,
+        ..area
+    };
+    Clear.render(notes_area, buf);
+    Paragraph::new(lines)
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .title("Notes")
+                .fg(Color::DarkGray),
+        )
+        .render(notes_area, buf);
+}
+
+// The right-aligned part of the status line, from the `status_line` config format.
+fn status_line_text(model: &Model, total: u16, inner_h: u16) -> String {
+    let (total, inner_h) = (total as u32, inner_h as u32);
+    format_status_line(&model.config.status_line, |field| match field {
+        "name" => Some(
+            model
+                .document_source()
+                .map(|source| source.name())
+                .unwrap_or_default(),
+        ),
+        "breadcrumb" => Some(model.breadcrumb().join(" › ")),
+        "percent" => {
+            let max_scroll = total.saturating_sub(inner_h);
+            let percent = if max_scroll == 0 {
+                100
+            } else {
+                (model.scroll as u32).min(max_scroll) * 100 / max_scroll
+            };
+            Some(format!("{percent}%"))
+        }
+        "page" => {
+            if let Some(slides) = &model.slides {
+                let (slide, count) = slides.counter();
+                return Some(format!("{slide}/{count}"));
+            }
+            let total_pages = total.div_ceil(inner_h);
+            let at_end = model.scroll as u32 + inner_h >= total;
+            let page = if at_end {
+                total_pages
+            } else {
+                model.scroll as u32 / inner_h + 1
+            };
+            Some(format!("{page}/{total_pages}"))
+        }
+        "watch" => Some(if model.watcher.is_some() {
+            String::from("watching")
+        } else {
+            String::new()
+        }),
+        _ => None,
+    })
+}
+
 fn section_lines(
     lines: &[(Line<'static>, Vec<LineExtra>)],
     buf: &mut Buffer,
@@ -306,6 +506,22 @@ fn section_lines(
             }
         }
 
+        if let Cursor::Links(CursorPointer { id, index }) = &model.cursor
+            && section_id == *id
+        {
+            for (i, extra) in extras.iter().enumerate() {
+                if let LineExtra::Task { start, end } = extra
+                    && flat_index + i == *index
+                {
+                    let display_text = extract_line_content(line, *start, *end);
+                    let (task_overlay, width) =
+                        link_highlighted(highlight_style)(*start, *end, display_text, "");
+                    let area = Rect::new(inner_area.x + start, line_y, width, 1);
+                    task_overlay.render(area, buf);
+                }
+            }
+        }
+
         if let Cursor::Search(_, pointer) = &model.cursor {
             for (i, extra) in extras.iter().enumerate() {
                 if let LineExtra::SearchMatch(start, end, text) = extra {
@@ -459,9 +675,6 @@ fn builtin_override_view(model: &Model, inner_area: Rect, buf: &mut Buffer) {
         Some(DocumentSource::BuiltIn(BuiltIn::Welcome)) => {
             render_welcome(model, inner_area, buf);
         }
-        Some(DocumentSource::Image { .. }) => {
-            render_image(model, inner_area, buf);
-        }
         Some(DocumentSource::Pdf { .. }) => {
             render_pdf(model, inner_area, buf);
         }
@@ -570,7 +783,7 @@ fn render_pdf(model: &Model, inner_area: Rect, buf: &mut Buffer) {
 }
 
 fn render_image(model: &Model, inner_area: Rect, buf: &mut Buffer) {
-    let Some(proto) = &model.root_image_proto else {
+    let Some(proto) = model.image_viewer.as_ref().and_then(|v| v.proto.as_ref()) else {
         return;
     };
     let size = proto.size();
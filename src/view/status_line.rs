@@ -0,0 +1,72 @@
+// Expand a status line format string like `"{name} · {percent}"`.
+//
+// The literal text between two fields is only kept if both fields are non-empty, so that a format
+// like `"{breadcrumb} · {name}"` doesn't leave a dangling separator without a breadcrumb. Unknown
+// fields are kept verbatim.
+pub fn format_status_line<F>(format: &str, field: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::new();
+    let mut rest = format;
+    let mut leading = true;
+    let mut has_value = false;
+    while let Some((literal, after)) = rest.split_once('{') {
+        let Some((name, after)) = after.split_once('}') else {
+            break;
+        };
+        rest = after;
+        let value = field(name).unwrap_or_else(|| format!("{{{name}}}"));
+        if leading {
+            out.push_str(literal);
+            leading = false;
+        } else if has_value && !value.is_empty() {
+            out.push_str(literal);
+        }
+        if !value.is_empty() {
+            out.push_str(&value);
+            has_value = true;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn fields(name: &str) -> Option<String> {
+        match name {
+            "breadcrumb" => Some(String::new()),
+            "name" => Some("README.md".to_owned()),
+            "percent" => Some("42%".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn skips_separators_of_empty_fields() {
+        assert_eq!(
+            format_status_line("{breadcrumb} · {name} · {percent}", fields),
+            "README.md · 42%"
+        );
+        assert_eq!(
+            format_status_line("{name} · {breadcrumb} · {percent}", fields),
+            "README.md · 42%"
+        );
+        assert_eq!(
+            format_status_line("[{name} {breadcrumb}]", fields),
+            "[README.md]"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_fields() {
+        assert_eq!(
+            format_status_line("{nope} {percent} {", fields),
+            "{nope} 42% {"
+        );
+    }
+}
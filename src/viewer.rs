@@ -0,0 +1,176 @@
+use image::{DynamicImage, imageops::FilterType};
+use ratatui::layout::Size;
+use ratatui_image::{FontSize, Resize, picker::Picker};
+
+use crate::{Protocol, error::Error};
+
+// Each zoom step scales the image by this much, from fitting the screen up to `MAX_ZOOM` steps.
+const ZOOM_FACTOR: f32 = 1.5;
+const MAX_ZOOM: u8 = 8;
+// A pan step moves by this part of the shown image.
+const PAN_STEP: f32 = 0.25;
+
+// Fullscreen view of an image of the document, or of an image source, with zoom and pan.
+pub struct ImageViewer {
+    // The URL of the markdown link, or the path of an image source.
+    pub url: String,
+    pub zoom: u8,
+    pub view: View,
+    pub proto: Option<Protocol>,
+}
+
+// The part of an image that is shown, relative to the image size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub center: (f32, f32),
+    pub extent: (f32, f32),
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View {
+            center: (0.5, 0.5),
+            extent: (1.0, 1.0),
+        }
+    }
+}
+
+impl ImageViewer {
+    pub fn new(url: String) -> Self {
+        ImageViewer {
+            url,
+            zoom: 0,
+            view: View::default(),
+            proto: None,
+        }
+    }
+
+    // Zoom in, or out with negative `steps`, returns false if already at the limit.
+    pub fn zoom_by(&mut self, steps: i32) -> bool {
+        let zoom = (i32::from(self.zoom) + steps).clamp(0, i32::from(MAX_ZOOM)) as u8;
+        if zoom == self.zoom {
+            return false;
+        }
+        self.zoom = zoom;
+        true
+    }
+
+    // The zoom relative to fitting the screen.
+    pub fn zoom_percent(&self) -> u32 {
+        (ZOOM_FACTOR.powi(i32::from(self.zoom)) * 100.0).round() as u32
+    }
+
+    // Pan by `dx` and `dy` steps, returns false if already at the edge.
+    pub fn pan_by(&mut self, dx: i32, dy: i32) -> bool {
+        let View { center, extent } = self.view;
+        let center = (
+            clamp_center(center.0 + dx as f32 * PAN_STEP * extent.0, extent.0),
+            clamp_center(center.1 + dy as f32 * PAN_STEP * extent.1, extent.1),
+        );
+        if center == self.view.center {
+            return false;
+        }
+        self.view.center = center;
+        true
+    }
+}
+
+/// Crop and scale `image` to the part that is shown in `available` cells at `zoom`, around
+/// `center`.
+///
+/// Returns the protocol, and the view moved inside the image.
+pub fn render(
+    picker: &Picker,
+    image: &DynamicImage,
+    available: Size,
+    zoom: u8,
+    center: (f32, f32),
+) -> Result<(Protocol, View), Error> {
+    let (image, view) = crop(image, picker.font_size(), available, zoom, center);
+    let size = Resize::Fit(None).size_for(&image, picker.font_size(), available);
+    let proto = picker.new_protocol(image, size, Resize::Fit(None))?;
+    Ok((proto, view))
+}
+
+fn crop(
+    image: &DynamicImage,
+    font_size: FontSize,
+    available: Size,
+    zoom: u8,
+    center: (f32, f32),
+) -> (DynamicImage, View) {
+    let (width, height) = (image.width().max(1) as f32, image.height().max(1) as f32);
+    let available_width = f32::from(available.width) * f32::from(font_size.width);
+    let available_height = f32::from(available.height) * f32::from(font_size.height);
+    // Like `Resize::Fit`, small images are not scaled up to fit.
+    let fit = (available_width / width)
+        .min(available_height / height)
+        .min(1.0);
+    let scale = fit * ZOOM_FACTOR.powi(i32::from(zoom));
+    let extent = (
+        (available_width / (width * scale)).min(1.0),
+        (available_height / (height * scale)).min(1.0),
+    );
+    let center = (
+        clamp_center(center.0, extent.0),
+        clamp_center(center.1, extent.1),
+    );
+    let crop_width = ((extent.0 * width).round() as u32).max(1);
+    let crop_height = ((extent.1 * height).round() as u32).max(1);
+    let x = ((center.0 - extent.0 / 2.0) * width).round() as u32;
+    let y = ((center.1 - extent.1 / 2.0) * height).round() as u32;
+    let cropped = image.crop_imm(x, y, crop_width, crop_height);
+    let scaled = cropped.resize_exact(
+        ((crop_width as f32 * scale).round() as u32).max(1),
+        ((crop_height as f32 * scale).round() as u32).max(1),
+        FilterType::Lanczos3,
+    );
+    (scaled, View { center, extent })
+}
+
+// Keep the shown part inside the image.
+fn clamp_center(center: f32, extent: f32) -> f32 {
+    let half = extent / 2.0;
+    center.clamp(half, 1.0 - half)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, RgbaImage};
+    use ratatui::layout::Size;
+    use ratatui_image::FontSize;
+
+    use super::{ImageViewer, View, crop};
+
+    #[test]
+    fn zoom_and_pan() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(400, 200));
+        let font_size = FontSize::new(10, 20);
+        // 200x200 pixels.
+        let available = Size::new(20, 10);
+
+        // Fits the width.
+        let (fitted, view) = crop(&image, font_size, available, 0, (0.0, 0.0));
+        assert_eq!((fitted.width(), fitted.height()), (200, 100));
+        assert_eq!(view, View::default());
+
+        // 450x225 at 2 steps, of which the top left corner is shown.
+        let (zoomed, view) = crop(&image, font_size, available, 2, (0.0, 0.0));
+        assert_eq!((zoomed.width(), zoomed.height()), (200, 200));
+        assert_eq!(view.extent, (200.0 / 450.0, 200.0 / 225.0));
+        assert_eq!(view.center, (view.extent.0 / 2.0, view.extent.1 / 2.0));
+
+        let mut viewer = ImageViewer::new("a.png".to_owned());
+        assert!(!viewer.zoom_by(-1));
+        assert!(viewer.zoom_by(2));
+        // Not panned before the view of the zoomed image is known.
+        assert!(!viewer.pan_by(1, 0));
+        viewer.view = view;
+        assert!(viewer.pan_by(1, 0));
+        assert!(viewer.view.center.0 > view.center.0);
+        assert!(viewer.pan_by(-8, 0));
+        assert!(!viewer.pan_by(-1, 0));
+        assert!(viewer.zoom_by(100));
+        assert_eq!(viewer.zoom, super::MAX_ZOOM);
+    }
+}
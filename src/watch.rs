@@ -1,73 +1,408 @@
 use std::{
-    cell::Cell,
+    collections::{HashMap, HashSet},
     fs,
-    path::PathBuf,
-    sync::mpsc::Sender,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, mpsc::Sender},
+    thread::{self, JoinHandle},
     time::{Duration, SystemTime},
 };
 
 use notify::{RecommendedWatcher, RecursiveMode};
 use notify_debouncer_mini::{DebounceEventResult, Debouncer, new_debouncer};
 
-use crate::{Event, error::Error};
+use crate::{
+    Event,
+    document::SectionID,
+    error::Error,
+    sources::{SharedDocumentSource, refetch},
+};
 
-// Should take `tx: Sender<Event>` but that complains about some weird lifetime stuff.
-pub fn watch(
-    path: &PathBuf,
-    tx: Sender<Event>,
-    debounce_milliseconds: u64,
-) -> Result<Debouncer<RecommendedWatcher>, Error> {
-    let parent = path
-        .parent()
-        .ok_or(Error::Watch("cannot watch without path".to_owned()))?
-        .to_owned();
-    let filename = path
-        .file_name()
-        .ok_or(Error::Watch(
-            "could not get filename part of path".to_owned(),
-        ))?
-        .to_owned();
-
-    let mtime = Some(fs::metadata(path).and_then(|m| m.modified())?);
-    let last_mtime: Cell<Option<SystemTime>> = Cell::new(mtime);
-    let mtime_path = path.clone();
-
-    // I can't believe we need to do this mtime check!
-    // mtime resolution by platform heuristics:
-    // - NTFS (Windows): 100ns - fine
-    // - FAT32:          2s    - two quick saves could collapse
-    // - APFS (macOS):   1ns   - fine
-    // - HFS+ (old mac): 1s    - rare edge case
-    // - ext4 (Linux):   1ns   - fine
-    // - NFS/SMB:        varies, can lag on network mounts
-
-    let mut debouncer = new_debouncer(
-        Duration::from_millis(debounce_milliseconds),
-        move |res: DebounceEventResult| match res {
-            Ok(events) => {
-                let dominated = events.iter().any(|e| e.path.file_name() == Some(&filename));
-                if !dominated {
-                    return;
+// Watches the displayed document's file and its local images, see `Model::watch_source`.
+pub struct Watcher {
+    debouncer: Debouncer<RecommendedWatcher>,
+    // Watched files, with their last modification time.
+    files: Arc<Mutex<HashMap<PathBuf, Option<SystemTime>>>>,
+    // Files are watched by their parent directory, so that atomic saves (write to a temporary
+    // file, then rename) are picked up.
+    dirs: HashSet<PathBuf>,
+}
+
+impl Watcher {
+    pub fn new(tx: Sender<Event>, debounce_milliseconds: u64) -> Result<Watcher, Error> {
+        let files: Arc<Mutex<HashMap<PathBuf, Option<SystemTime>>>> = Arc::default();
+        let watched = files.clone();
+
+        // I can't believe we need to do this mtime check!
+        // mtime resolution by platform heuristics:
+        // - NTFS (Windows): 100ns - fine
+        // - FAT32:          2s    - two quick saves could collapse
+        // - APFS (macOS):   1ns   - fine
+        // - HFS+ (old mac): 1s    - rare edge case
+        // - ext4 (Linux):   1ns   - fine
+        // - NFS/SMB:        varies, can lag on network mounts
+
+        let debouncer = new_debouncer(
+            Duration::from_millis(debounce_milliseconds),
+            move |res: DebounceEventResult| match res {
+                Ok(events) => {
+                    let Ok(mut watched) = watched.lock() else {
+                        log::error!("watched files lock poisoned");
+                        return;
+                    };
+                    for event in events {
+                        let Some(last_mtime) = watched.get_mut(&event.path) else {
+                            continue;
+                        };
+                        let mtime = modified(&event.path);
+                        if mtime == *last_mtime {
+                            continue;
+                        }
+                        *last_mtime = mtime;
+                        log::warn!("watch mtime changed, Event::FileChanged({:?})", event.path);
+                        if let Err(err) = tx.send(Event::FileChanged(event.path)) {
+                            log::error!("Failed to send Event::FileChanged: {err}");
+                        }
+                    }
                 }
+                Err(err) => {
+                    log::error!("DebounceEventResult error: {err}");
+                }
+            },
+        )?;
+        Ok(Watcher {
+            debouncer,
+            files,
+            dirs: HashSet::new(),
+        })
+    }
 
-                let mtime = fs::metadata(&mtime_path).and_then(|m| m.modified()).ok();
-                if mtime == last_mtime.get() {
-                    return;
+    // Follow the displayed document, forgetting the previous document and its images.
+    pub fn set_document(&mut self, path: Option<&Path>) -> Result<(), Error> {
+        self.files.lock()?.clear();
+        for dir in self.dirs.drain() {
+            log::info!("notify stops watching {dir:?}");
+            if let Err(err) = self.debouncer.watcher().unwatch(&dir) {
+                // E.g. the directory was removed.
+                log::debug!("could not unwatch {dir:?}: {err}");
+            }
+        }
+        if let Some(path) = path {
+            self.add(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, path: &Path) -> Result<(), Error> {
+        let path = normalize(path);
+        let parent = path
+            .parent()
+            .ok_or(Error::Watch("cannot watch without path".to_owned()))?
+            .to_owned();
+        if !self.dirs.contains(&parent) {
+            log::info!("notify is watching {parent:?}");
+            self.debouncer
+                .watcher()
+                .watch(&parent, RecursiveMode::NonRecursive)?;
+            self.dirs.insert(parent);
+        }
+        let mtime = modified(&path);
+        self.files.lock()?.insert(path, mtime);
+        Ok(())
+    }
+}
+
+// Absolute path, as reported by notify for the watched directories.
+pub fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// Remote sources can't be watched, instead they are fetched every `interval`, and
+// `Event::SourceChanged` is sent when the text differs from the previous fetch.
+pub fn poll_remote(
+    document_source: SharedDocumentSource,
+    tx: Sender<Event>,
+    interval: Duration,
+    url_transform_command: Option<String>,
+) -> Result<JoinHandle<()>, Error> {
+    Ok(thread::Builder::new().name("poll".into()).spawn(move || {
+        let mut last = None;
+        loop {
+            thread::sleep(interval);
+            let Ok(source) = document_source.read() else {
+                log::error!("poll: document source lock poisoned");
+                return;
+            };
+            let text = match refetch(&source, url_transform_command.clone()) {
+                Ok(Some(text)) => text,
+                Ok(None) => {
+                    last = None;
+                    continue;
                 }
-                last_mtime.set(mtime);
-                log::warn!("watch mtime changed, Cmd::FileChanged");
-                if let Err(err) = tx.send(Event::FileChanged) {
-                    log::error!("Failed to send Cmd::FileChanged: {err}");
+                Err(err) => {
+                    log::warn!("poll: {err}");
+                    continue;
+                }
+            };
+            if let Some((last_source, last_text)) = &last
+                && *last_source == source
+                && *last_text != text
+            {
+                log::info!("poll: source changed, Event::SourceChanged");
+                if tx
+                    .send(Event::SourceChanged(source.clone(), text.clone()))
+                    .is_err()
+                {
+                    return;
                 }
             }
-            Err(err) => {
-                log::error!("DebounceEventResult error: {err}");
+            last = Some((source, text));
+        }
+    })?)
+}
+
+// A document section's position and plain text, see `Document::snapshot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionSnapshot {
+    pub id: SectionID,
+    // The markdown source byte range, if known.
+    pub source: Option<Range<usize>>,
+    pub y: u16,
+    pub height: u16,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct FollowChanges {
+    pub scroll: u16,
+    // The changed lines of the new document, if any.
+    pub changed: Option<Range<u16>>,
+}
+
+// Where to scroll after the watched file changed.
+//
+// The position stays anchored to the section at the top of the viewport. If the first changed
+// section is near the viewport, within a screen height, it is scrolled into view.
+pub fn follow_changes(
+    old: &[SectionSnapshot],
+    new: &[SectionSnapshot],
+    scroll: u16,
+    height: u16,
+) -> FollowChanges {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old.text == new.text)
+        .count();
+    if prefix == old.len() && prefix == new.len() {
+        return FollowChanges {
+            scroll,
+            changed: None,
+        };
+    }
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(old.len().min(new.len()) - prefix)
+        .take_while(|(old, new)| old.text == new.text)
+        .count();
+
+    // Sections after the change have moved by the difference in height of the changed sections.
+    let anchored = match old
+        .iter()
+        .position(|section| section.y + section.height > scroll)
+    {
+        Some(i) if i >= old.len() - suffix => {
+            let offset = scroll - old[i].y;
+            new[i + new.len() - old.len()].y + offset
+        }
+        _ => scroll,
+    };
+
+    let start = y_at(new, prefix);
+    let end = y_at(new, new.len() - suffix);
+    let near = anchored.saturating_sub(height)..anchored.saturating_add(height.saturating_mul(2));
+    let visible = start >= anchored && start < anchored.saturating_add(height);
+    let scroll = if near.contains(&start) && !visible {
+        start
+    } else {
+        anchored
+    };
+    FollowChanges {
+        scroll,
+        changed: (start < end).then_some(start..end),
+    }
+}
+
+// The y of the section at index `i`, or the end of the document.
+fn y_at(sections: &[SectionSnapshot], i: usize) -> u16 {
+    sections.get(i).map(|section| section.y).unwrap_or_else(|| {
+        sections
+            .last()
+            .map(|section| section.y + section.height)
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{RwLock, mpsc};
+
+    use url::Url;
+
+    use super::*;
+    use crate::{sources::DocumentSource, testing};
+
+    // One section per text, each `height` lines high.
+    fn snapshot(texts: &[&str], height: u16) -> Vec<SectionSnapshot> {
+        texts
+            .iter()
+            .zip(0..)
+            .map(|(text, i)| SectionSnapshot {
+                id: i.into(),
+                source: None,
+                y: i * height,
+                height,
+                text: (*text).to_owned(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_keeps_scroll() {
+        let old = snapshot(&["a", "b", "c"], 10);
+        let follow = follow_changes(&old, &old.clone(), 15, 10);
+        assert_eq!(
+            follow,
+            FollowChanges {
+                scroll: 15,
+                changed: None
+            }
+        );
+    }
+
+    #[test]
+    fn jump_to_nearby_change() {
+        let old = snapshot(&["a", "b", "c", "d"], 10);
+        let new = snapshot(&["a", "b", "c", "D"], 10);
+        let follow = follow_changes(&old, &new, 15, 10);
+        assert_eq!(
+            follow,
+            FollowChanges {
+                scroll: 30,
+                changed: Some(30..40)
+            }
+        );
+    }
+
+    #[test]
+    fn visible_change_keeps_scroll() {
+        let old = snapshot(&["a", "b", "c", "d"], 10);
+        let new = snapshot(&["a", "B", "c", "d"], 10);
+        let follow = follow_changes(&old, &new, 5, 10);
+        assert_eq!(
+            follow,
+            FollowChanges {
+                scroll: 5,
+                changed: Some(10..20)
             }
-        },
-    )?;
-    log::info!("notify is watching {path:?}");
-    debouncer
-        .watcher()
-        .watch(&parent, RecursiveMode::NonRecursive)?;
-    Ok(debouncer)
+        );
+    }
+
+    #[test]
+    fn anchored_after_far_insert() {
+        let texts = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let old = snapshot(&texts, 10);
+        let mut new = snapshot(&["inserted"], 10);
+        new.extend(
+            snapshot(&texts, 10)
+                .into_iter()
+                .map(|section| SectionSnapshot {
+                    y: section.y + 10,
+                    ..section
+                }),
+        );
+        // Reading in section "g", the insert at the top is far away.
+        let follow = follow_changes(&old, &new, 63, 10);
+        assert_eq!(
+            follow,
+            FollowChanges {
+                scroll: 73,
+                changed: Some(0..10)
+            }
+        );
+    }
+
+    #[test]
+    fn removed_section() {
+        let old = snapshot(&["a", "b", "c"], 10);
+        let new = snapshot(&["a", "c"], 10);
+        let follow = follow_changes(&old, &new, 25, 10);
+        // Anchored to "c", and the removal at y 10 is just above the viewport.
+        assert_eq!(
+            follow,
+            FollowChanges {
+                scroll: 10,
+                changed: None
+            }
+        );
+    }
+
+    #[test]
+    fn watch_linked_image() {
+        let dir = testing::temp_path("watch_test");
+        fs::create_dir_all(&dir).unwrap();
+        let document = dir.join("document.md");
+        let image = dir.join("image.png");
+        fs::write(&document, "![image](image.png)\n").unwrap();
+        fs::write(&image, "before").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = Watcher::new(tx, 10).unwrap();
+        watcher.set_document(Some(&document)).unwrap();
+        watcher.add(&image).unwrap();
+
+        fs::write(&image, "after").unwrap();
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(event, Event::FileChanged(path) if path == normalize(&image)));
+
+        // Navigating away forgets the previous document and its images, and their directory.
+        watcher.set_document(None).unwrap();
+        assert!(watcher.dirs.is_empty(), "{:?} still watched", watcher.dirs);
+        fs::write(&image, "again").unwrap();
+        rx.recv_timeout(Duration::from_millis(200)).unwrap_err();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn poll_remote_source() {
+        let body = Arc::new(Mutex::new(String::from("# Before\n")));
+        let server_body = body.clone();
+        let url = testing::serve("/doc.md", move |_| {
+            let body = server_body.lock().unwrap();
+            testing::response("200 OK", &["Content-Type: text/markdown"], &body)
+        });
+        let url = Url::parse(&url).unwrap();
+
+        let source = DocumentSource::HyperText { url };
+        let document_source = SharedDocumentSource(Arc::new(RwLock::new(source.clone())));
+        let (tx, rx) = mpsc::channel();
+        poll_remote(document_source, tx, Duration::from_millis(20), None).unwrap();
+
+        // Unchanged polls send nothing.
+        rx.recv_timeout(Duration::from_millis(200)).unwrap_err();
+
+        *body.lock().unwrap() = String::from("# After\n");
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(
+            matches!(event, Event::SourceChanged(changed, text) if changed == source && text == "# After\n")
+        );
+    }
 }
@@ -3,16 +3,21 @@
 //! Ideally, any intensive work *must* happen in the worker, to avoid locking up the main/UI thread
 //! as much as possible.
 //!
-//! For now this only happens for markdown parsing, and image loading, resizing, and encoding.
+//! For now this only happens for markdown parsing, image loading, resizing, and encoding, and the
+//! playback of animated images.
 //!
 //! For example, text search could benefit from running in the worker, but it's not clear how the
 //! text should then actually be shared.
+pub mod animation;
 pub mod highlighter;
 pub mod mermaid;
 pub mod sections;
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufWriter, Cursor, Write},
+    ops::Range,
     sync::{
         Arc,
         mpsc::{Receiver, Sender},
@@ -21,23 +26,31 @@ use std::{
 };
 
 use cosmic_text::fontdb::Database;
-use mdfrier::MdFrier;
+use image::{DynamicImage, ImageFormat};
+use mdfrier::{Changes, Line, MdDocument, MdFrier};
 use ratatui::layout::Size;
 use ratatui_image::{Resize, picker::Picker, sliced::SlicedProtocol};
 use reqwest::Client;
 use tokio::{runtime::Builder, sync::RwLock, task::JoinSet};
 
 use crate::{
-    Cmd, Event, Protocol, VERSION,
-    config::{Config, MermaidConfig},
+    Cmd, Event, Protocol,
+    config::{Config, MermaidConfig, RemoteImages},
     document::{
-        LineExtra, LinkReference, SectionContent, header_images, header_sections, image_section,
+        HEADER_ROW_COUNT, LineExtra, LinkReference, Section, SectionContent, SectionID,
+        SectionPatch, header_images, header_sections, image_section, image_size, is_image_cached,
+        load_image, resolve_link_references, text_hash,
     },
     error::Error,
+    export::{self, ExportFormat, Figure},
+    http,
     model::DocumentId,
-    setup::FontRenderer,
+    setup::{FontRenderer, export_font_renderer},
     sources::{SharedDocumentSource, open_source},
+    viewer,
+    watch::SectionSnapshot,
     worker::{
+        animation::Player,
         highlighter::Highlighter,
         sections::{SectionEvent, SectionIterator},
     },
@@ -61,24 +74,7 @@ pub fn worker_thread(
             .build()?;
         let result = runtime.block_on(async {
 
-            let builder = Client::builder().user_agent(format!(
-                "mdfried/{}",
-                VERSION.get().unwrap_or(&"unknown".to_owned())
-            ));
-
-            // Attempt to mitigate a test failure on darwin.
-            // `system-configuration` is traced to reqwest via:
-            //     cargo tree -i system-configuration --target all
-            // The tests shouldn't be doing any requests, so the client building here would be the
-            // most likely source of that panic.
-            // ```
-            // thread '<unnamed>' (103071) panicked at /nix/build/nix-5646-2352996470/mdfried-0.20.1-vendor/source-registry-0/system-configuration-0.5.1/src/dynamic_store.rs:154:1:
-            // Attempted to create a NULL object.
-            // ```
-            #[cfg(test)]
-            let builder = builder.no_proxy();
-
-            let client = Arc::new(RwLock::new(builder.build()?));
+            let client = Arc::new(RwLock::new(http::client()?));
 
             #[cfg(feature = "svg")]
             let fontdb = renderer
@@ -106,76 +102,167 @@ pub fn worker_thread(
             #[cfg(not(feature = "svg"))]
             let fontdb = None;
 
-            let highlighter = Arc::new(Highlighter::new(&config.theme));
+            let highlighter = match Highlighter::new(&config.theme, config.code_theme.as_ref()) {
+                Ok(highlighter) => highlighter,
+                Err(err) => {
+                    event_tx.send(Event::WorkerWarning(err))?;
+                    Highlighter::new(&config.theme, None)?
+                }
+            };
+            let highlighter = Arc::new(highlighter);
 
             // Specifically not a tokio Mutex, because we use it in spawn_blocking.
             let thread_renderer =
                 renderer.map(|renderer| Arc::new(std::sync::Mutex::new(renderer)));
             let thread_picker = Arc::new(picker);
+            let player = Arc::new(Player::default());
+            if config.max_animation_fps > 0 {
+                let player = player.clone();
+                let picker = thread_picker.clone();
+                let event_tx = event_tx.clone();
+                let max_fps = config.max_animation_fps;
+                tokio::spawn(async move {
+                    if let Err(err) = player.run(picker, event_tx, max_fps).await {
+                        log::debug!("animation player stopped: {err}");
+                    }
+                });
+            }
             let mut parser = MdFrier::new()?;
+            // The document that `parser` parsed last, and the hash of its text. Incremental
+            // reparses are relative to it, and the same text is only laid out again.
+            let mut parsed: Option<(u64, MdDocument)> = None;
+            // The image that is zoomed into last, decoded once for zooming and panning.
+            let mut viewed: Option<(String, Arc<DynamicImage>)> = None;
 
             for cmd in cmd_rx {
                 log::debug!("Cmd: {cmd}");
                 let result = async {
                     match cmd {
-                        Cmd::Parse(document_id, width, text, image_cache) => {
+                        Cmd::Parse(document_id, width, text, image_cache, previous, remote_images, diff_base) => {
                             event_tx.send(Event::NewDocument(document_id))?;
 
-                            let lines = parser.parse(width, &text, &config.theme)?;
-                            let mut section_iter = SectionIterator::new(lines, &config.theme);
+                            let hash = text_hash(&text);
+                            let previous_hash = parsed.as_ref().map(|(hash, _)| *hash);
+                            let (document, changes) = match parsed.take() {
+                                // E.g. after a resize.
+                                Some((parsed_hash, document)) if parsed_hash == hash => (document, Some(Changes::default())),
+                                _ => parser.reparse(&text)?,
+                            };
+                            // With a diff base, the removed blocks are laid out too.
+                            let diff = diff_base.map(|old| parser.diff(&old, &document)).transpose()?;
+                            let lines: Box<dyn Iterator<Item = Line>> = match &diff {
+                                Some(diff) => Box::new(diff.layout(width, &config.theme)),
+                                None => Box::new(document.layout(width, &config.theme)),
+                            };
+                            // Sections of the previous parse whose source is untouched by the
+                            // changes, keyed by their source range in the new text. If the parser
+                            // last saw a different document, every section is new.
+                            let mut unchanged: HashMap<Range<usize>, Vec<&SectionSnapshot>> = HashMap::new();
+                            if let Some(previous) = &previous
+                                && diff.is_none()
+                                && previous_hash == Some(previous.text_hash)
+                                && let Some(changes) = &changes {
+                                for snapshot in &previous.sections {
+                                    if let Some(source) = snapshot.source.clone().and_then(|source| changes.map_range(source)) {
+                                        unchanged.entry(source).or_default().push(snapshot);
+                                    }
+                                }
+                            }
+                            let first_id = previous.iter().flat_map(|previous| &previous.sections).map(|snapshot| snapshot.id + 1).max().unwrap_or_default();
+                            let mut patches = previous.is_some().then(Vec::new);
+                            let mut section_iter = SectionIterator::new(lines, &config.theme).starting_at(first_id);
                             let mut post_parse_events = Vec::new();
-                            for section in &mut section_iter {
-                                match &section.content {
-                                    SectionContent::Lines(lines) => {
-                                        for (_, extras) in lines {
-                                            for extra in extras {
-                                                if let LineExtra::Link { reference, .. } = extra
-                                                    && let LinkReference::ReferenceDefinition{ id, url } = reference {
-                                                        post_parse_events.push(SectionEvent::ReferenceDefinition{ id: id.clone(), url: url.clone() });
-                                                }
+                            let mut kept = HashSet::new();
+                            let mut section_id = None;
+                            while let Some(section) = section_iter.next() {
+                                let source = section_iter.source();
+                                // Definitions of kept sections are sent too, because the model
+                                // resolves all references again after each parse.
+                                if let SectionContent::Lines(lines) = &section.content {
+                                    for (_, extras) in lines {
+                                        for extra in extras {
+                                            if let LineExtra::Link { reference, .. } = extra
+                                                && let LinkReference::ReferenceDefinition{ id, url } = reference {
+                                                    post_parse_events.push(SectionEvent::ReferenceDefinition{ id: id.clone(), url: url.clone() });
                                             }
                                         }
-                                        event_tx.send(Event::Parsed(document_id, section))?;
                                     }
+                                }
+                                if let Some(patches) = &mut patches {
+                                    let text = section.content.plain_text();
+                                    let snapshot = unchanged.get_mut(&source.range).and_then(|snapshots| {
+                                        let i = snapshots.iter().position(|snapshot| snapshot.text == text)?;
+                                        Some(snapshots.remove(i))
+                                    });
+                                    if let Some(snapshot) = snapshot {
+                                        kept.insert(snapshot.id);
+                                        section_id = Some(snapshot.id);
+                                        patches.push(SectionPatch::Keep(snapshot.id, source));
+                                        continue;
+                                    }
+                                }
+                                section_id = Some(section.id);
+                                match &section.content {
+                                    SectionContent::Lines(_) => {}
                                     SectionContent::Code(language, lines) => {
                                         post_parse_events.push(SectionEvent::Code(section.id, language.clone(), lines.iter().map(|(line,_)| line.clone()).collect()));
-                                        event_tx.send(Event::Parsed(document_id, section))?;
                                     }
                                     SectionContent::Image(_, _,_,_) => {
                                         unreachable!("SectionIterator produced Image");
                                     }
+                                    SectionContent::ImagePlaceholder(_, _) if !config.load_images => {}
                                     SectionContent::ImagePlaceholder(link, lines) => {
-                                        let section_id = section.id;
-                                        let link = link.clone();
                                         let has_trailing_blank = lines.last().map(|(line,_)| line.spans.is_empty()).unwrap_or_default();
-                                        event_tx.send(Event::Parsed(document_id, section))?;
-                                        post_parse_events.push(SectionEvent::Image(section_id, link, has_trailing_blank));
+                                        post_parse_events.push(SectionEvent::Image(section.id, link.clone(), has_trailing_blank));
                                     },
                                     SectionContent::Header(_, _, _) => {
                                         if !config.theme.has_text_size_protocol.unwrap_or_default() {
                                             unreachable!("SectionIterator produced Header without text-size-protocol");
                                         }
-                                        event_tx.send(Event::Parsed(document_id, section))?;
                                     }
                                     SectionContent::HeaderPlaceholder(text,tier,_) => {
                                         if config.theme.has_text_size_protocol.unwrap_or_default() {
                                             unreachable!("SectionIterator produced HeaderPlaceholder with text-size-protocol");
                                         }
-                                        let section_id = section.id;
-                                        let text = text.clone();
-                                        let tier = *tier;
-                                        event_tx.send(Event::Parsed(document_id, section))?;
                                         if thread_renderer.is_some() {
-                                            post_parse_events.push(SectionEvent::Header(section_id, text, tier));
+                                            post_parse_events.push(SectionEvent::Header(section.id, text.clone(), *tier));
                                         }
                                     }
                                 }
+                                if let Some(patches) = &mut patches {
+                                    patches.push(SectionPatch::New(section, source));
+                                } else {
+                                    event_tx.send(Event::Parsed(document_id, section, source))?;
+                                }
                             }
-                            let section_id = section_iter.last_section_id();
                             drop(section_iter);
+                            parsed = Some((hash, document));
+                            if let Some(patches) = patches {
+                                event_tx.send(Event::Reparsed(document_id, patches))?;
+                            }
+                            // `Document::patch` moves loaded images of sections that were not
+                            // kept to new sections of the same image.
+                            if let Some(previous) = &previous {
+                                let mut moved: HashMap<&str, usize> = HashMap::new();
+                                for snapshot in &previous.sections {
+                                    if previous.images.contains(&snapshot.id) && !kept.contains(&snapshot.id) {
+                                        *moved.entry(snapshot.text.as_str()).or_default() += 1;
+                                    }
+                                }
+                                post_parse_events.retain(|event| {
+                                    if let SectionEvent::Image(_, link, _) = event
+                                        && let Some(count) = moved.get_mut(link.url.as_str())
+                                        && *count > 0 {
+                                        *count -= 1;
+                                        return false;
+                                    }
+                                    true
+                                });
+                            }
 
                             // Send cached images synchronously before ParseDone
                             let mut image_cache = image_cache.unwrap_or_default();
+                            let mut disk_cached_post_parse_events = Vec::new();
                             let mut uncached_post_parse_events = Vec::new();
                             for event in post_parse_events {
                                 match &event {
@@ -198,6 +285,9 @@ pub fn worker_thread(
                                                 log::debug!("image cache hit but different max width ({width}x{config_max_image_height} vs {max_size}): {size:?}, {}", link.url);
                                                 uncached_post_parse_events.push(event);
                                             }
+                                        } else if is_image_cached(&document_source, remote_images, &link.url) {
+                                            log::debug!("image disk cache hit: {}", link.url);
+                                            disk_cached_post_parse_events.push(event);
                                         } else {
                                             log::debug!("image cache miss: {}", link.url);
                                             uncached_post_parse_events.push(event);
@@ -227,13 +317,12 @@ pub fn worker_thread(
                                 }
                             }
 
-                            event_tx.send(Event::ParseDone(document_id, section_id, text))?;
-
-                            if !uncached_post_parse_events.is_empty() {
+                            let process = async |events| {
                                 process_post_parse_events(
                                     event_tx.clone(),
                                     document_source.clone(),
                                     client.clone(),
+                                    remote_images,
                                     thread_picker.clone(),
                                     thread_renderer.clone(),
                                     fontdb.clone(),
@@ -242,15 +331,32 @@ pub fn worker_thread(
                                     &config,
                                     deep_fry,
                                     document_id,
-                                    uncached_post_parse_events,
-                                ).await?;
+                                    events,
+                                    player.clone(),
+                                ).await
+                            };
+                            // Images in the HTTP cache are decoded before ParseDone, like cached
+                            // protocols, instead of showing their placeholder first.
+                            if !disk_cached_post_parse_events.is_empty() {
+                                process(disk_cached_post_parse_events).await?;
                             }
+
+                            let changes = diff.map(|diff| diff.changes().clone()).unwrap_or_default();
+                            event_tx.send(Event::ParseDone(document_id, section_id, text, changes))?;
+
+                            if !uncached_post_parse_events.is_empty() {
+                                process(uncached_post_parse_events).await?;
+                            }
+                            event_tx.send(Event::LoadDone(document_id))?;
+                        }
+                        Cmd::Animate(document_id, sections) => {
+                            player.play(document_id, sections)?;
                         }
                         Cmd::OpenUrl(url) => {
                             let event_tx = event_tx.clone();
                             tokio::task::spawn_blocking(move || -> Result<(), Error> {
-                                if let Ok((text, _)) = open_source(&url, None) {
-                                    event_tx.send(Event::NewSourceContent(text))?;
+                                if let Ok((text, source)) = open_source(&url, None, true) {
+                                    event_tx.send(Event::NewSourceContent(source, text))?;
                                 }
                                 Ok(())
                             })
@@ -307,32 +413,101 @@ pub fn worker_thread(
                             })
                             .await??;
                         }
-                        Cmd::LoadImage(image) => {
+                        Cmd::LoadLogo => {
                             let event_tx = event_tx.clone();
                             let picker = thread_picker.clone();
                             tokio::task::spawn_blocking(move || -> Result<(), Error> {
-                                let resize = Resize::Fit(Some(ratatui_image::FilterType::Lanczos3));
-                                let (dyn_img, size) = if let Some((path, available)) = image {
-                                    let dyn_img = image::ImageReader::open(path)?.decode()?;
-                                    let size = resize.size_for(&dyn_img, picker.font_size(), available);
-                                    (dyn_img, size)
-                                } else {
-                                    let bytes = include_bytes!("../assets/logo.png");
-                                    (
-                                        image::ImageReader::with_format(std::io::Cursor::new(bytes), image::ImageFormat::Png).decode()?,
-                                        crate::view::WELCOME_LOGO_SIZE.into(),
-                                    )
-                                };
+                                let bytes = include_bytes!("../assets/logo.png");
+                                let dyn_img = image::ImageReader::with_format(Cursor::new(bytes), ImageFormat::Png).decode()?;
                                 let proto = picker.new_protocol(
                                     dyn_img,
-                                    size,
-                                    resize,
+                                    crate::view::WELCOME_LOGO_SIZE.into(),
+                                    Resize::Fit(Some(ratatui_image::FilterType::Lanczos3)),
                                 )?;
                                 event_tx.send(Event::RootImageLoaded(proto))?;
                                 Ok(())
                             })
                             .await??;
                         }
+                        Cmd::ViewImage(url, available, zoom, center, remote_images, reload) => {
+                            let dyn_img = match &viewed {
+                                Some((viewed_url, dyn_img)) if !reload && *viewed_url == url => dyn_img.clone(),
+                                _ => {
+                                    let dyn_img = Arc::new(load_image(
+                                        document_source.clone(),
+                                        client.clone(),
+                                        remote_images,
+                                        &url,
+                                        deep_fry,
+                                        fontdb.clone(),
+                                    )
+                                    .await?);
+                                    viewed = Some((url.clone(), dyn_img.clone()));
+                                    dyn_img
+                                }
+                            };
+                            let event_tx = event_tx.clone();
+                            let picker = thread_picker.clone();
+                            tokio::task::spawn_blocking(move || -> Result<(), Error> {
+                                let (proto, view) = viewer::render(&picker, &dyn_img, available, zoom, center)?;
+                                event_tx.send(Event::ImageViewed(url, proto, view))?;
+                                Ok(())
+                            })
+                            .await??;
+                        }
+                        Cmd::Export(width, text, format, path, remote_images) => {
+                            let title = document_source.read()?.name();
+                            // A separate parser, the incremental reparses stay relative to the
+                            // displayed document.
+                            let document = MdFrier::new()?.parse_document(&text)?;
+                            let format = format.with_renderer(|| match &thread_renderer {
+                                Some(renderer) => renderer.clone(),
+                                None => Arc::new(std::sync::Mutex::new(Box::new(
+                                    export_font_renderer(config.font_family.as_deref(), config.theme.header_color, thread_picker.font_size()),
+                                ))),
+                            });
+                            let mut theme = config.theme.clone();
+                            let font_renderer = match &format {
+                                ExportFormat::Html => thread_renderer.clone(),
+                                ExportFormat::Png(renderer) | ExportFormat::Svg(renderer) => {
+                                    // Images have no big text, so their headers are header images.
+                                    theme.has_text_size_protocol = Some(false);
+                                    Some(renderer.clone())
+                                }
+                            };
+                            let mut sections: Vec<Section> =
+                                SectionIterator::new(document.layout(width, &theme), &theme).collect();
+                            let figures = export_figures(
+                                &mut sections,
+                                document_source.clone(),
+                                client.clone(),
+                                remote_images,
+                                &thread_picker,
+                                font_renderer.clone(),
+                                fontdb.clone(),
+                                &highlighter,
+                                width,
+                                &config,
+                                deep_fry,
+                            ).await?;
+                            let mut out: Box<dyn Write> = match &path {
+                                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                                None => Box::new(io::stdout().lock()),
+                            };
+                            match format {
+                                ExportFormat::Html => export::html(&mut out, &title, width, &sections, &figures, &theme)?,
+                                ExportFormat::Png(font_renderer) => {
+                                    let mut font_renderer = font_renderer.lock()?;
+                                    export::png(&mut out, &mut font_renderer, width, &sections, &figures, &theme)?;
+                                }
+                                ExportFormat::Svg(font_renderer) => {
+                                    let font_renderer = font_renderer.lock()?;
+                                    export::svg(&mut out, &font_renderer.font_name, font_renderer.font_size, width, &sections, &figures, &theme)?;
+                                }
+                            }
+                            drop(out);
+                            event_tx.send(Event::Exported(path))?;
+                        }
                     }
 
                     Ok::<(),Error>(())
@@ -361,6 +536,7 @@ async fn process_post_parse_events(
     task_tx: Sender<Event>,
     document_source: SharedDocumentSource,
     client: Arc<RwLock<Client>>,
+    remote_images: RemoteImages,
     picker: Arc<Picker>,
     font_renderer: Option<Arc<std::sync::Mutex<Box<FontRenderer>>>>,
     fontdb: Option<Arc<Database>>,
@@ -370,9 +546,11 @@ async fn process_post_parse_events(
     deep_fry: bool,
     document_id: DocumentId,
     post_parse_events: Vec<SectionEvent>,
+    player: Arc<Player>,
 ) -> Result<(), Error> {
     // TODO: handle spawned task result errors, right now it's just logged and discarded.
     let config_max_image_height = config.max_image_height;
+    let animate = config.max_animation_fps > 0;
 
     let mut set: JoinSet<Result<(), Error>> = JoinSet::new();
     for event in post_parse_events {
@@ -384,6 +562,7 @@ async fn process_post_parse_events(
         let highlighter = highlighter.clone();
         let document_source = document_source.clone();
         let mermaid_config = config.mermaid.clone();
+        let player = player.clone();
 
         set.spawn(async move {
             match event {
@@ -396,19 +575,25 @@ async fn process_post_parse_events(
                         width,
                         document_source.clone(),
                         client.clone(),
+                        remote_images,
                         section_id,
                         link,
                         deep_fry,
                         fontdb.clone(),
+                        animate,
                     )
                     .await
                     {
-                        Ok(section) => {
+                        Ok((section, animation)) => {
                             let SectionContent::Image(link, protos, size, max_size) =
                                 section.content
                             else {
                                 unreachable!("image_section should return SectionContent::Image");
                             };
+                            let animated = animation.is_some();
+                            if player.set(&link.url, animation)? || animated {
+                                task_tx.send(Event::ImageAnimated(link.url.clone(), animated))?;
+                            }
                             task_tx.send(Event::ImageLoaded(
                                 document_id,
                                 section_id,
@@ -523,6 +708,141 @@ async fn process_post_parse_events(
     Ok(())
 }
 
+// Load the images, header images, diagrams and highlighted code of an export, like
+// `process_post_parse_events` does for the view. Failed images keep their placeholder.
+#[expect(clippy::too_many_arguments)]
+async fn export_figures(
+    sections: &mut [Section],
+    document_source: SharedDocumentSource,
+    client: Arc<RwLock<Client>>,
+    remote_images: RemoteImages,
+    picker: &Picker,
+    font_renderer: Option<Arc<std::sync::Mutex<Box<FontRenderer>>>>,
+    fontdb: Option<Arc<Database>>,
+    highlighter: &Highlighter,
+    width: u16,
+    config: &Config,
+    deep_fry: bool,
+) -> Result<HashMap<SectionID, Vec<Figure>>, Error> {
+    let mut definitions = Vec::new();
+    for section in sections.iter() {
+        if let SectionContent::Lines(lines) = &section.content {
+            for (_, extras) in lines {
+                for extra in extras {
+                    if let LineExtra::Link {
+                        reference: LinkReference::ReferenceDefinition { id, url },
+                        ..
+                    } = extra
+                    {
+                        definitions.push((format!("[{id}]"), url.clone()));
+                    }
+                }
+            }
+        }
+    }
+    for (id, url) in definitions {
+        resolve_link_references(sections, &id, &url);
+    }
+
+    let max_size = Size::new(width, config.max_image_height);
+    let mut figures = HashMap::new();
+    for section in sections.iter_mut() {
+        match &section.content {
+            SectionContent::ImagePlaceholder(link, _) => {
+                match load_image(
+                    document_source.clone(),
+                    client.clone(),
+                    remote_images,
+                    &link.url,
+                    deep_fry,
+                    fontdb.clone(),
+                )
+                .await
+                {
+                    Ok(dyn_img) => {
+                        let size = image_size(picker, &dyn_img, width, config.max_image_height);
+                        figures.insert(
+                            section.id,
+                            vec![Figure {
+                                image: dyn_img,
+                                size,
+                            }],
+                        );
+                    }
+                    Err(err) => log::warn!("export: image {}: {err}", link.url),
+                }
+            }
+            SectionContent::HeaderPlaceholder(text, tier, _) => {
+                let Some(font_renderer) = font_renderer.clone() else {
+                    continue;
+                };
+                let (text, tier) = (text.clone(), *tier);
+                let images = tokio::task::spawn_blocking(move || {
+                    let mut r = font_renderer.lock()?;
+                    header_images(&mut r, width, text, tier, deep_fry)
+                })
+                .await??;
+                let rows = images
+                    .into_iter()
+                    .map(|(_, _, image)| {
+                        let size = Resize::Fit(None).size_for(
+                            &image,
+                            picker.font_size(),
+                            Size::new(width, HEADER_ROW_COUNT),
+                        );
+                        Figure { image, size }
+                    })
+                    .collect();
+                figures.insert(section.id, rows);
+            }
+            SectionContent::Code(language, lines) => {
+                let lines: Vec<_> = lines.iter().map(|(line, _)| line.clone()).collect();
+                let diagram = match &config.mermaid {
+                    _ if language != "mermaid" => None,
+                    MermaidConfig::Disabled => None,
+                    #[cfg(feature = "mermaid")]
+                    MermaidConfig::Builtin => match &fontdb {
+                        Some(fontdb) => Some(
+                            mermaid::internal::image(&lines, width, fontdb.clone(), picker).await,
+                        ),
+                        None => None,
+                    },
+                    MermaidConfig::Command(cmd) => Some(mermaid::image_with_cmd(cmd, &lines).await),
+                };
+                match diagram {
+                    Some(Ok(dyn_img)) => {
+                        let size =
+                            Resize::Fit(None).size_for(&dyn_img, picker.font_size(), max_size);
+                        figures.insert(
+                            section.id,
+                            vec![Figure {
+                                image: dyn_img,
+                                size,
+                            }],
+                        );
+                        continue;
+                    }
+                    Some(Err(err)) => log::error!("{err}"),
+                    None => {}
+                }
+                match highlighter.fork().highlight(language, lines) {
+                    Ok(text) => {
+                        section.content = SectionContent::Lines(
+                            text.lines
+                                .into_iter()
+                                .map(|line| (line, Vec::new()))
+                                .collect(),
+                        );
+                    }
+                    Err(err) => log::error!("export: {err}"),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(figures)
+}
+
 #[derive(Default)]
 pub struct ImageCache {
     pub images: HashMap<String, (SlicedProtocol, Size, Size)>,
@@ -0,0 +1,304 @@
+//! Playback of animated GIF, APNG and WebP images.
+//!
+//! The frames are decoded with the image, and each frame is encoded for the terminal only when it
+//! is shown. Only the images in view play, as told by `Cmd::Animate`.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, mpsc::Sender},
+    time::{Duration, Instant},
+};
+
+use image::{DynamicImage, imageops::FilterType};
+use ratatui::layout::Size;
+use ratatui_image::{FontSize, Resize, picker::Picker, sliced::SlicedProtocol};
+use tokio::sync::Notify;
+
+use crate::{Event, document::SectionID, error::Error, model::DocumentId};
+
+// The rest of a longer animation is dropped.
+const MAX_ANIMATION_BYTES: usize = 64 << 20;
+// Above this, the animations that are not playing are dropped.
+const MAX_TOTAL_BYTES: usize = 256 << 20;
+// Like browsers do, shorter frame delays are taken as the usual default.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+pub struct Animation {
+    // Resized to the image section.
+    frames: Vec<(DynamicImage, Duration)>,
+    size: Size,
+    bytes: usize,
+}
+
+impl Animation {
+    /// Resize the frames to fit `size`, or `None` if there is only one frame.
+    pub fn new<I: Iterator<Item = (DynamicImage, Duration)>>(
+        frames: I,
+        font_size: FontSize,
+        size: Size,
+    ) -> Option<Animation> {
+        let width = u32::from(size.width) * u32::from(font_size.width);
+        let height = u32::from(size.height) * u32::from(font_size.height);
+        let mut resized = Vec::new();
+        let mut bytes = 0;
+        for (frame, delay) in frames {
+            let frame = frame.resize(width, height, FilterType::Triangle);
+            if bytes + frame.as_bytes().len() > MAX_ANIMATION_BYTES {
+                log::warn!("animation cut short at frame {}", resized.len());
+                break;
+            }
+            bytes += frame.as_bytes().len();
+            let delay = if delay < MIN_FRAME_DELAY {
+                DEFAULT_FRAME_DELAY
+            } else {
+                delay
+            };
+            resized.push((frame, delay));
+        }
+        (resized.len() > 1).then_some(Animation {
+            frames: resized,
+            size,
+            bytes,
+        })
+    }
+}
+
+/// The animations by image URL, and the ones that are playing.
+#[derive(Default)]
+pub struct Player {
+    state: Mutex<PlayerState>,
+    // Wakes up `run` when the playing images change.
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct PlayerState {
+    animations: HashMap<String, Arc<Animation>>,
+    // The frame that an animation stopped at, to continue from there.
+    positions: HashMap<String, usize>,
+    document_id: DocumentId,
+    playing: Vec<Playing>,
+}
+
+struct Playing {
+    section_id: SectionID,
+    url: String,
+    position: usize,
+    due: Instant,
+}
+
+impl Player {
+    /// Set or clear the animation of the image at `url`, e.g. after it was loaded again.
+    ///
+    /// Returns whether the image was animated before.
+    pub fn set(&self, url: &str, animation: Option<Animation>) -> Result<bool, Error> {
+        let mut state = self.state.lock()?;
+        state.positions.remove(url);
+        let had_animation = match animation {
+            Some(animation) => state
+                .animations
+                .insert(url.to_owned(), Arc::new(animation))
+                .is_some(),
+            None => state.animations.remove(url).is_some(),
+        };
+        let mut total: usize = state.animations.values().map(|a| a.bytes).sum();
+        if total > MAX_TOTAL_BYTES {
+            let PlayerState {
+                animations,
+                playing,
+                ..
+            } = &mut *state;
+            animations.retain(|other, animation| {
+                if total <= MAX_TOTAL_BYTES
+                    || other == url
+                    || playing.iter().any(|playing| &playing.url == other)
+                {
+                    return true;
+                }
+                total -= animation.bytes;
+                false
+            });
+        }
+        Ok(had_animation)
+    }
+
+    /// Play the animations of these sections, with their size, and stop all others.
+    pub fn play(
+        &self,
+        document_id: DocumentId,
+        sections: Vec<(SectionID, String, Size)>,
+    ) -> Result<(), Error> {
+        let now = Instant::now();
+        let mut state = self.state.lock()?;
+        let mut previous = std::mem::take(&mut state.playing);
+        for playing in &previous {
+            state
+                .positions
+                .insert(playing.url.clone(), playing.position);
+        }
+        if state.document_id != document_id {
+            previous.clear();
+        }
+        for (section_id, url, size) in sections {
+            // Otherwise it is from before a resize.
+            let Some(animation) = state.animations.get(&url).filter(|a| a.size == size) else {
+                continue;
+            };
+            let playing = match previous
+                .iter()
+                .position(|playing| playing.section_id == section_id && playing.url == url)
+            {
+                Some(i) => previous.swap_remove(i),
+                None => {
+                    let position = state.positions.get(&url).copied().unwrap_or_default();
+                    let delay = animation.frames.get(position).map(|(_, delay)| *delay);
+                    Playing {
+                        section_id,
+                        url,
+                        position,
+                        due: now + delay.unwrap_or(DEFAULT_FRAME_DELAY),
+                    }
+                }
+            };
+            state.playing.push(playing);
+        }
+        state.document_id = document_id;
+        drop(state);
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    /// Send the next frames of the playing animations as they are due, at most `max_fps` per
+    /// second each.
+    pub async fn run(
+        self: Arc<Self>,
+        picker: Arc<Picker>,
+        event_tx: Sender<Event>,
+        max_fps: u16,
+    ) -> Result<(), Error> {
+        let min_delay = Duration::from_secs(1) / u32::from(max_fps.max(1));
+        loop {
+            let due = self.state.lock()?.playing.iter().map(|p| p.due).min();
+            match due {
+                None => {
+                    self.changed.notified().await;
+                    continue;
+                }
+                Some(due) if due > Instant::now() => {
+                    tokio::select! {
+                        () = tokio::time::sleep_until(due.into()) => {}
+                        () = self.changed.notified() => continue,
+                    }
+                }
+                Some(_) => {}
+            }
+            for (document_id, section_id, url, frame, size) in self.advance(min_delay)? {
+                let picker = picker.clone();
+                let proto = match tokio::task::spawn_blocking(move || {
+                    SlicedProtocol::new_with_resize(&picker, frame, size, Resize::Fit(None))
+                })
+                .await?
+                {
+                    Ok(proto) => proto,
+                    Err(err) => {
+                        // The image stays at its last frame, and the others play on.
+                        log::warn!("could not encode animation frame of {url}: {err}");
+                        continue;
+                    }
+                };
+                event_tx.send(Event::AnimationFrame(document_id, section_id, url, proto))?;
+            }
+        }
+    }
+
+    // Step the due animations to their next frame.
+    #[expect(clippy::type_complexity)]
+    fn advance(
+        &self,
+        min_delay: Duration,
+    ) -> Result<Vec<(DocumentId, SectionID, String, DynamicImage, Size)>, Error> {
+        let now = Instant::now();
+        let mut state = self.state.lock()?;
+        let PlayerState {
+            animations,
+            document_id,
+            playing,
+            ..
+        } = &mut *state;
+        let mut frames = Vec::new();
+        for playing in playing.iter_mut().filter(|playing| playing.due <= now) {
+            let Some(animation) = animations.get(&playing.url) else {
+                // Dropped by `set`, wait for the next `play`.
+                playing.due = now + DEFAULT_FRAME_DELAY;
+                continue;
+            };
+            playing.position = (playing.position + 1) % animation.frames.len();
+            let (frame, delay) = &animation.frames[playing.position];
+            playing.due = now + (*delay).max(min_delay);
+            frames.push((
+                *document_id,
+                playing.section_id,
+                playing.url.clone(),
+                frame.clone(),
+                animation.size,
+            ));
+        }
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use ratatui::layout::Size;
+    use ratatui_image::FontSize;
+
+    use super::{Animation, Player};
+    use crate::model::DocumentId;
+
+    fn frames(count: u8) -> impl Iterator<Item = (DynamicImage, Duration)> {
+        (0..count).map(|i| {
+            let image = RgbaImage::from_pixel(40, 40, Rgba([i, 0, 0, 255]));
+            (DynamicImage::ImageRgba8(image), Duration::ZERO)
+        })
+    }
+
+    #[test]
+    fn plays_in_view() {
+        let font_size = FontSize::new(2, 4);
+        let size = Size::new(5, 5);
+        assert!(Animation::new(frames(1), font_size, size).is_none());
+
+        let animation = Animation::new(frames(3), font_size, size).unwrap();
+        // Resized to the cells, with the default delay.
+        assert_eq!(animation.frames[0].0.width(), 10);
+        assert_eq!(animation.frames[0].1, super::DEFAULT_FRAME_DELAY);
+
+        let player = Player::default();
+        assert!(!player.set("a.gif", Some(animation)).unwrap());
+        let document_id = DocumentId::default();
+        // Not at the size it was loaded for, e.g. before a resize.
+        player
+            .play(document_id, vec![(1, "a.gif".to_owned(), Size::new(9, 9))])
+            .unwrap();
+        assert!(player.state.lock().unwrap().playing.is_empty());
+
+        player
+            .play(document_id, vec![(1, "a.gif".to_owned(), size)])
+            .unwrap();
+        player.state.lock().unwrap().playing[0].due = Instant::now();
+        let frames = player.advance(Duration::ZERO).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].1, 1);
+
+        // Stopped and played again, it continues at the same frame.
+        player.play(document_id, Vec::new()).unwrap();
+        player
+            .play(document_id, vec![(2, "a.gif".to_owned(), size)])
+            .unwrap();
+        assert_eq!(player.state.lock().unwrap().playing[0].position, 1);
+    }
+}
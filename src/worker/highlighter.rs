@@ -1,5 +1,7 @@
+use std::fs;
+
 use ansi_to_tui::IntoText;
-use arborium::AnsiHighlighter;
+use arborium::{AnsiHighlighter, theme::builtin};
 use itertools::Itertools as _;
 use mdfrier::ratatui::Theme as _;
 use ratatui::{
@@ -7,7 +9,10 @@ use ratatui::{
     text::{Line, Text},
 };
 
-use crate::error::Error;
+use crate::{
+    config::{self, CodeTheme},
+    error::Error,
+};
 
 pub struct Highlighter {
     hl: AnsiHighlighter,
@@ -15,11 +20,17 @@ pub struct Highlighter {
 }
 
 impl Highlighter {
-    pub fn new(mdfried_theme: &crate::config::Theme) -> Self {
-        let theme = arborium::theme::builtin::tokyo_night().clone();
+    pub fn new(
+        mdfried_theme: &config::Theme,
+        code_theme: Option<&CodeTheme>,
+    ) -> Result<Self, Error> {
+        let theme = match code_theme {
+            None => builtin::tokyo_night().clone(),
+            Some(code_theme) => resolve_theme(code_theme, mdfried_theme.is_dark())?,
+        };
         let bg = mdfried_theme.code_bg();
         let hl = AnsiHighlighter::new(theme);
-        Self { hl, bg }
+        Ok(Self { hl, bg })
     }
 
     pub fn highlight(
@@ -57,6 +68,51 @@ impl Highlighter {
     }
 }
 
+fn resolve_theme(code_theme: &CodeTheme, is_dark: bool) -> Result<arborium::theme::Theme, Error> {
+    let name = match code_theme {
+        CodeTheme::Name(name) => name,
+        CodeTheme::Variants { dark, .. } if is_dark => dark,
+        CodeTheme::Variants { light, .. } => light,
+    };
+
+    if name.ends_with(".toml") {
+        let path = config::get_configuration_file_path()
+            .and_then(|path| path.parent().map(|dir| dir.join(name)))
+            .ok_or_else(|| Error::CodeHighlight(format!("no config directory for {name}")))?;
+        let toml = fs::read_to_string(&path)
+            .map_err(|err| Error::CodeHighlight(format!("{}: {err}", path.display())))?;
+        return Ok(arborium::theme::Theme::from_toml(&toml)?);
+    }
+
+    let wanted = normalize_name(name);
+    let themes = builtin::all();
+    if let Some(theme) = themes
+        .iter()
+        .find(|theme| normalize_name(&theme.name) == wanted)
+    {
+        return Ok(theme.clone());
+    }
+    // A family name like "gruvbox" picks the variant matching the document theme.
+    themes
+        .into_iter()
+        .filter(|theme| normalize_name(&theme.name).starts_with(&wanted))
+        .min_by_key(|theme| theme.is_dark != is_dark)
+        .ok_or_else(|| Error::CodeHighlight(format!("unknown code theme: {name}")))
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c == 'é' {
+                'e'
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .filter(char::is_ascii_alphanumeric)
+        .collect()
+}
+
 #[cfg(test)]
 #[expect(clippy::unwrap_used)]
 mod tests {
@@ -64,8 +120,8 @@ mod tests {
 
     #[test]
     fn highlight_languages() {
-        let theme = crate::config::Theme::default();
-        let mut hl = Highlighter::new(&theme);
+        let theme = config::Theme::default();
+        let mut hl = Highlighter::new(&theme, None).unwrap();
 
         let result = hl
             .highlight(
@@ -88,4 +144,28 @@ mod tests {
                 .any(|span| span.style.fg.is_some())
         );
     }
+
+    #[test]
+    fn resolve_theme_names() {
+        let theme = resolve_theme(&CodeTheme::Name("rose-pine-moon".to_owned()), true).unwrap();
+        assert_eq!(theme.name, "Rosé Pine Moon");
+
+        let theme = resolve_theme(&CodeTheme::Name("Gruvbox".to_owned()), false).unwrap();
+        assert_eq!(theme.name, "Gruvbox Light");
+
+        resolve_theme(&CodeTheme::Name("nope".to_owned()), true).unwrap_err();
+    }
+
+    #[test]
+    fn resolve_theme_variants() {
+        let variants = CodeTheme::Variants {
+            dark: "nord".to_owned(),
+            light: "solarized-light".to_owned(),
+        };
+        assert_eq!(resolve_theme(&variants, true).unwrap().name, "Nord");
+        assert_eq!(
+            resolve_theme(&variants, false).unwrap().name,
+            "Solarized Light"
+        );
+    }
 }
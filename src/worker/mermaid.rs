@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use image::DynamicImage;
 use mdfrier::MarkdownLink;
 use ratatui::{layout::Size, text::Line};
 use ratatui_image::{Resize, picker::Picker, sliced::SlicedProtocol};
@@ -15,6 +16,11 @@ pub async fn render_with_cmd(
     max_height: u16,
     picker: Arc<Picker>,
 ) -> Result<(SlicedProtocol, Size, Size, MarkdownLink), Error> {
+    let dyn_img = image_with_cmd(cmd, lines).await?;
+    sliced(dyn_img, width, max_height, picker).await
+}
+
+pub async fn image_with_cmd(cmd: &str, lines: &Vec<Line<'static>>) -> Result<DynamicImage, Error> {
     use std::io::Write as _;
     use std::process::{Command, Stdio};
 
@@ -23,10 +29,9 @@ pub async fn render_with_cmd(
         .map(|l| l.to_string())
         .collect::<Vec<_>>()
         .join("\n");
-    let max_size = Size::new(width, max_height);
 
     let cmd = cmd.to_owned();
-    let (sliced, size) = tokio::task::spawn_blocking(move || {
+    tokio::task::spawn_blocking(move || {
         let mut child = Command::new("sh")
             .arg("-c")
             .arg(&cmd)
@@ -43,7 +48,19 @@ pub async fn render_with_cmd(
         stdin.write_all(diagram.as_bytes())?;
 
         let output = child.wait_with_output()?;
-        let dyn_img = load_from_memory(&output.stdout)?;
+        Ok::<_, Error>(load_from_memory(&output.stdout)?)
+    })
+    .await?
+}
+
+async fn sliced(
+    dyn_img: DynamicImage,
+    width: u16,
+    max_height: u16,
+    picker: Arc<Picker>,
+) -> Result<(SlicedProtocol, Size, Size, MarkdownLink), Error> {
+    let max_size = Size::new(width, max_height);
+    let (sliced, size) = tokio::task::spawn_blocking(move || {
         let size = Resize::Fit(None).size_for(&dyn_img, picker.font_size(), max_size);
         let sliced = SlicedProtocol::new(&picker, dyn_img, Some(size))?;
         Ok::<_, Error>((sliced, size))
@@ -62,7 +79,6 @@ pub mod internal {
     use super::*;
     use crate::document::svg_tree_to_rgba;
     use cosmic_text::fontdb::Database;
-    use image::DynamicImage;
     use mermaid_rs_renderer::Theme;
 
     #[cfg(feature = "mermaid")]
@@ -73,27 +89,26 @@ pub mod internal {
         fontdb: Arc<Database>,
         picker: Arc<Picker>,
     ) -> Result<(SlicedProtocol, Size, Size, MarkdownLink), Error> {
+        let dyn_img = image(lines, width, fontdb, &picker).await?;
+        sliced(dyn_img, width, max_height, picker).await
+    }
+
+    #[cfg(feature = "mermaid")]
+    pub async fn image(
+        lines: &Vec<Line<'static>>,
+        width: u16,
+        fontdb: Arc<Database>,
+        picker: &Picker,
+    ) -> Result<DynamicImage, Error> {
         let diagram = lines
             .iter()
             .map(|l| l.to_string())
             .collect::<Vec<_>>()
             .join("\n");
         let max_width_px = width as f32 * picker.font_size().width as f32;
-        let max_size = Size::new(width, max_height);
-
-        let (sliced, size) = tokio::task::spawn_blocking(move || {
-            let dyn_img = render_image(&diagram, fontdb, max_width_px, None)?;
-            let size = Resize::Fit(None).size_for(&dyn_img, picker.font_size(), max_size);
-            let sliced = SlicedProtocol::new(&picker, dyn_img, Some(size))?;
-            Ok::<_, Error>((sliced, size))
-        })
-        .await??;
-
-        let link = MarkdownLink {
-            url: String::new(),
-            description: "mermaid".to_owned(),
-        };
-        Ok((sliced, size, max_size, link))
+
+        tokio::task::spawn_blocking(move || render_image(&diagram, fontdb, max_width_px, None))
+            .await?
     }
 
     #[cfg(feature = "mermaid")]
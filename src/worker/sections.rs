@@ -10,11 +10,14 @@ use std::iter::Peekable;
 
 use mdfrier::link_tracker::TrackedUrl;
 use mdfrier::ratatui::{Theme as _, render_line};
-use mdfrier::{Line, LineKind, MarkdownLink, Modifier, SourceContent};
+use mdfrier::{Line, LineKind, Mapper as _, MarkdownLink, Modifier, SourceContent};
 use ratatui::text::Span;
+use unicode_width::UnicodeWidthStr as _;
 
 use crate::config::Theme;
-use crate::document::{LineExtra, LinkReference, Section, SectionContent, SectionID};
+use crate::document::{
+    LineExtra, LinkReference, Section, SectionContent, SectionID, SectionSource,
+};
 
 /// Events produced during section iteration that need post-processing.
 pub enum SectionEvent {
@@ -40,6 +43,7 @@ pub struct SectionIterator<'a, I: Iterator<Item = Line>> {
     inner: Peekable<I>,
     theme: &'a Theme,
     section_id: usize,
+    source: SectionSource,
 }
 
 impl<'a, I: Iterator<Item = Line>> SectionIterator<'a, I> {
@@ -49,16 +53,20 @@ impl<'a, I: Iterator<Item = Line>> SectionIterator<'a, I> {
             inner: inner.peekable(),
             theme,
             section_id: 0,
+            source: SectionSource::default(),
         }
     }
 
-    /// Get the last section ID that was assigned (for ParseDone).
-    pub fn last_section_id(&self) -> Option<usize> {
-        if self.section_id == 0 {
-            None
-        } else {
-            Some(self.section_id - 1)
-        }
+    /// Start assigning section IDs at `section_id`, so that they don't collide with the IDs of
+    /// sections kept from a previous parse.
+    pub fn starting_at(mut self, section_id: SectionID) -> Self {
+        self.section_id = section_id;
+        self
+    }
+
+    /// The markdown source of the last section that was returned.
+    pub fn source(&self) -> SectionSource {
+        self.source.clone()
     }
 
     pub fn next_section_id(&mut self) -> SectionID {
@@ -67,6 +75,16 @@ impl<'a, I: Iterator<Item = Line>> SectionIterator<'a, I> {
         id
     }
 
+    /// Consume the next line that is aggregated into the current section.
+    fn next_line(&mut self) -> Line {
+        let line = self.inner.next().expect("peeked value should exist");
+        let range = &mut self.source.range;
+        range.start = range.start.min(line.source.start);
+        range.end = range.end.max(line.source.end);
+        self.source.lines.push(line.source.clone());
+        line
+    }
+
     /// Render a line to ratatui Line without links, for headers, images, or other non-text
     /// content.
     fn render_simple_line(&self, line: Line) -> ratatui::text::Line<'static> {
@@ -127,7 +145,7 @@ impl<'a, I: Iterator<Item = Line>> SectionIterator<'a, I> {
                 LineKind::Header(_) | LineKind::Image { .. } | LineKind::CodeBlock { .. } => break,
                 // Continue aggregating all other lines (including blanks)
                 _ => {
-                    let line = self.inner.next().expect("peeked value should exist");
+                    let line = self.next_line();
                     lines.push(line);
                 }
             }
@@ -157,11 +175,12 @@ impl<'a, I: Iterator<Item = Line>> SectionIterator<'a, I> {
                         None
                     };
 
+                let task = task_checkbox(&line, self.theme);
                 let (ratatui_line, urls) = render_line(line, self.theme);
 
-                let extras: Vec<LineExtra> = urls
+                let extras: Vec<LineExtra> = task
                     .into_iter()
-                    .filter_map(|tracked_url| {
+                    .chain(urls.into_iter().filter_map(|tracked_url| {
                         if let TrackedUrl::Link {
                             start,
                             lines,
@@ -192,7 +211,7 @@ impl<'a, I: Iterator<Item = Line>> SectionIterator<'a, I> {
                         } else {
                             None
                         }
-                    })
+                    }))
                     .collect();
 
                 (ratatui_line, extras)
@@ -222,7 +241,7 @@ impl<'a, I: Iterator<Item = Line>> SectionIterator<'a, I> {
                 LineKind::CodeBlock {
                     language: next_language,
                 } if *next_language == language => {
-                    let line = self.inner.next().expect("peeked value should exist");
+                    let line = self.next_line();
                     lines.push(to_line(line));
                 }
                 _ => {
@@ -249,6 +268,10 @@ impl<I: Iterator<Item = Line>> Iterator for SectionIterator<'_, I> {
         // Return buffered section if available
         loop {
             let first = self.inner.next()?;
+            self.source = SectionSource {
+                range: first.source.clone(),
+                lines: vec![first.source.clone()],
+            };
 
             match first.kind {
                 // Headers are always their own section
@@ -279,6 +302,27 @@ impl<I: Iterator<Item = Line>> Iterator for SectionIterator<'_, I> {
     }
 }
 
+// The checkbox of a task list item, at the end of its list marker.
+fn task_checkbox(line: &Line, theme: &Theme) -> Option<LineExtra> {
+    let mut column = 0;
+    for span in &line.spans {
+        let width = span.content.width();
+        if span.modifiers.contains(Modifier::ListMarker)
+            && let Some(mark) = [theme.task_checked(), theme.task_unchecked()]
+                .into_iter()
+                .find(|mark| span.content.ends_with(mark))
+        {
+            let start = column + width - mark.width();
+            return Some(LineExtra::Task {
+                start: start as u16,
+                end: (start + mark.trim_end().width()) as u16,
+            });
+        }
+        column += width;
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -399,6 +443,27 @@ mod tests {
         assert!(matches!(lines[0].1.as_slice(), [LineExtra::Link { .. }]),);
     }
 
+    #[test]
+    fn task_checkbox_is_selectable() {
+        let sections = parse_sections("- [ ] todo [link](https://example.org/)\n- plain\n");
+        let SectionContent::Lines(lines) = &sections[0].content else {
+            panic!("expected SectionContent::Lines");
+        };
+        let (line, extras) = &lines[0];
+        let [LineExtra::Task { start, end }, LineExtra::Link { .. }] = extras.as_slice() else {
+            panic!("expected a task and a link: {extras:?}");
+        };
+        let checkbox: String = line
+            .spans
+            .iter()
+            .flat_map(|span| span.content.chars())
+            .skip(usize::from(*start))
+            .take(usize::from(end - start))
+            .collect();
+        assert_eq!(checkbox, Theme::default().task_unchecked().trim_end());
+        assert!(lines[1].1.is_empty(), "plain items have no checkbox");
+    }
+
     #[test]
     fn md_link_with_code_block_parses_as_section_with_one_link() {
         let sections = parse_sections("[example `code`](https://example.org/)\n");